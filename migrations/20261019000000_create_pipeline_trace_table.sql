@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS pipeline_trace (
+    id BIGSERIAL PRIMARY KEY,
+    trace_id VARCHAR(64) NOT NULL,
+    slot BIGINT NOT NULL,
+    causing_tx VARCHAR(88),
+    dex_pair VARCHAR(64),
+    fired BOOLEAN NOT NULL,
+    last_step VARCHAR(128) NOT NULL,
+    total_duration_ms BIGINT NOT NULL,
+    trace JSONB NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_pipeline_trace_created_at ON pipeline_trace(created_at DESC);
+CREATE INDEX idx_pipeline_trace_dex_pair ON pipeline_trace(dex_pair);
@@ -1,14 +1,21 @@
+use crate::convention::chain::stamp::{Commitment, SlotStamp};
 use crate::sdk::yellowstone::GrpcAccountUpdate;
+use serde::{Deserialize, Serialize};
 use solana_program::pubkey::Pubkey;
 use std::time::Instant;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AccountState {
     pub pubkey: Pubkey,
     pub lamports: u64,
     pub data: Vec<u8>,
     pub owner: Pubkey,
     pub slot: u64,
+    pub write_version: u64,
+    pub source: u64,
+    pub txn_signature: Option<String>,
+    pub commitment: Commitment,
+    #[serde(skip, default = "Instant::now")]
     pub last_update: Instant,
 }
 
@@ -20,10 +27,18 @@ impl AccountState {
             data: update.data.clone(),
             owner: update.owner,
             slot: update.slot,
+            write_version: update.write_version,
+            source: update.source,
+            txn_signature: update.txn_signature.clone(),
+            commitment: update.commitment,
             last_update: Instant::now(),
         }
     }
 
+    pub fn stamp(&self) -> SlotStamp {
+        SlotStamp::new(self.slot, self.commitment)
+    }
+
     pub fn calculate_lamport_change(&self, previous: &AccountState) -> i64 {
         self.lamports as i64 - previous.lamports as i64
     }
@@ -35,4 +50,43 @@ impl AccountState {
     pub fn owner_changed(&self, previous: &AccountState) -> bool {
         self.owner != previous.owner
     }
+
+    // Write versions only grow per source, so they order two writes of the same slot
+    // from one stream. Across streams the first write seen for a slot is kept.
+    pub fn is_newer_than(&self, previous: &AccountState) -> bool {
+        if self.slot != previous.slot {
+            return self.slot > previous.slot;
+        }
+        self.source == previous.source && self.write_version > previous.write_version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(slot: u64, write_version: u64, source: u64) -> AccountState {
+        AccountState {
+            pubkey: Pubkey::default(),
+            lamports: 0,
+            data: vec![],
+            owner: Pubkey::default(),
+            slot,
+            write_version,
+            source,
+            txn_signature: None,
+            commitment: Commitment::Processed,
+            last_update: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_write_versions_only_order_writes_of_one_source() {
+        assert!(write(11, 1, 1).is_newer_than(&write(10, 9, 2)));
+        assert!(!write(10, 9, 2).is_newer_than(&write(11, 1, 1)));
+        assert!(write(10, 5, 1).is_newer_than(&write(10, 4, 1)));
+        assert!(!write(10, 4, 1).is_newer_than(&write(10, 5, 1)));
+        // another validator's counter says nothing, the first write seen stays
+        assert!(!write(10, 500, 2).is_newer_than(&write(10, 4, 1)));
+    }
 }
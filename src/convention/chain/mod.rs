@@ -4,6 +4,7 @@ pub mod mapper;
 pub mod message;
 pub mod meta;
 pub mod simulation;
+pub mod stamp;
 pub mod transaction;
 pub mod types;
 pub mod util;
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::commitment_config::CommitmentConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "processed" => Some(Commitment::Processed),
+            "confirmed" => Some(Commitment::Confirmed),
+            "finalized" => Some(Commitment::Finalized),
+            _ => None,
+        }
+    }
+
+    pub fn to_config(self) -> CommitmentConfig {
+        match self {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+// Slot and commitment a piece of cached state was observed at. For streamed state
+// this is the slot of the write, which stays current until the next write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotStamp {
+    pub slot: u64,
+    pub commitment: Commitment,
+}
+
+impl SlotStamp {
+    pub fn new(slot: u64, commitment: Commitment) -> Self {
+        Self { slot, commitment }
+    }
+
+    // True when both were written within `max_distance` slots of each other, whichever
+    // side is the older one.
+    pub fn is_close_to(&self, other: &SlotStamp, max_distance: u64) -> bool {
+        self.slot.abs_diff(other.slot) <= max_distance
+    }
+
+    // Processed state can still be dropped with its fork.
+    pub fn can_roll_back(&self) -> bool {
+        self.commitment == Commitment::Processed
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stamped<T> {
+    pub value: T,
+    pub stamp: SlotStamp,
+}
+
+impl<T> Stamped<T> {
+    pub fn new(value: T, stamp: SlotStamp) -> Self {
+        Self { value, stamp }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_close_to() {
+        let pool = SlotStamp::new(110, Commitment::Processed);
+        let reserve = SlotStamp::new(100, Commitment::Confirmed);
+
+        assert!(!reserve.is_close_to(&pool, 4));
+        assert!(reserve.is_close_to(&pool, 10));
+        // reserves far ahead of the pool state are just as inconsistent
+        assert!(!pool.is_close_to(&reserve, 4));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Commitment::parse(" Confirmed"), Some(Commitment::Confirmed));
+        assert_eq!(Commitment::parse("recent"), None);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::global::enums::dex_type::DexType;
+use serde::Serialize;
 use solana_program::pubkey::Pubkey;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SwapInstruction {
     pub dex_type: DexType,
     pub pool_address: Pubkey,
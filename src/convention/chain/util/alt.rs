@@ -1,19 +1,22 @@
 use crate::global::constant::duration::Interval;
-use crate::sdk::rpc::methods::account::buffered_get_account;
+use crate::sdk::rpc::methods::account::{buffered_get_account_in, BatchLane};
 use crate::util::cache::loading_cache::LoadingCache;
 use anyhow::Result;
 use futures::future::try_join_all;
 use once_cell::sync::Lazy;
+use solana_program::instruction::Instruction;
 use solana_sdk::address_lookup_table::state::AddressLookupTable;
 use solana_sdk::address_lookup_table::AddressLookupTableAccount;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
 
 #[allow(non_upper_case_globals)]
 pub static AltCache: Lazy<LoadingCache<Pubkey, AddressLookupTableAccount>> = Lazy::new(|| {
     LoadingCache::with_ttl(200, Interval::DAY, |key: &Pubkey| {
         let key = *key;
-        async move { fetch_alt(&key).await.ok() }
+        async move { fetch_alt(&key, BatchLane::Hot).await.ok() }
     })
+    .named("AltCache")
 });
 
 pub async fn get_alt(key: &Pubkey) -> Result<AddressLookupTableAccount> {
@@ -27,8 +30,66 @@ pub async fn get_alt_batch(alts: &[Pubkey]) -> Result<Vec<AddressLookupTableAcco
     try_join_all(alts.iter().map(get_alt)).await
 }
 
-async fn fetch_alt(key: &Pubkey) -> Result<AddressLookupTableAccount> {
-    let account = buffered_get_account(key)
+// Only non-signer accounts that are not invoked as programs can be loaded through
+// a lookup table, so those are the keys we try to cover. Greedy set cover: keep
+// picking the table that resolves the most still-inlined keys.
+pub fn select_minimal_alts(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    candidates: &[AddressLookupTableAccount],
+) -> Vec<AddressLookupTableAccount> {
+    let program_ids: HashSet<Pubkey> = instructions.iter().map(|ix| ix.program_id).collect();
+    let mut uncovered: HashSet<Pubkey> = instructions
+        .iter()
+        .flat_map(|ix| ix.accounts.iter())
+        .filter(|meta| !meta.is_signer && meta.pubkey != *payer)
+        .map(|meta| meta.pubkey)
+        .filter(|key| !program_ids.contains(key))
+        .collect();
+
+    let mut remaining: Vec<&AddressLookupTableAccount> = candidates.iter().collect();
+    let mut selected = Vec::new();
+
+    while !uncovered.is_empty() {
+        let best = remaining
+            .iter()
+            .enumerate()
+            .map(|(idx, alt)| {
+                let covered = alt
+                    .addresses
+                    .iter()
+                    .filter(|key| uncovered.contains(key))
+                    .count();
+                (idx, covered)
+            })
+            .max_by_key(|(_, covered)| *covered);
+
+        let Some((idx, covered)) = best else {
+            break;
+        };
+        if covered == 0 {
+            break;
+        }
+
+        let alt = remaining.swap_remove(idx);
+        for key in &alt.addresses {
+            uncovered.remove(key);
+        }
+        selected.push(alt.clone());
+    }
+
+    selected
+}
+
+// Replaces the cached copy, picking up addresses the table was extended with since.
+pub async fn refresh_alt(key: &Pubkey) -> Result<()> {
+    let alt = fetch_alt(key, BatchLane::Background).await?;
+    AltCache.put(*key, alt).await;
+    Ok(())
+}
+
+async fn fetch_alt(key: &Pubkey, lane: BatchLane) -> Result<AddressLookupTableAccount> {
+    let account = buffered_get_account_in(key, lane)
         .await
         .map_err(|e| anyhow::anyhow!("Failed to fetch ALT {}: {}", key, e))?;
 
@@ -100,6 +161,41 @@ mod tests {
         }
     }
 
+    fn alt_of(keys: &[Pubkey]) -> AddressLookupTableAccount {
+        AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: keys.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_select_minimal_alts() {
+        use solana_program::instruction::AccountMeta;
+
+        let payer = Pubkey::new_unique();
+        let accounts: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: std::iter::once(AccountMeta::new(payer, true))
+                .chain(accounts.iter().map(|key| AccountMeta::new(*key, false)))
+                .collect(),
+            data: vec![],
+        };
+
+        let wide = alt_of(&accounts[0..3]);
+        let narrow = alt_of(&accounts[0..1]);
+        let tail = alt_of(&accounts[3..4]);
+        let unrelated = alt_of(&[Pubkey::new_unique()]);
+
+        let selected = select_minimal_alts(
+            &payer,
+            &[ix],
+            &[narrow, unrelated, wide.clone(), tail.clone()],
+        );
+        let selected_keys: Vec<Pubkey> = selected.iter().map(|alt| alt.key).collect();
+        assert_eq!(selected_keys, vec![wide.key, tail.key]);
+    }
+
     #[tokio::test]
     async fn test_cache_invalidation() {
         let alt_key = "EyFCXwfjTjYAZz7pz1fwiQfRq8YPUKotSNyCeihHMWgZ".to_pubkey();
@@ -29,6 +29,20 @@ impl KvCacheRepository {
         Ok(result)
     }
 
+    pub async fn list(cache_type: CacheType) -> Result<Vec<KvCache>> {
+        let db = get_db().await;
+
+        let now = Utc::now();
+        let cache_type_column = CacheTypeColumn::from(cache_type);
+        let result = KvCacheTable::find()
+            .filter(model::Column::Type.eq(cache_type_column))
+            .filter(model::Column::ValidUntil.gt(now))
+            .all(db)
+            .await?;
+
+        Ok(result)
+    }
+
     pub async fn put(
         cache_type: CacheType,
         key: String,
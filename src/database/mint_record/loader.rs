@@ -2,7 +2,7 @@ use crate::database::mint_record::model::Model as MintRecord;
 #[allow(unused_imports)]
 use crate::global::constant::mint::Mints;
 use crate::global::constant::token_program::TokenProgram;
-use crate::sdk::rpc::methods::account::buffered_get_account_batch;
+use crate::sdk::rpc::methods::account::{buffered_get_account_batch, BatchLane};
 use crate::util::traits::orm::ToOrmString;
 use anyhow::Result;
 use borsh::BorshDeserialize;
@@ -19,7 +19,7 @@ pub async fn load_mint_from_address(mint: &Pubkey) -> Result<MintRecord> {
     let (metadata_pda, _) = Pubkey::find_program_address(metadata_seeds, &METADATA_PROGRAM_ID);
 
     let addresses = vec![*mint, metadata_pda];
-    let accounts = buffered_get_account_batch(&addresses)
+    let accounts = buffered_get_account_batch(&addresses, BatchLane::Background)
         .await
         .map_err(|e| anyhow::anyhow!("Failed to fetch accounts: {}", e))?;
 
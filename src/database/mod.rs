@@ -5,4 +5,5 @@ pub mod columns;
 pub mod kv_cache;
 pub mod mev_simulation_log;
 pub mod mint_record;
+pub mod pipeline_trace;
 pub mod pool_record;
@@ -0,0 +1,5 @@
+pub mod model;
+pub mod repository;
+pub use crate::database::pipeline_trace::model::Entity as PipelineTraceTable;
+pub use crate::database::pipeline_trace::model::Model as PipelineTrace;
+pub use crate::database::pipeline_trace::model::PipelineTraceParams;
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "pipeline_trace")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub trace_id: String,
+    pub slot: i64,
+    pub causing_tx: Option<String>,
+    pub dex_pair: Option<String>,
+    pub fired: bool,
+    pub last_step: String,
+    pub total_duration_ms: i64,
+    pub trace: JsonValue,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PipelineTraceParams {
+    pub trace_id: String,
+    pub slot: i64,
+    pub causing_tx: Option<String>,
+    pub dex_pair: Option<String>,
+    pub fired: bool,
+    pub last_step: String,
+    pub total_duration_ms: i64,
+    pub trace: JsonValue,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
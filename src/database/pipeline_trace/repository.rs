@@ -0,0 +1,64 @@
+use crate::database::pipeline_trace::{
+    model, PipelineTrace, PipelineTraceParams, PipelineTraceTable,
+};
+use crate::global::client::db::get_db;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+};
+
+pub struct PipelineTraceRepository;
+
+impl PipelineTraceRepository {
+    pub async fn insert_many(params: Vec<PipelineTraceParams>) -> Result<()> {
+        if params.is_empty() {
+            return Ok(());
+        }
+        let models = params.into_iter().map(|params| model::ActiveModel {
+            id: NotSet,
+            trace_id: Set(params.trace_id),
+            slot: Set(params.slot),
+            causing_tx: Set(params.causing_tx),
+            dex_pair: Set(params.dex_pair),
+            fired: Set(params.fired),
+            last_step: Set(params.last_step),
+            total_duration_ms: Set(params.total_duration_ms),
+            trace: Set(params.trace),
+            created_at: NotSet,
+        });
+
+        let db = get_db().await;
+        PipelineTraceTable::insert_many(models)
+            .exec_without_returning(db)
+            .await?;
+        Ok(())
+    }
+
+    // Newest first, at most `limit` rows.
+    pub async fn find_since(
+        since: DateTime<Utc>,
+        dex_pair: Option<&str>,
+        limit: u64,
+    ) -> Result<Vec<PipelineTrace>> {
+        let db = get_db().await;
+        let mut query = PipelineTraceTable::find().filter(model::Column::CreatedAt.gte(since));
+        if let Some(dex_pair) = dex_pair {
+            query = query.filter(model::Column::DexPair.eq(dex_pair));
+        }
+        let paginator = query
+            .order_by_desc(model::Column::CreatedAt)
+            .paginate(db, limit);
+        Ok(paginator.fetch_page(0).await?)
+    }
+
+    pub async fn delete_before(cutoff: DateTime<Utc>) -> Result<u64> {
+        let db = get_db().await;
+        let result = PipelineTraceTable::delete_many()
+            .filter(model::Column::CreatedAt.lt(cutoff))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
@@ -45,6 +45,7 @@ pub static PoolCachePrimary: Lazy<PersistentCache<PoolAddress, PoolRecord>> = La
             let _ = PoolRecordRepository::upsert_pool(record).await;
         },
     )
+    .named("PoolCachePrimary")
 });
 
 pub static PoolInDataBaseSecondary: Lazy<LoadingCache<PoolAddress, bool>> = Lazy::new(|| {
@@ -52,4 +53,5 @@ pub static PoolInDataBaseSecondary: Lazy<LoadingCache<PoolAddress, bool>> = Lazy
         let addr = *addr;
         async move { Some(PoolRecordRepository::find_by_address(addr).await.is_some()) }
     })
+    .named("PoolInDataBaseSecondary")
 });
@@ -90,6 +90,11 @@ impl PoolRecordRepository {
             .await?)
     }
 
+    pub async fn find_all() -> Result<Vec<PoolRecord>> {
+        let db = get_db().await;
+        Ok(PoolRecordEntity::find().all(db).await?)
+    }
+
     pub async fn find_by_address(address: Pubkey) -> Option<PoolRecord> {
         let db = get_db().await;
         PoolRecordEntity::find_by_id(PubkeyTypeString::from(address))
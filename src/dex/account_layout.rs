@@ -0,0 +1,151 @@
+use crate::global::constant::pool_program::PoolProgram;
+use crate::global::enums::dex_type::DexType;
+use crate::sdk::yellowstone::AccountFilter;
+use solana_program::pubkey::Pubkey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountKind {
+    Pool,
+    // Read by the quoting code, cached but never treated as a pool.
+    BinArray,
+}
+
+// Anchor account we stream from a DEX program. `size` is left out where the program
+// has resized the account over time and both layouts still exist on chain.
+#[derive(Debug, Clone, Copy)]
+pub struct AccountLayout {
+    pub owner: Pubkey,
+    pub kind: AccountKind,
+    pub discriminator: [u8; 8],
+    pub size: Option<u64>,
+}
+
+impl AccountLayout {
+    const fn new(owner: Pubkey, kind: AccountKind, discriminator: [u8; 8], size: u64) -> Self {
+        Self {
+            owner,
+            kind,
+            discriminator,
+            size: Some(size),
+        }
+    }
+
+    pub fn matches(&self, owner: &Pubkey, data: &[u8]) -> bool {
+        self.owner == *owner
+            && data.starts_with(&self.discriminator)
+            && self.size.is_none_or(|size| data.len() as u64 == size)
+    }
+
+    pub fn to_filter(&self) -> AccountFilter {
+        let dex_type = DexType::determine_from(&self.owner);
+        let filter = AccountFilter::new(format!("{}_{:?}", dex_type, self.kind))
+            .with_owner(&self.owner)
+            .with_memcmp(0, &self.discriminator);
+        match self.size {
+            Some(size) => filter.with_datasize(size),
+            None => filter,
+        }
+    }
+}
+
+pub const SUBSCRIBED_ACCOUNTS: &[AccountLayout] = &[
+    // LbPair
+    AccountLayout::new(
+        PoolProgram::METEORA_DLMM,
+        AccountKind::Pool,
+        [33, 11, 49, 98, 181, 101, 177, 13],
+        904,
+    ),
+    AccountLayout::new(
+        PoolProgram::METEORA_DLMM,
+        AccountKind::BinArray,
+        [92, 142, 92, 220, 5, 148, 70, 181],
+        10136,
+    ),
+    // Pool
+    AccountLayout::new(
+        PoolProgram::METEORA_DAMM_V2,
+        AccountKind::Pool,
+        [241, 154, 109, 4, 17, 177, 109, 188],
+        1112,
+    ),
+    // Pool, older pools are smaller than the current 300 bytes
+    AccountLayout {
+        owner: PoolProgram::PUMP_AMM,
+        kind: AccountKind::Pool,
+        discriminator: [241, 154, 109, 4, 17, 177, 109, 188],
+        size: None,
+    },
+    // PoolState
+    AccountLayout::new(
+        PoolProgram::RAYDIUM_CPMM,
+        AccountKind::Pool,
+        [247, 237, 227, 245, 215, 195, 222, 70],
+        637,
+    ),
+    // PoolState
+    AccountLayout::new(
+        PoolProgram::RAYDIUM_CLMM,
+        AccountKind::Pool,
+        [247, 237, 227, 245, 215, 195, 222, 70],
+        1544,
+    ),
+    // Whirlpool
+    AccountLayout::new(
+        PoolProgram::WHIRLPOOL,
+        AccountKind::Pool,
+        [63, 149, 209, 12, 225, 128, 99, 9],
+        653,
+    ),
+];
+
+pub fn subscribed_account_filters() -> Vec<AccountFilter> {
+    SUBSCRIBED_ACCOUNTS
+        .iter()
+        .map(AccountLayout::to_filter)
+        .collect()
+}
+
+pub fn account_kind(owner: &Pubkey, data: &[u8]) -> Option<AccountKind> {
+    SUBSCRIBED_ACCOUNTS
+        .iter()
+        .find(|layout| layout.matches(owner, data))
+        .map(|layout| layout.kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_data(discriminator: [u8; 8], size: usize) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        data.resize(size, 0);
+        data
+    }
+
+    #[test]
+    fn test_account_kind() {
+        let bin_array = account_data([92, 142, 92, 220, 5, 148, 70, 181], 10136);
+        assert_eq!(
+            account_kind(&PoolProgram::METEORA_DLMM, &bin_array),
+            Some(AccountKind::BinArray)
+        );
+
+        // same discriminator, told apart by the owner and size
+        let clmm_pool = account_data([247, 237, 227, 245, 215, 195, 222, 70], 1544);
+        assert_eq!(
+            account_kind(&PoolProgram::RAYDIUM_CLMM, &clmm_pool),
+            Some(AccountKind::Pool)
+        );
+        assert_eq!(account_kind(&PoolProgram::RAYDIUM_CPMM, &clmm_pool), None);
+
+        let old_pump_pool = account_data([241, 154, 109, 4, 17, 177, 109, 188], 243);
+        assert_eq!(
+            account_kind(&PoolProgram::PUMP_AMM, &old_pump_pool),
+            Some(AccountKind::Pool)
+        );
+
+        let tick_array = account_data([69, 97, 189, 190, 110, 7, 66, 187], 9988);
+        assert_eq!(account_kind(&PoolProgram::WHIRLPOOL, &tick_array), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::convention::chain::instruction::Instruction;
+use crate::convention::chain::stamp::{SlotStamp, Stamped};
 use crate::convention::chain::types::SwapInstruction;
 use crate::dex::any_pool_config::AnyPoolConfig::{
     MeteoraDammV2, MeteoraDlmm, PumpAmm, RaydiumClmm, RaydiumCpmm, Whirlpool,
@@ -11,7 +12,9 @@ use crate::dex::raydium_clmm::config::RaydiumClmmConfig;
 use crate::dex::raydium_cpmm::config::RaydiumCpmmConfig;
 use crate::dex::whirlpool::config::WhirlpoolConfig;
 use crate::global::enums::dex_type::DexType;
-use crate::global::state::account_balance_holder::get_balance_of_account;
+use crate::global::state::account_balance_holder::{
+    get_stamped_balance_of_account, refresh_balance_of_account,
+};
 use crate::pipeline::event_processor::token_balance::token_balance_processor::TokenAmount;
 use crate::return_error;
 use crate::util::alias::{AResult, MintAddress, PoolAddress};
@@ -83,20 +86,53 @@ impl AnyPoolConfig {
 }
 
 impl AnyPoolConfig {
-    pub async fn get_reserves(&self) -> (Option<TokenAmount>, Option<TokenAmount>) {
+    pub async fn get_reserves(
+        &self,
+    ) -> (Option<Stamped<TokenAmount>>, Option<Stamped<TokenAmount>>) {
         let base_reserve_addr = self.base_reserve_address();
         let quote_reserve_addr = self.quote_reserve_address();
         let base_mint = self.base_mint();
         let quote_mint = self.quote_mint();
 
         let (base_balance, quote_balance) = tokio::join!(
-            get_balance_of_account(&base_reserve_addr, &base_mint),
-            get_balance_of_account(&quote_reserve_addr, &quote_mint)
+            get_stamped_balance_of_account(&base_reserve_addr, &base_mint),
+            get_stamped_balance_of_account(&quote_reserve_addr, &quote_mint)
         );
 
         (base_balance, quote_balance)
     }
 
+    // Reserves written within `max_distance` slots of `pool_stamp`. Lagging reserves
+    // are reloaded once; if they are still apart, the pool is not quoted. Reserves of
+    // pools whose vaults are not streamed are never compared, their RPC slot says
+    // nothing about when the vault was last written.
+    pub async fn get_consistent_reserves(
+        &self,
+        pool_stamp: &SlotStamp,
+        max_distance: u64,
+    ) -> Option<(TokenAmount, TokenAmount)> {
+        let consistent = |base: &Stamped<TokenAmount>, quote: &Stamped<TokenAmount>| {
+            base.stamp.is_close_to(pool_stamp, max_distance)
+                && quote.stamp.is_close_to(pool_stamp, max_distance)
+        };
+
+        let (base, quote) = self.get_reserves().await;
+        let (base, quote) = (base?, quote?);
+        if !self.dex_type().streams_vault_balances() || consistent(&base, &quote) {
+            return Some((base.value, quote.value));
+        }
+
+        let (base_reserve, quote_reserve) =
+            (self.base_reserve_address(), self.quote_reserve_address());
+        let (base_mint, quote_mint) = (self.base_mint(), self.quote_mint());
+        let (base, quote) = tokio::join!(
+            refresh_balance_of_account(&base_reserve, &base_mint),
+            refresh_balance_of_account(&quote_reserve, &quote_mint)
+        );
+        let (base, quote) = (base?, quote?);
+        consistent(&base, &quote).then_some((base.value, quote.value))
+    }
+
     delegate! {
         to match self {
             MeteoraDlmm(a) => a,
@@ -1,5 +1,6 @@
 use rust_decimal::Decimal;
 
+pub mod account_layout;
 pub mod any_pool_config;
 pub mod interface;
 pub mod legacy_interface;
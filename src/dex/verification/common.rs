@@ -428,7 +428,7 @@ pub async fn simulate_raydium_cpmm_swap_and_get_balance_diff(
         Ok(rpc_response) => rpc_response,
         Err(error) => {
             println!("{}", error);
-            return Err(error.into());
+            return Err(error);
         }
     };
 
@@ -570,7 +570,7 @@ pub async fn simulate_raydium_clmm_swap_and_get_balance_diff(
         Ok(rpc_response) => rpc_response,
         Err(error) => {
             println!("Simulation error: {}", error);
-            return Err(error.into());
+            return Err(error);
         }
     };
 
@@ -758,7 +758,7 @@ pub async fn simulate_pump_amm_swap_and_get_balance_diff(
         Ok(rpc_response) => rpc_response,
         Err(error) => {
             println!("{}", error);
-            return Err(error.into());
+            return Err(error);
         }
     };
 
@@ -901,7 +901,7 @@ pub async fn simulate_whirlpool_swap_and_get_balance_diff(
         Ok(rpc_response) => rpc_response,
         Err(error) => {
             println!("Simulation error: {}", error);
-            return Err(error.into());
+            return Err(error);
         }
     };
 
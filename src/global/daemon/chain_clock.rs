@@ -0,0 +1,313 @@
+use crate::sdk::rpc::methods::block::{get_latest_blockhash_with_height, get_slot};
+use crate::sdk::yellowstone::{GrpcBlockMetaUpdate, GrpcSlotUpdate};
+use crate::sdk::yellowstone_multi::MultiGrpcClient;
+use crate::unit_ok;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use solana_sdk::clock::MAX_PROCESSING_AGE;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Builder;
+use tokio::sync::OnceCell;
+use tokio::time::interval;
+use yellowstone_grpc_proto::prelude::SlotStatus;
+
+const DEFAULT_SLOT_DURATION_MS: f64 = 400.0;
+// Roughly the average over the last 32 slots.
+const SLOT_DURATION_SMOOTHING: f64 = 1.0 / 32.0;
+const RECENT_SLOTS: usize = 256;
+// RPC polling takes over while the gRPC streams have been silent for this long.
+const STREAM_STALE_AFTER: Duration = Duration::from_secs(2);
+const RPC_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+// Where in the chain a moment falls: the newest slot seen and how far into it we are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotTime {
+    pub slot: u64,
+    pub offset_ms: u64,
+}
+
+struct ClockState {
+    slot: u64,
+    // When each recent slot was first observed locally.
+    slot_starts: BTreeMap<u64, Instant>,
+    slot_duration_ms: f64,
+    blockhash: Hash,
+    block_height: u64,
+    last_valid_block_height: u64,
+    last_stream_update: Option<Instant>,
+}
+
+impl ClockState {
+    fn new() -> Self {
+        Self {
+            slot: 0,
+            slot_starts: BTreeMap::new(),
+            slot_duration_ms: DEFAULT_SLOT_DURATION_MS,
+            blockhash: Hash::default(),
+            block_height: 0,
+            last_valid_block_height: 0,
+            last_stream_update: None,
+        }
+    }
+
+    fn on_slot(&mut self, slot: u64, at: Instant) {
+        if slot <= self.slot {
+            return;
+        }
+        if slot == self.slot + 1 {
+            if let Some(previous) = self.slot_starts.get(&self.slot) {
+                let observed = at.saturating_duration_since(*previous).as_secs_f64() * 1000.0;
+                self.slot_duration_ms +=
+                    (observed - self.slot_duration_ms) * SLOT_DURATION_SMOOTHING;
+            }
+        }
+        self.slot = slot;
+        self.slot_starts.insert(slot, at);
+        while self.slot_starts.len() > RECENT_SLOTS {
+            self.slot_starts.pop_first();
+        }
+    }
+
+    fn on_blockhash(&mut self, blockhash: Hash, block_height: u64, last_valid_block_height: u64) {
+        if block_height < self.block_height {
+            return;
+        }
+        self.blockhash = blockhash;
+        self.block_height = block_height;
+        self.last_valid_block_height = last_valid_block_height;
+    }
+
+    // Slots that were never observed are placed by the average slot duration.
+    fn slot_started_at(&self, slot: u64) -> Option<Instant> {
+        if let Some(started) = self.slot_starts.get(&slot) {
+            return Some(*started);
+        }
+        let current = *self.slot_starts.get(&self.slot)?;
+        let distance = Duration::from_secs_f64(
+            self.slot.abs_diff(slot) as f64 * self.slot_duration_ms / 1000.0,
+        );
+        if slot < self.slot {
+            current.checked_sub(distance)
+        } else {
+            current.checked_add(distance)
+        }
+    }
+
+    fn slot_time(&self, now: Instant) -> SlotTime {
+        let offset_ms = self
+            .slot_starts
+            .get(&self.slot)
+            .map(|started| now.saturating_duration_since(*started).as_millis() as u64)
+            .unwrap_or(0);
+        SlotTime {
+            slot: self.slot,
+            offset_ms,
+        }
+    }
+
+    fn stream_is_stale(&self, now: Instant) -> bool {
+        self.last_stream_update
+            .is_none_or(|at| now.saturating_duration_since(at) > STREAM_STALE_AFTER)
+    }
+}
+
+static CHAIN_CLOCK: Lazy<RwLock<ClockState>> = Lazy::new(|| RwLock::new(ClockState::new()));
+static FEEDS: OnceCell<()> = OnceCell::const_new();
+
+fn on_stream_slot(update: GrpcSlotUpdate) {
+    if update.status == SlotStatus::SlotDead {
+        return;
+    }
+    let now = Instant::now();
+    let mut clock = CHAIN_CLOCK.write();
+    clock.on_slot(update.slot, now);
+    clock.last_stream_update = Some(now);
+}
+
+fn on_stream_block_meta(update: GrpcBlockMetaUpdate) {
+    let now = Instant::now();
+    let mut clock = CHAIN_CLOCK.write();
+    clock.on_slot(update.slot, now);
+    clock.on_blockhash(
+        update.blockhash,
+        update.block_height,
+        update.block_height + MAX_PROCESSING_AGE as u64,
+    );
+    clock.last_stream_update = Some(now);
+}
+
+async fn poll_rpc() {
+    match get_slot(CommitmentConfig::processed()).await {
+        Ok(slot) => CHAIN_CLOCK.write().on_slot(slot, Instant::now()),
+        Err(e) => tracing::error!("Failed to fetch slot: {:?}", e),
+    }
+    match get_latest_blockhash_with_height(CommitmentConfig::processed()).await {
+        Ok((blockhash, last_valid_block_height)) => {
+            let block_height = last_valid_block_height.saturating_sub(MAX_PROCESSING_AGE as u64);
+            CHAIN_CLOCK
+                .write()
+                .on_blockhash(blockhash, block_height, last_valid_block_height);
+            tracing::trace!("Blockhash updated from RPC: {}", blockhash);
+        }
+        Err(e) => tracing::error!("Failed to fetch blockhash: {:?}", e),
+    }
+}
+
+fn start_stream_feeds() {
+    tokio::spawn(async {
+        let result = MultiGrpcClient::from_env()
+            .subscribe_slots(|update| async move {
+                on_stream_slot(update);
+                unit_ok!()
+            })
+            .await;
+        if let Err(e) = result {
+            tracing::error!("Chain clock slot stream stopped: {:?}", e);
+        }
+    });
+    tokio::spawn(async {
+        let result = MultiGrpcClient::from_env()
+            .subscribe_blocks_meta(|update| async move {
+                on_stream_block_meta(update);
+                unit_ok!()
+            })
+            .await;
+        if let Err(e) = result {
+            tracing::error!("Chain clock block meta stream stopped: {:?}", e);
+        }
+    });
+}
+
+fn start_rpc_fallback() {
+    // Spawn a dedicated OS thread so the fallback keeps running under a busy runtime
+    thread::Builder::new()
+        .name("chain-clock-rpc".to_string())
+        .spawn(move || {
+            tracing::info!("Chain clock RPC fallback thread started");
+
+            let runtime = Builder::new_current_thread()
+                .enable_all()
+                .thread_name("chain-clock-runtime")
+                .build()
+                .expect("Failed to create runtime for chain clock fallback");
+
+            runtime.block_on(async move {
+                let mut interval = interval(RPC_POLL_INTERVAL);
+
+                loop {
+                    interval.tick().await;
+                    if CHAIN_CLOCK.read().stream_is_stale(Instant::now()) {
+                        poll_rpc().await;
+                    }
+                }
+            });
+        })
+        .expect("Failed to spawn chain clock fallback thread");
+}
+
+async fn ensure_initialized() -> Result<()> {
+    FEEDS
+        .get_or_init(|| async {
+            // Fetch initial slot and blockhash
+            poll_rpc().await;
+
+            start_stream_feeds();
+            start_rpc_fallback();
+        })
+        .await;
+
+    Ok(())
+}
+
+pub async fn initialize() -> Result<()> {
+    ensure_initialized().await
+}
+
+pub async fn get_blockhash() -> Result<Hash> {
+    ensure_initialized().await?;
+    Ok(CHAIN_CLOCK.read().blockhash)
+}
+
+// Last block height at which the current blockhash is still accepted.
+pub fn last_valid_block_height() -> u64 {
+    CHAIN_CLOCK.read().last_valid_block_height
+}
+
+pub fn current_slot() -> u64 {
+    CHAIN_CLOCK.read().slot
+}
+
+pub fn slot_time() -> SlotTime {
+    CHAIN_CLOCK.read().slot_time(Instant::now())
+}
+
+pub fn slot_duration() -> Duration {
+    Duration::from_secs_f64(CHAIN_CLOCK.read().slot_duration_ms / 1000.0)
+}
+
+// Whether the gRPC slot streams are delivering, i.e. the Geyser provider is reachable.
+pub fn stream_is_live() -> bool {
+    !CHAIN_CLOCK.read().stream_is_stale(Instant::now())
+}
+
+// None until the clock has seen a slot.
+pub fn since_slot_start(slot: u64) -> Option<Duration> {
+    let started = CHAIN_CLOCK.read().slot_started_at(slot)?;
+    Some(Instant::now().saturating_duration_since(started))
+}
+
+// Gives up after `timeout` of wall time, in case the clock itself stopped moving.
+pub async fn wait_until_slot(target: u64, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while current_slot() < target && Instant::now() < deadline {
+        tokio::time::sleep(slot_duration() / 4).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_slot_duration_and_extrapolates_unseen_slots() {
+        let start = Instant::now();
+        let mut clock = ClockState::new();
+        clock.on_slot(100, start);
+        clock.on_slot(101, start + Duration::from_millis(400));
+        // older and repeated slots do not move the clock back
+        clock.on_slot(99, start + Duration::from_millis(500));
+        clock.on_slot(101, start + Duration::from_millis(600));
+
+        assert_eq!(clock.slot, 101);
+        assert_eq!(
+            clock.slot_time(start + Duration::from_millis(650)),
+            SlotTime {
+                slot: 101,
+                offset_ms: 250
+            }
+        );
+        assert_eq!(
+            clock.slot_started_at(103),
+            Some(start + Duration::from_millis(1200))
+        );
+        assert_eq!(clock.slot_started_at(100), Some(start));
+    }
+
+    #[test]
+    fn keeps_blockhash_of_highest_block() {
+        let mut clock = ClockState::new();
+        let newer = Hash::new_unique();
+        clock.on_blockhash(newer, 20, 170);
+        clock.on_blockhash(Hash::new_unique(), 19, 169);
+
+        assert_eq!(clock.blockhash, newer);
+        assert_eq!(clock.last_valid_block_height, 170);
+        assert!(clock.stream_is_stale(Instant::now()));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::global::trace::analytics;
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use crate::util::prometheus;
+use anyhow::Result;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::thread;
+use tokio::runtime::{Builder, Handle};
+use tracing::{error, info};
+
+async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        prometheus::render(&METRICS_REGISTRY),
+    )
+}
+
+#[derive(Deserialize)]
+struct LatencyQuery {
+    since_minutes: Option<i64>,
+    dex_pair: Option<String>,
+}
+
+// The database pool belongs to the main runtime, so the query runs there.
+async fn trace_latency(
+    State(main_runtime): State<Handle>,
+    Query(query): Query<LatencyQuery>,
+) -> Response {
+    let report = main_runtime
+        .spawn(async move {
+            analytics::latency_report(query.since_minutes.unwrap_or(60), query.dex_pair.as_deref())
+                .await
+        })
+        .await;
+    match report {
+        Ok(Ok(report)) => Json(report).into_response(),
+        Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+// Binds before returning so a taken port fails startup instead of going unnoticed.
+pub fn initialize() -> Result<()> {
+    let listener = std::net::TcpListener::bind(&ENV_CONFIG.metrics_addr)?;
+    listener.set_nonblocking(true)?;
+    let main_runtime = Handle::current();
+
+    // Spawn a dedicated OS thread so scrapes are answered under a busy runtime
+    thread::Builder::new()
+        .name("metrics-server".to_string())
+        .spawn(move || {
+            let runtime = Builder::new_current_thread()
+                .enable_all()
+                .thread_name("metrics-server-runtime")
+                .build()
+                .expect("Failed to create runtime for metrics server");
+
+            runtime.block_on(async move {
+                let listener = match tokio::net::TcpListener::from_std(listener) {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        error!("Metrics server failed to take over its listener: {}", e);
+                        return;
+                    }
+                };
+                let app = Router::new()
+                    .route("/metrics", get(metrics))
+                    .route("/traces/latency", get(trace_latency))
+                    .with_state(main_runtime);
+                if let Err(e) = axum::serve(listener, app).await {
+                    error!("Metrics server stopped: {}", e);
+                }
+            });
+        })?;
+
+    info!(
+        "Serving Prometheus metrics on http://{}/metrics",
+        ENV_CONFIG.metrics_addr
+    );
+    Ok(())
+}
@@ -1 +1,4 @@
-pub mod blockhash;
+pub mod chain_clock;
+pub mod metrics_server;
+pub mod priority_fee;
+pub mod state_snapshot;
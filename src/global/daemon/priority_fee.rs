@@ -0,0 +1,310 @@
+use crate::sdk::rpc::methods::fee::get_recent_prioritization_fees;
+use crate::util::alias::Lamport;
+use anyhow::Result;
+use parking_lot::RwLock;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Builder;
+use tokio::sync::OnceCell;
+use tokio::time::interval;
+
+// Used until the first sample arrives, matches the previous fixed unit price.
+const DEFAULT_UNIT_PRICE: Lamport = 30_000;
+const MAX_UNIT_PRICE: Lamport = 2_000_000;
+// Accounts not seen in a route for this long stop being sampled.
+const HOT_ACCOUNT_TTL: Duration = Duration::from_secs(120);
+// A per-account sample older than this is dropped and the global fee used instead.
+const ACCOUNT_FEE_TTL: Duration = Duration::from_secs(30);
+const MAX_ACCOUNTS_PER_ROUND: usize = 16;
+
+// Compute unit prices in micro-lamports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityFee {
+    pub p50: Lamport,
+    pub p75: Lamport,
+    pub p90: Lamport,
+}
+
+impl PriorityFee {
+    pub const DEFAULT: PriorityFee = PriorityFee {
+        p50: DEFAULT_UNIT_PRICE,
+        p75: DEFAULT_UNIT_PRICE,
+        p90: DEFAULT_UNIT_PRICE,
+    };
+
+    fn from_samples(mut samples: Vec<Lamport>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        Some(Self {
+            p50: percentile(&samples, 50),
+            p75: percentile(&samples, 75),
+            p90: percentile(&samples, 90),
+        })
+    }
+
+    fn max(self, other: PriorityFee) -> PriorityFee {
+        PriorityFee {
+            p50: self.p50.max(other.p50),
+            p75: self.p75.max(other.p75),
+            p90: self.p90.max(other.p90),
+        }
+    }
+
+    fn capped(self) -> PriorityFee {
+        PriorityFee {
+            p50: self.p50.min(MAX_UNIT_PRICE),
+            p75: self.p75.min(MAX_UNIT_PRICE),
+            p90: self.p90.min(MAX_UNIT_PRICE),
+        }
+    }
+}
+
+struct AccountFee {
+    fee: Option<PriorityFee>,
+    refreshed_at: Option<Instant>,
+    last_requested: Instant,
+}
+
+struct PriorityFeeHolder {
+    global: RwLock<Option<PriorityFee>>,
+    accounts: RwLock<HashMap<Pubkey, AccountFee>>,
+}
+
+impl PriorityFeeHolder {
+    fn new() -> Self {
+        Self {
+            global: RwLock::new(None),
+            accounts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn recommend(&self, writable_accounts: &[Pubkey]) -> PriorityFee {
+        let mut accounts = self.accounts.write();
+        let now = Instant::now();
+
+        let mut recommendation = *self.global.read();
+        for account in writable_accounts {
+            let entry = accounts.entry(*account).or_insert(AccountFee {
+                fee: None,
+                refreshed_at: None,
+                last_requested: now,
+            });
+            entry.last_requested = now;
+            if let Some(fee) = entry.fee {
+                recommendation = Some(match recommendation {
+                    Some(current) => current.max(fee),
+                    None => fee,
+                });
+            }
+        }
+
+        recommendation.unwrap_or(PriorityFee::DEFAULT).capped()
+    }
+
+    // Forgets cold accounts and stale samples, then picks the accounts to sample this
+    // round: never sampled ones first, then the least recently refreshed.
+    fn round_targets(&self) -> Vec<Pubkey> {
+        let mut accounts = self.accounts.write();
+        accounts.retain(|_, entry| entry.last_requested.elapsed() < HOT_ACCOUNT_TTL);
+        for entry in accounts.values_mut() {
+            if entry
+                .refreshed_at
+                .is_some_and(|at| at.elapsed() >= ACCOUNT_FEE_TTL)
+            {
+                entry.fee = None;
+            }
+        }
+
+        let mut targets: Vec<(Option<Instant>, Pubkey)> = accounts
+            .iter()
+            .map(|(key, entry)| (entry.refreshed_at, *key))
+            .collect();
+        targets.sort_unstable_by_key(|(refreshed_at, _)| *refreshed_at);
+        targets
+            .into_iter()
+            .take(MAX_ACCOUNTS_PER_ROUND)
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    // Samples each target on its own: passing several accounts at once would only
+    // report fees of transactions that locked all of them.
+    async fn refresh(&self) {
+        match sample(&[]).await {
+            Ok(fee) => *self.global.write() = fee,
+            Err(e) => tracing::warn!("Failed to sample global priority fees: {:?}", e),
+        }
+
+        for account in self.round_targets() {
+            match sample(&[account]).await {
+                Ok(fee) => {
+                    if let Some(entry) = self.accounts.write().get_mut(&account) {
+                        entry.fee = fee;
+                        entry.refreshed_at = Some(Instant::now());
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to sample priority fees of {}: {:?}", account, e);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn start_updater(self: &Arc<Self>) {
+        let holder = self.clone();
+
+        thread::Builder::new()
+            .name("priority-fee-updater".to_string())
+            .spawn(move || {
+                tracing::info!("Priority fee updater thread started");
+
+                let runtime = Builder::new_current_thread()
+                    .enable_all()
+                    .thread_name("priority-fee-runtime")
+                    .build()
+                    .expect("Failed to create runtime for priority fee updater");
+
+                runtime.block_on(async move {
+                    let mut interval = interval(Duration::from_secs(2));
+
+                    loop {
+                        interval.tick().await;
+                        holder.refresh().await;
+                    }
+                });
+            })
+            .expect("Failed to spawn priority fee updater thread");
+    }
+}
+
+async fn sample(accounts: &[Pubkey]) -> Result<Option<PriorityFee>> {
+    let fees = get_recent_prioritization_fees(accounts).await?;
+    Ok(PriorityFee::from_samples(
+        fees.into_iter().map(|f| f.prioritization_fee).collect(),
+    ))
+}
+
+fn percentile(sorted: &[Lamport], p: usize) -> Lamport {
+    let idx = (sorted.len() * p).div_ceil(100).saturating_sub(1);
+    sorted[idx.min(sorted.len() - 1)]
+}
+
+static GLOBAL_PRIORITY_FEE: OnceCell<Arc<PriorityFeeHolder>> = OnceCell::const_new();
+
+async fn ensure_initialized() -> Result<()> {
+    GLOBAL_PRIORITY_FEE
+        .get_or_init(|| async {
+            let holder = Arc::new(PriorityFeeHolder::new());
+            holder.start_updater();
+            holder
+        })
+        .await;
+
+    Ok(())
+}
+
+pub async fn initialize() -> Result<()> {
+    ensure_initialized().await
+}
+
+// Recommendation for a transaction write-locking `writable_accounts`; the accounts
+// are remembered and sampled by the updater from then on.
+pub async fn recommend_priority_fee(writable_accounts: &[Pubkey]) -> Result<PriorityFee> {
+    ensure_initialized().await?;
+    Ok(GLOBAL_PRIORITY_FEE
+        .get()
+        .unwrap()
+        .recommend(writable_accounts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let fee = PriorityFee::from_samples((1..=100).rev().collect()).unwrap();
+        assert_eq!(
+            fee,
+            PriorityFee {
+                p50: 50,
+                p75: 75,
+                p90: 90
+            }
+        );
+
+        let single = PriorityFee::from_samples(vec![7]).unwrap();
+        assert_eq!(single.p50, 7);
+        assert_eq!(single.p90, 7);
+
+        assert!(PriorityFee::from_samples(vec![]).is_none());
+    }
+
+    #[test]
+    fn test_recommendation_takes_hottest_account() {
+        let holder = PriorityFeeHolder::new();
+        let cold = Pubkey::new_unique();
+        let hot = Pubkey::new_unique();
+
+        // unknown accounts fall back to the default
+        assert_eq!(holder.recommend(&[cold, hot]), PriorityFee::DEFAULT);
+
+        holder.accounts.write().get_mut(&cold).unwrap().fee = Some(PriorityFee {
+            p50: 1_000,
+            p75: 2_000,
+            p90: 3_000,
+        });
+        holder.accounts.write().get_mut(&hot).unwrap().fee = Some(PriorityFee {
+            p50: 100_000,
+            p75: 200_000,
+            p90: 5_000_000,
+        });
+
+        assert_eq!(
+            holder.recommend(&[cold, hot]),
+            PriorityFee {
+                p50: 100_000,
+                p75: 200_000,
+                p90: MAX_UNIT_PRICE
+            }
+        );
+    }
+
+    #[test]
+    fn test_round_targets_oldest_refresh_first() {
+        let holder = PriorityFeeHolder::new();
+        let keys: Vec<Pubkey> = (0..MAX_ACCOUNTS_PER_ROUND + 2)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        holder.recommend(&keys);
+
+        let now = Instant::now();
+        {
+            let mut accounts = holder.accounts.write();
+            for (i, key) in keys.iter().enumerate() {
+                let entry = accounts.get_mut(key).unwrap();
+                entry.fee = Some(PriorityFee::DEFAULT);
+                entry.refreshed_at = Some(now - Duration::from_secs(i as u64));
+            }
+            // never sampled yet
+            accounts.get_mut(&keys[0]).unwrap().refreshed_at = None;
+            // stale sample
+            accounts.get_mut(&keys[1]).unwrap().refreshed_at = Some(now - ACCOUNT_FEE_TTL);
+        }
+
+        let targets = holder.round_targets();
+        assert_eq!(targets.len(), MAX_ACCOUNTS_PER_ROUND);
+        assert_eq!(targets[0], keys[0]);
+        assert_eq!(targets[1], keys[1]);
+        assert_eq!(targets[2], keys[keys.len() - 1]);
+        assert!(!targets.contains(&keys[2]));
+        assert!(holder.accounts.read()[&keys[1]].fee.is_none());
+        assert!(holder.accounts.read()[&keys[2]].fee.is_some());
+    }
+}
@@ -0,0 +1,359 @@
+use crate::convention::chain::stamp::{SlotStamp, Stamped};
+use crate::convention::chain::util::alt::{refresh_alt, AltCache};
+use crate::convention::chain::AccountState;
+use crate::dex::any_pool_config::AnyPoolConfig;
+use crate::global::daemon::chain_clock;
+use crate::global::state::account_balance_holder::{
+    restore_streamed_balance, revalidate_balance, streamed_balances,
+};
+use crate::global::state::account_data_holder::AccountDataHolder;
+use crate::global::state::any_pool_holder::AnyPoolHolder;
+use crate::lined_err;
+use crate::pipeline::event_processor::owner_account_debouncer::{
+    last_updates, restore_last_update,
+};
+use crate::pipeline::event_processor::token_balance::token_balance_processor::TokenAmount;
+use crate::util::alias::{AResult, MintAddress};
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::address_lookup_table::AddressLookupTableAccount;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::Builder;
+use tokio::time::interval;
+use tracing::{error, info, warn};
+
+// Bumped whenever a cached type changes shape, older files are then ignored.
+const SNAPSHOT_VERSION: u32 = 2;
+const REVALIDATE_CONCURRENCY: usize = 64;
+
+// Serializes writers, the periodic one can still be running when shutdown saves.
+static WRITING: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AltEntry {
+    key: Pubkey,
+    addresses: Vec<Pubkey>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct StateSnapshot {
+    version: u32,
+    taken_at_slot: u64,
+    pools: Vec<Stamped<AnyPoolConfig>>,
+    last_updates: Vec<AccountState>,
+    balances: Vec<((Pubkey, MintAddress), Stamped<TokenAmount>)>,
+    account_data: Vec<(Pubkey, Stamped<Vec<u8>>)>,
+    // Lookup tables are cached without a slot, they carry the one of the snapshot.
+    alts: Vec<Stamped<AltEntry>>,
+}
+
+impl StateSnapshot {
+    fn capture(taken_at_slot: u64) -> Self {
+        let stamp = SlotStamp::new(taken_at_slot, ENV_CONFIG.rpc_commitment);
+        Self {
+            version: SNAPSHOT_VERSION,
+            taken_at_slot,
+            pools: AnyPoolHolder::entries(),
+            last_updates: last_updates(),
+            balances: streamed_balances(),
+            account_data: AccountDataHolder::entries(),
+            alts: AltCache
+                .entries()
+                .into_iter()
+                .map(|(key, alt)| {
+                    let entry = AltEntry {
+                        key,
+                        addresses: alt.addresses,
+                    };
+                    Stamped::new(entry, stamp)
+                })
+                .collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.pools.len()
+            + self.last_updates.len()
+            + self.balances.len()
+            + self.account_data.len()
+            + self.alts.len()
+    }
+
+    // Drops entries written more than `max_distance` slots before `current_slot`,
+    // returns how many went.
+    fn retain_recent(&mut self, current_slot: u64, max_distance: u64) -> usize {
+        let before = self.len();
+        let recent = |stamp: &SlotStamp| stamp.slot.saturating_add(max_distance) >= current_slot;
+        self.pools.retain(|stamped| recent(&stamped.stamp));
+        self.last_updates.retain(|state| recent(&state.stamp()));
+        self.balances.retain(|(_, stamped)| recent(&stamped.stamp));
+        self.account_data
+            .retain(|(_, stamped)| recent(&stamped.stamp));
+        self.alts.retain(|stamped| recent(&stamped.stamp));
+        before - self.len()
+    }
+}
+
+fn snapshot_path() -> Option<PathBuf> {
+    let path = &ENV_CONFIG.state_snapshot_path;
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+// Written next to the target and renamed over it, so a crash mid-write keeps the
+// previous snapshot.
+fn write(path: PathBuf, snapshot: &StateSnapshot) -> AResult<()> {
+    let _writing = WRITING.lock();
+    let bytes = bincode::serialize(snapshot)?;
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, bytes)?;
+    std::fs::rename(&partial, &path)?;
+    Ok(())
+}
+
+async fn persist(path: PathBuf, snapshot: StateSnapshot) -> AResult<()> {
+    let result = tokio::task::spawn_blocking(move || write(path, &snapshot)).await?;
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    METRICS_REGISTRY.inc_counter("state_snapshot_saves_total", &[("outcome", outcome)]);
+    result
+}
+
+// Returns the number of entries that could not be written, for the shutdown summary.
+pub async fn save_on_shutdown() -> usize {
+    let Some(path) = snapshot_path() else {
+        return 0;
+    };
+    let snapshot = StateSnapshot::capture(chain_clock::current_slot());
+    let entries = snapshot.len();
+    match persist(path, snapshot).await {
+        Ok(()) => {
+            info!("Saved {} cached entries for the next start", entries);
+            0
+        }
+        Err(e) => {
+            error!("Failed to save state snapshot: {:?}", e);
+            entries
+        }
+    }
+}
+
+async fn read(path: PathBuf) -> AResult<Option<StateSnapshot>> {
+    let bytes = match tokio::fs::read(&path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let snapshot: StateSnapshot =
+        tokio::task::spawn_blocking(move || bincode::deserialize(&bytes)).await??;
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(lined_err!(
+            "snapshot version {} is not {}",
+            snapshot.version,
+            SNAPSHOT_VERSION
+        ));
+    }
+    Ok(Some(snapshot))
+}
+
+// Seeds the caches from the last snapshot, then refetches everything that has an RPC
+// source on the background lane. Must run after the chain clock is up and before the
+// streams start. A missing or unreadable snapshot only means a cold start.
+pub async fn restore() {
+    let Some(path) = snapshot_path() else {
+        return;
+    };
+    let mut snapshot = match read(path.clone()).await {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Ignoring state snapshot {}: {:?}", path.display(), e);
+            return;
+        }
+    };
+    let current_slot = chain_clock::current_slot();
+    let discarded =
+        snapshot.retain_recent(current_slot, ENV_CONFIG.state_snapshot_max_slot_distance);
+    info!(
+        "Restoring {} cached entries from slot {}, {} slots ago, {} too old to keep",
+        snapshot.len(),
+        snapshot.taken_at_slot,
+        current_slot.saturating_sub(snapshot.taken_at_slot),
+        discarded
+    );
+
+    let pools: Vec<Pubkey> = snapshot
+        .pools
+        .iter()
+        .map(|s| s.value.pool_address())
+        .collect();
+    let balances: Vec<(Pubkey, MintAddress)> =
+        snapshot.balances.iter().map(|(key, _)| *key).collect();
+    let accounts: Vec<Pubkey> = snapshot.account_data.iter().map(|(key, _)| *key).collect();
+    let alts: Vec<Pubkey> = snapshot.alts.iter().map(|s| s.value.key).collect();
+
+    for stamped in snapshot.pools {
+        AnyPoolHolder::restore(stamped).await;
+    }
+    // Only orders streamed writes, the next write of each account replaces it.
+    for state in snapshot.last_updates {
+        restore_last_update(state).await;
+    }
+    for (key, amount) in snapshot.balances {
+        restore_streamed_balance(key, amount).await;
+    }
+    for (key, stamped) in snapshot.account_data {
+        AccountDataHolder::update(key, stamped.value, stamped.stamp).await;
+    }
+    for stamped in snapshot.alts {
+        let AltEntry { key, addresses } = stamped.value;
+        AltCache
+            .put(key, AddressLookupTableAccount { key, addresses })
+            .await;
+    }
+
+    tokio::spawn(revalidate(pools, balances, accounts, alts));
+}
+
+async fn revalidate(
+    pools: Vec<Pubkey>,
+    balances: Vec<(Pubkey, MintAddress)>,
+    accounts: Vec<Pubkey>,
+    alts: Vec<Pubkey>,
+) {
+    let total = pools.len() + balances.len() + accounts.len() + alts.len();
+    futures::stream::iter(pools)
+        .for_each_concurrent(REVALIDATE_CONCURRENCY, |pool| async move {
+            if let Err(e) = AnyPoolHolder::revalidate(&pool).await {
+                warn!("Failed to revalidate pool {}: {:?}", pool, e);
+            }
+        })
+        .await;
+    futures::stream::iter(balances)
+        .for_each_concurrent(REVALIDATE_CONCURRENCY, |(account, mint)| async move {
+            revalidate_balance(&account, &mint).await;
+        })
+        .await;
+    futures::stream::iter(accounts)
+        .for_each_concurrent(REVALIDATE_CONCURRENCY, |account| async move {
+            if let Err(e) = AccountDataHolder::revalidate(&account).await {
+                warn!("Failed to revalidate account {}: {:?}", account, e);
+            }
+        })
+        .await;
+    futures::stream::iter(alts)
+        .for_each_concurrent(REVALIDATE_CONCURRENCY, |alt| async move {
+            if let Err(e) = refresh_alt(&alt).await {
+                warn!("Failed to revalidate ALT {}: {:?}", alt, e);
+            }
+        })
+        .await;
+    info!("Revalidated {} restored entries", total);
+}
+
+// Saves on a dedicated thread every `STATE_SNAPSHOT_INTERVAL_SECS`, starting one
+// interval in so a cold start does not replace the previous snapshot right away.
+pub fn initialize() {
+    let Some(path) = snapshot_path() else {
+        return;
+    };
+    let period = Duration::from_secs(ENV_CONFIG.state_snapshot_interval_secs.max(1));
+
+    thread::Builder::new()
+        .name("state-snapshot".to_string())
+        .spawn(move || {
+            let runtime = Builder::new_current_thread()
+                .enable_all()
+                .thread_name("state-snapshot-runtime")
+                .build()
+                .expect("Failed to create runtime for state snapshots");
+
+            runtime.block_on(async move {
+                let mut interval = interval(period);
+                interval.tick().await;
+
+                loop {
+                    interval.tick().await;
+                    let snapshot = StateSnapshot::capture(chain_clock::current_slot());
+                    if let Err(e) = persist(path.clone(), snapshot).await {
+                        error!("Failed to save state snapshot: {:?}", e);
+                    }
+                }
+            });
+        })
+        .expect("Failed to spawn state snapshot thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convention::chain::stamp::Commitment;
+    use std::time::Instant;
+
+    fn state_at(slot: u64) -> AccountState {
+        AccountState {
+            pubkey: Pubkey::new_unique(),
+            lamports: 1,
+            data: vec![1, 2, 3],
+            owner: Pubkey::new_unique(),
+            slot,
+            write_version: 7,
+            source: 1,
+            txn_signature: None,
+            commitment: Commitment::Processed,
+            last_update: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_retain_recent_drops_entries_past_slot_distance() {
+        let stamp = |slot| SlotStamp::new(slot, Commitment::Confirmed);
+        let mut snapshot = StateSnapshot {
+            version: SNAPSHOT_VERSION,
+            taken_at_slot: 1_000,
+            last_updates: vec![state_at(400), state_at(900)],
+            account_data: vec![
+                (Pubkey::new_unique(), Stamped::new(vec![1], stamp(499))),
+                (Pubkey::new_unique(), Stamped::new(vec![2], stamp(500))),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(snapshot.retain_recent(1_000, 500), 2);
+        assert_eq!(snapshot.last_updates[0].slot, 900);
+        assert_eq!(snapshot.account_data[0].1.stamp.slot, 500);
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_through_bincode() {
+        let key = (Pubkey::new_unique(), Pubkey::new_unique());
+        let snapshot = StateSnapshot {
+            version: SNAPSHOT_VERSION,
+            taken_at_slot: 42,
+            last_updates: vec![state_at(41)],
+            balances: vec![(
+                key,
+                Stamped::new(
+                    TokenAmount {
+                        amount: 5,
+                        decimals: 6,
+                    },
+                    SlotStamp::new(40, Commitment::Processed),
+                ),
+            )],
+            ..Default::default()
+        };
+
+        let bytes = bincode::serialize(&snapshot).unwrap();
+        let restored: StateSnapshot = bincode::deserialize(&bytes).unwrap();
+
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.last_updates[0].write_version, 7);
+        assert_eq!(restored.balances[0].0, key);
+        assert_eq!(restored.balances[0].1.value.amount, 5);
+    }
+}
@@ -6,6 +6,7 @@ pub enum BlocklistReason {
     QueryFailed,
     AccountNotFound,
     InvalidDataSize { size: usize },
+    InvalidPoolData,
     NotInDatabase,
     NoWsolInvolved,
 }
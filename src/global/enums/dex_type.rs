@@ -71,6 +71,12 @@ impl DexType {
             .unwrap_or(DexType::Unknown)
     }
 
+    // Vault balances of these pools are tracked from streamed transactions, the rest
+    // only ever come from RPC and carry the slot they were fetched at.
+    pub fn streams_vault_balances(&self) -> bool {
+        matches!(self, DexType::PumpAmm | DexType::RaydiumCpmm)
+    }
+
     pub fn owner_program_id(&self) -> Pubkey {
         PROGRAM_TO_DEX
             .iter()
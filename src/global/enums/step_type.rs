@@ -14,8 +14,12 @@ pub enum StepType {
     DetermineOpportunityLoadingRelatedMints,
     DetermineOpportunityLoadedRelatedMints,
     DetermineOpportunityFinished,
+    MevTxSuppressed,
+    SupersededByNewerState,
+    DeadlineExpired,
     MevTxFired,
     MevTxTryToFire,
+    CausingTxResolved,
     MevTxReadyToBuild,
     MevIxBuilding,
     MevIxBuilt,
@@ -27,3 +31,12 @@ pub enum StepType {
     #[strum(default)]
     Custom(String),
 }
+
+impl StepType {
+    pub fn label(&self) -> &str {
+        match self {
+            StepType::Custom(s) => s.as_str(),
+            other => other.as_ref(),
+        }
+    }
+}
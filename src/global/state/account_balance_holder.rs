@@ -1,11 +1,14 @@
+use crate::convention::chain::stamp::{SlotStamp, Stamped};
 use crate::database::mint_record::repository::MintRecordRepository;
+use crate::global::state::slot_state_index::{SlotStateIndex, StateKey};
 use crate::lazy_arc;
 use crate::pipeline::event_processor::token_balance::token_balance_processor::{
     TokenAmount, TokenBalanceShortLivingCache,
 };
-use crate::sdk::rpc::methods::account::buffered_get_account;
+use crate::sdk::rpc::methods::account::{buffered_get_account_with_slot_in, BatchLane};
 use crate::util::alias::MintAddress;
 use crate::util::cache::loading_cache::LoadingCache;
+use crate::util::env::env_config::ENV_CONFIG;
 use crate::util::structs::rate_limiter::RateLimiter;
 use once_cell::sync::Lazy;
 use solana_program::program_pack::Pack;
@@ -19,15 +22,26 @@ use tracing::{error, warn};
 type Token2022Account<'a> = StateWithExtensions<'a, spl_token_2022::state::Account>;
 
 #[allow(non_upper_case_globals)]
-static LongTermCache: Lazy<LoadingCache<(Pubkey, MintAddress), TokenAmount>> = Lazy::new(|| {
-    LoadingCache::with_ttl(
-        1_000_000,
-        Duration::from_secs(3600 * 3),
-        |_| async move { None },
-    )
-});
+static LongTermCache: Lazy<LoadingCache<(Pubkey, MintAddress), Stamped<TokenAmount>>> =
+    Lazy::new(|| {
+        LoadingCache::with_ttl(
+            1_000_000,
+            Duration::from_secs(3600 * 3),
+            |_| async move { None },
+        )
+        .named("AccountBalanceLongTermCache")
+    });
 
 pub async fn get_balance_of_account(account: &Pubkey, mint: &MintAddress) -> Option<TokenAmount> {
+    get_stamped_balance_of_account(account, mint)
+        .await
+        .map(|stamped| stamped.value)
+}
+
+pub async fn get_stamped_balance_of_account(
+    account: &Pubkey,
+    mint: &MintAddress,
+) -> Option<Stamped<TokenAmount>> {
     let key = &(account.clone(), mint.clone());
 
     // first, fetch from short term cache which is generated by grpc streaming
@@ -49,8 +63,58 @@ pub async fn get_balance_of_account(account: &Pubkey, mint: &MintAddress) -> Opt
     None
 }
 
-async fn fetch_from_rpc(account: &Pubkey, mint: &MintAddress) -> Option<TokenAmount> {
-    let data = buffered_get_account(account).await.ok()?;
+// Bypasses both caches, used when streamed balance updates may have been missed.
+pub async fn refresh_balance_of_account(
+    account: &Pubkey,
+    mint: &MintAddress,
+) -> Option<Stamped<TokenAmount>> {
+    let key = (*account, *mint);
+    let amount = fetch_from_rpc(account, mint).await?;
+    TokenBalanceShortLivingCache.put(key, amount.clone()).await;
+    LongTermCache.put(key, amount.clone()).await;
+    Some(amount)
+}
+
+pub fn streamed_balances() -> Vec<((Pubkey, MintAddress), Stamped<TokenAmount>)> {
+    TokenBalanceShortLivingCache.entries()
+}
+
+// Seeds a streamed balance, unless one written at a later slot is cached already.
+pub async fn restore_streamed_balance(key: (Pubkey, MintAddress), amount: Stamped<TokenAmount>) {
+    let cached = streamed_balance_stamp(&key.0, &key.1).await;
+    if cached.is_none_or(|cached| cached.slot <= amount.stamp.slot) {
+        SlotStateIndex::record(&amount.stamp, StateKey::Balance(key.0, key.1));
+        TokenBalanceShortLivingCache.put(key, amount).await;
+    }
+}
+
+// Refetches on the background lane, keeping whatever was written at a later slot.
+pub async fn revalidate_balance(account: &Pubkey, mint: &MintAddress) {
+    if let Some(amount) = fetch_from_rpc_in(account, mint, BatchLane::Background).await {
+        LongTermCache.put((*account, *mint), amount.clone()).await;
+        restore_streamed_balance((*account, *mint), amount).await;
+    }
+}
+
+pub async fn streamed_balance_stamp(account: &Pubkey, mint: &MintAddress) -> Option<SlotStamp> {
+    TokenBalanceShortLivingCache
+        .get_if_present(&(*account, *mint))
+        .await
+        .map(|stamped| stamped.stamp)
+}
+
+async fn fetch_from_rpc(account: &Pubkey, mint: &MintAddress) -> Option<Stamped<TokenAmount>> {
+    fetch_from_rpc_in(account, mint, BatchLane::Hot).await
+}
+
+async fn fetch_from_rpc_in(
+    account: &Pubkey,
+    mint: &MintAddress,
+    lane: BatchLane,
+) -> Option<Stamped<TokenAmount>> {
+    let (data, slot) = buffered_get_account_with_slot_in(account, lane)
+        .await
+        .ok()?;
 
     let (vault_mint, vault_amount) = if let Ok(vault) = Account::unpack_from_slice(&data.data) {
         (vault.mint, vault.amount)
@@ -67,8 +131,11 @@ async fn fetch_from_rpc(account: &Pubkey, mint: &MintAddress) -> Option<TokenAmo
         );
     }
     let decimals = MintRecordRepository::get_decimal(mint).await?;
-    Some(TokenAmount {
-        amount: vault_amount,
-        decimals,
-    })
+    Some(Stamped::new(
+        TokenAmount {
+            amount: vault_amount,
+            decimals,
+        },
+        SlotStamp::new(slot, ENV_CONFIG.rpc_commitment),
+    ))
 }
@@ -1,31 +1,61 @@
+use crate::convention::chain::stamp::{SlotStamp, Stamped};
 use crate::global::constant::duration::Interval;
-use crate::sdk::rpc::methods::account::buffered_get_account;
+use crate::sdk::rpc::methods::account::{
+    buffered_get_account_with_slot, buffered_get_account_with_slot_in, BatchLane,
+};
+use crate::util::alias::AResult;
 use crate::util::cache::loading_cache::LoadingCache;
+use crate::util::env::env_config::ENV_CONFIG;
 use once_cell::sync::Lazy;
 use solana_program::pubkey::Pubkey;
 
 #[allow(non_upper_case_globals)]
-static AccountDataCache: Lazy<LoadingCache<Pubkey, Vec<u8>>> =
-    Lazy::new(|| LoadingCache::with_ttl(500_000, Interval::HOUR, |_| async move { None }));
+static AccountDataCache: Lazy<LoadingCache<Pubkey, Stamped<Vec<u8>>>> = Lazy::new(|| {
+    LoadingCache::with_ttl(500_000, Interval::HOUR, |_| async move { None })
+        .named("AccountDataCache")
+});
 
 pub struct AccountDataHolder {}
 
 impl AccountDataHolder {
     pub async fn get_account_data(addr: &Pubkey) -> Option<Vec<u8>> {
-        if let Some(data) = AccountDataCache.get_if_present(addr).await {
-            return Some(data);
+        if let Some(stamped) = AccountDataCache.get_if_present(addr).await {
+            return Some(stamped.value);
         }
 
-        if let Some(account) = buffered_get_account(addr).await.ok() {
+        if let Ok((account, slot)) = buffered_get_account_with_slot(addr).await {
             let data = account.data;
-            AccountDataCache.put(*addr, data.clone()).await;
+            let stamp = SlotStamp::new(slot, ENV_CONFIG.rpc_commitment);
+            AccountDataCache
+                .put(*addr, Stamped::new(data.clone(), stamp))
+                .await;
             return Some(data);
         }
 
         None
     }
 
-    pub async fn update(addr: Pubkey, data: Vec<u8>) {
-        AccountDataCache.put(addr, data).await;
+    pub async fn update(addr: Pubkey, data: Vec<u8>, stamp: SlotStamp) {
+        AccountDataCache.put(addr, Stamped::new(data, stamp)).await;
+    }
+
+    pub fn entries() -> Vec<(Pubkey, Stamped<Vec<u8>>)> {
+        AccountDataCache.entries()
+    }
+
+    // Refetches on the background lane, keeping whatever was written at a later slot.
+    pub async fn revalidate(addr: &Pubkey) -> AResult<()> {
+        let (account, slot) =
+            buffered_get_account_with_slot_in(addr, BatchLane::Background).await?;
+        let cached = AccountDataCache.get_if_present(addr).await;
+        if cached.is_none_or(|cached| cached.stamp.slot <= slot) {
+            let stamp = SlotStamp::new(slot, ENV_CONFIG.rpc_commitment);
+            Self::update(*addr, account.data, stamp).await;
+        }
+        Ok(())
+    }
+
+    pub fn invalidate_all() {
+        AccountDataCache.invalidate_all();
     }
 }
@@ -1,9 +1,13 @@
+use crate::convention::chain::stamp::{SlotStamp, Stamped};
 use crate::dex::any_pool_config::AnyPoolConfig;
 use crate::global::enums::dex_type::DexType;
-use crate::sdk::rpc::methods::account::buffered_get_account;
+use crate::global::state::slot_state_index::{SlotStateIndex, StateKey};
+use crate::sdk::rpc::methods::account::{
+    buffered_get_account_with_slot, buffered_get_account_with_slot_in, BatchLane,
+};
 use crate::util::alias::{AResult, PoolAddress};
 use crate::util::cache::loading_cache::LoadingCache;
-use crate::util::traits::option::OptionExt;
+use crate::util::env::env_config::ENV_CONFIG;
 use once_cell::sync::Lazy;
 use solana_program::pubkey::Pubkey;
 
@@ -11,47 +15,87 @@ pub struct AnyPoolHolder;
 
 impl AnyPoolHolder {
     pub async fn get(addr: &Pubkey) -> Option<AnyPoolConfig> {
+        cache.get(addr).await.map(|stamped| stamped.value)
+    }
+
+    pub async fn get_stamped(addr: &Pubkey) -> Option<Stamped<AnyPoolConfig>> {
         cache.get(addr).await
     }
 
+    pub async fn stamp_of(addr: &Pubkey) -> Option<SlotStamp> {
+        cache
+            .get_if_present(addr)
+            .await
+            .map(|stamped| stamped.stamp)
+    }
+
     pub async fn batch_get(pool_addresses: &[Pubkey]) -> Vec<Option<AnyPoolConfig>> {
-        let futures = pool_addresses
-            .iter()
-            .map(|addr| cache.get(addr))
-            .collect::<Vec<_>>();
+        let futures = pool_addresses.iter().map(Self::get).collect::<Vec<_>>();
 
         futures::future::join_all(futures).await
     }
 
     pub async fn fresh_get(addr: &Pubkey) -> AResult<AnyPoolConfig> {
-        let config = AnyPoolConfig::from(addr).await?;
-        cache.put(addr.clone(), config.clone()).await;
+        let stamped = AnyPoolConfig::from(addr).await?;
+        let config = stamped.value.clone();
+        Self::put(stamped).await;
         Ok(config)
     }
 
-    pub async fn upsert(config: AnyPoolConfig) {
-        cache.put(config.pool_address(), config).await
-    }
-
     pub async fn update_config(
         pool_address: &PoolAddress,
         owner: &Pubkey,
         data: &[u8],
+        stamp: SlotStamp,
     ) -> AResult<AnyPoolConfig> {
         let updated_config = AnyPoolConfig::from_owner_and_data(pool_address, owner, data)?;
+        Self::put(Stamped::new(updated_config.clone(), stamp)).await;
+        Ok(updated_config)
+    }
+
+    pub fn entries() -> Vec<Stamped<AnyPoolConfig>> {
         cache
-            .put(updated_config.pool_address(), updated_config)
-            .await;
-        Ok(cache.get(pool_address).await.or_err("")?)
+            .entries()
+            .into_iter()
+            .map(|(_, stamped)| stamped)
+            .collect()
+    }
+
+    // Seeds a pool config, unless one written at a later slot is cached already.
+    pub async fn restore(stamped: Stamped<AnyPoolConfig>) {
+        let cached = Self::stamp_of(&stamped.value.pool_address()).await;
+        if cached.is_none_or(|cached| cached.slot <= stamped.stamp.slot) {
+            Self::put(stamped).await;
+        }
+    }
+
+    // Refetches on the background lane, keeping whatever was written at a later slot.
+    pub async fn revalidate(addr: &Pubkey) -> AResult<()> {
+        let (account, slot) =
+            buffered_get_account_with_slot_in(addr, BatchLane::Background).await?;
+        let config = AnyPoolConfig::from_owner_and_data(addr, &account.owner, &account.data)?;
+        Self::restore(Stamped::new(
+            config,
+            SlotStamp::new(slot, ENV_CONFIG.rpc_commitment),
+        ))
+        .await;
+        Ok(())
+    }
+
+    async fn put(stamped: Stamped<AnyPoolConfig>) {
+        let pool_address = stamped.value.pool_address();
+        SlotStateIndex::record(&stamped.stamp, StateKey::Pool(pool_address));
+        cache.put(pool_address, stamped).await
     }
 }
 
 #[allow(non_upper_case_globals)]
-static cache: Lazy<LoadingCache<Pubkey, AnyPoolConfig>> = Lazy::new(|| {
+static cache: Lazy<LoadingCache<Pubkey, Stamped<AnyPoolConfig>>> = Lazy::new(|| {
     LoadingCache::new(100_000, |pool: &Pubkey| {
         let pool = *pool;
         async move { AnyPoolConfig::from(&pool).await.ok() }
     })
+    .named("AnyPoolCache")
 });
 
 impl AnyPoolConfig {
@@ -64,9 +108,12 @@ impl AnyPoolConfig {
         Self::new(*pool_address, dex_type, data)
     }
 
-    async fn from(pool_address: &Pubkey) -> AResult<AnyPoolConfig> {
-        let account = buffered_get_account(pool_address).await?;
+    async fn from(pool_address: &Pubkey) -> AResult<Stamped<AnyPoolConfig>> {
+        let (account, slot) = buffered_get_account_with_slot(pool_address).await?;
         let dex_type = DexType::determine_from(&account.owner);
-        Self::new(*pool_address, dex_type, &account.data)
+        Ok(Stamped::new(
+            Self::new(*pool_address, dex_type, &account.data)?,
+            SlotStamp::new(slot, ENV_CONFIG.rpc_commitment),
+        ))
     }
 }
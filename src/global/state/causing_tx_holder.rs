@@ -0,0 +1,55 @@
+use crate::convention::chain::types::SwapInstruction;
+use crate::convention::chain::Transaction;
+use crate::global::constant::duration::Interval;
+use crate::sdk::rpc::methods::transaction::fetch_tx;
+use crate::util::cache::loading_cache::LoadingCache;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+// The transaction behind an account write, reduced to the swaps we can recognise.
+#[derive(Debug, Clone, Serialize)]
+pub struct CausingTx {
+    pub signature: String,
+    pub slot: u64,
+    pub swaps: Vec<SwapInstruction>,
+}
+
+impl CausingTx {
+    fn from_transaction(tx: &Transaction) -> Self {
+        Self {
+            signature: tx.signature.clone(),
+            slot: tx.slot,
+            swaps: tx
+                .extract_known_swap_ix(&tx.all_instructions())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[allow(non_upper_case_globals)]
+static CausingTxCache: Lazy<LoadingCache<String, CausingTx>> = Lazy::new(|| {
+    LoadingCache::with_ttl(100_000, Interval::MINUTE * 5, |_| async move { None })
+        .named("CausingTxCache")
+});
+
+pub struct CausingTxHolder {}
+
+impl CausingTxHolder {
+    // Transactions seen on the transaction stream, so most lookups skip the RPC.
+    pub async fn record(tx: &Transaction) {
+        CausingTxCache
+            .put(tx.signature.clone(), CausingTx::from_transaction(tx))
+            .await;
+    }
+
+    pub async fn resolve(signature: &str) -> Option<CausingTx> {
+        let signature = signature.to_string();
+        if let Some(tx) = CausingTxCache.get_if_present(&signature).await {
+            return Some(tx);
+        }
+
+        let tx = CausingTx::from_transaction(&fetch_tx(&signature).await.ok()?);
+        CausingTxCache.put(signature, tx.clone()).await;
+        Some(tx)
+    }
+}
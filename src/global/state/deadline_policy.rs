@@ -0,0 +1,278 @@
+#![allow(non_upper_case_globals)]
+use crate::global::enums::step_type::StepType;
+use crate::global::trace::types::Trace;
+use crate::lined_err;
+use crate::util::alias::AResult;
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use strum_macros::AsRefStr;
+use tracing::{error, info};
+
+const DEFAULT_BUDGETS_MS: [(Stage, u32); 3] = [
+    (Stage::Strategy, 10_000),
+    // leaves the send step time to go out before the next slot is over
+    (Stage::Simulation, 700),
+    // the opportunity is stale once the slot after the triggering one is over
+    (Stage::Send, 800),
+];
+
+const AGE_BUCKET_MS: u32 = 100;
+const AGE_BUCKETS: usize = 16;
+const MIN_BUCKET_SENDS: f64 = 20.0;
+// An age bucket landing at under this share of the best bucket's rate is cut off.
+const DEAD_RATE_RATIO: f64 = 0.2;
+// Counts are halved once a bucket has this many sends, so recent behaviour dominates.
+const DECAY_AT_SENDS: f64 = 200.0;
+// Share of sends let through past a learned cutoff, so a recovering channel is noticed.
+const EXPLORE_RATE: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum Stage {
+    Strategy,
+    Simulation,
+    Send,
+}
+
+// Milliseconds allowed since the start of the triggering slot, keyed `stage` or
+// `stage.qualifier`, where the qualifier is a DEX (`simulation.PumpAmm`) or a landing
+// channel (`send.Jito`).
+#[derive(Debug, Clone)]
+pub struct DeadlineBudgets(HashMap<String, u32>);
+
+impl DeadlineBudgets {
+    // Entries look like `send.Jito=600`, they override the defaults.
+    pub fn parse(entries: &[String]) -> AResult<Self> {
+        let mut budgets: HashMap<String, u32> = DEFAULT_BUDGETS_MS
+            .iter()
+            .map(|(stage, ms)| (stage.as_ref().to_string(), *ms))
+            .collect();
+        for entry in entries {
+            let (key, ms) = entry
+                .split_once('=')
+                .ok_or_else(|| lined_err!("deadline budget {} is not key=ms", entry))?;
+            budgets.insert(key.trim().to_string(), ms.trim().parse()?);
+        }
+        Ok(Self(budgets))
+    }
+
+    // The tightest budget among the qualifiers, the stage's own when none is configured.
+    fn limit_ms<Q: AsRef<str>>(&self, stage: Stage, qualifiers: &[Q]) -> (String, u32) {
+        qualifiers
+            .iter()
+            .filter_map(|qualifier| {
+                let key = format!("{}.{}", stage.as_ref(), qualifier.as_ref());
+                let ms = *self.0.get(&key)?;
+                Some((key, ms))
+            })
+            .min_by_key(|(_, ms)| *ms)
+            .unwrap_or_else(|| (stage.as_ref().to_string(), self.0[stage.as_ref()]))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct AgeBucket {
+    sent: f64,
+    landed: f64,
+}
+
+impl AgeBucket {
+    fn rate(&self) -> Option<f64> {
+        (self.sent >= MIN_BUCKET_SENDS).then(|| self.landed / self.sent)
+    }
+}
+
+// Landing outcomes of one channel by how far past the slot start they were sent.
+#[derive(Debug, Default)]
+struct LandingByAge {
+    buckets: [AgeBucket; AGE_BUCKETS],
+}
+
+impl LandingByAge {
+    fn record(&mut self, age_ms: u32, landed: bool) {
+        let idx = ((age_ms / AGE_BUCKET_MS) as usize).min(AGE_BUCKETS - 1);
+        let bucket = &mut self.buckets[idx];
+        bucket.sent += 1.0;
+        if landed {
+            bucket.landed += 1.0;
+        }
+        if bucket.sent >= DECAY_AT_SENDS {
+            bucket.sent /= 2.0;
+            bucket.landed /= 2.0;
+        }
+    }
+
+    // Start of the first age bucket after the best one whose landing rate fell off.
+    fn cutoff_ms(&self) -> Option<u32> {
+        let (best_idx, best_rate) = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, bucket)| Some((idx, bucket.rate()?)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if best_rate == 0.0 {
+            return None;
+        }
+        self.buckets
+            .iter()
+            .enumerate()
+            .skip(best_idx + 1)
+            .find(|(_, bucket)| {
+                bucket
+                    .rate()
+                    .is_some_and(|rate| rate < best_rate * DEAD_RATE_RATIO)
+            })
+            .map(|(idx, _)| idx as u32 * AGE_BUCKET_MS)
+    }
+}
+
+// Decides when a stage should give up on an opportunity, measured on the chain clock
+// from the start of the triggering slot. Send budgets also tighten per channel to the
+// age past which sends stop landing, learned from the landing status of every send.
+pub struct DeadlinePolicy {
+    budgets: DeadlineBudgets,
+    landing: Mutex<HashMap<String, LandingByAge>>,
+}
+
+impl DeadlinePolicy {
+    pub fn new(budgets: DeadlineBudgets) -> Self {
+        Self {
+            budgets,
+            landing: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn limit<Q: AsRef<str>>(&self, stage: Stage, qualifiers: &[Q], explore: bool) -> (String, u32) {
+        let (budget, limit_ms) = self.budgets.limit_ms(stage, qualifiers);
+        if stage != Stage::Send || explore {
+            return (budget, limit_ms);
+        }
+        let landing = self.landing.lock();
+        qualifiers
+            .iter()
+            .filter_map(|channel| {
+                let cutoff = landing.get(channel.as_ref())?.cutoff_ms()?;
+                Some((
+                    format!("{}.{}.landing", stage.as_ref(), channel.as_ref()),
+                    cutoff,
+                ))
+            })
+            .filter(|(_, cutoff)| *cutoff < limit_ms)
+            .min_by_key(|(_, cutoff)| *cutoff)
+            .unwrap_or((budget, limit_ms))
+    }
+
+    // Records the budget that ran out on the trace and in `deadline_expired_total`.
+    pub fn expired<Q: AsRef<str>>(&self, stage: Stage, qualifiers: &[Q], trace: &Trace) -> bool {
+        let elapsed_ms = trace.since_slot_start();
+        let (budget, limit_ms) =
+            self.limit(stage, qualifiers, rand::random::<f64>() < EXPLORE_RATE);
+        if elapsed_ms <= limit_ms {
+            return false;
+        }
+        info!(
+            "Gave up at {} because {} milliseconds passed since slot {} started, budget {} allows {}",
+            stage.as_ref(),
+            elapsed_ms,
+            trace.slot,
+            budget,
+            limit_ms
+        );
+        trace.step_with_struct(
+            StepType::DeadlineExpired,
+            "deadline",
+            &json!({
+                "budget": budget,
+                "elapsed_ms": elapsed_ms,
+                "limit_ms": limit_ms,
+            }),
+        );
+        METRICS_REGISTRY.inc_counter("deadline_expired_total", &[("budget", &budget)]);
+        true
+    }
+
+    pub fn record_landing(&self, channel: &str, age_ms: u32, landed: bool) {
+        self.landing
+            .lock()
+            .entry(channel.to_string())
+            .or_default()
+            .record(age_ms, landed);
+    }
+
+    fn export_cutoffs(&self) {
+        for (channel, landing) in self.landing.lock().iter() {
+            let cutoff = landing.cutoff_ms().map_or(0.0, |ms| ms as f64);
+            METRICS_REGISTRY.set_gauge(
+                "deadline_landing_cutoff_ms",
+                &[("channel", channel)],
+                cutoff,
+            );
+        }
+    }
+}
+
+pub static DeadlinePolicyHolder: Lazy<Arc<DeadlinePolicy>> = Lazy::new(|| {
+    let budgets = DeadlineBudgets::parse(&ENV_CONFIG.deadline_budgets_ms).unwrap_or_else(|e| {
+        error!("Ignoring DEADLINE_BUDGETS_MS: {}", e);
+        DeadlineBudgets::parse(&[]).unwrap()
+    });
+    let policy = Arc::new(DeadlinePolicy::new(budgets));
+    let policy_clone = policy.clone();
+    METRICS_REGISTRY.register_collector(move || {
+        policy_clone.export_cutoffs();
+        true
+    });
+    policy
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_lookup_prefers_tightest_qualifier() {
+        let budgets = DeadlineBudgets::parse(&[
+            "simulation.PumpAmm=500".to_string(),
+            "simulation.RaydiumV4 = 600".to_string(),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            budgets.limit_ms(Stage::Simulation, &["RaydiumV4", "PumpAmm"]),
+            ("simulation.PumpAmm".to_string(), 500)
+        );
+        assert_eq!(
+            budgets.limit_ms(Stage::Simulation, &["Whirlpool"]),
+            ("simulation".to_string(), 700)
+        );
+        assert!(DeadlineBudgets::parse(&["send".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_send_budget_tightens_where_landings_stop() {
+        let policy = DeadlinePolicy::new(DeadlineBudgets::parse(&[]).unwrap());
+        for _ in 0..40 {
+            policy.record_landing("Jito", 150, true);
+            policy.record_landing("Jito", 250, true);
+            policy.record_landing("Jito", 450, false);
+        }
+
+        assert_eq!(
+            policy.limit(Stage::Send, &["Jito"], false),
+            ("send.Jito.landing".to_string(), 400)
+        );
+        assert_eq!(
+            policy.limit(Stage::Send, &["Jito"], true),
+            ("send".to_string(), 800)
+        );
+        assert_eq!(
+            policy.limit(Stage::Send, &["Shyft"], false),
+            ("send".to_string(), 800)
+        );
+    }
+}
@@ -1,3 +1,8 @@
 pub mod account_balance_holder;
 pub mod account_data_holder;
 pub mod any_pool_holder;
+pub mod causing_tx_holder;
+pub mod deadline_policy;
+pub mod pair_cooldown_holder;
+pub mod pool_generation_holder;
+pub mod slot_state_index;
@@ -0,0 +1,222 @@
+#![allow(non_upper_case_globals)]
+use crate::convention::chain::util::simulation::SimulationResult;
+use crate::database::kv_cache::repository::KvCacheRepository;
+use crate::util::alias::{MintAddress, PoolAddress};
+use crate::util::cache::persistent_cache::PersistentCache;
+use crate::util::structs::cache_type::CacheType;
+use crate::util::structs::cooldown::{classify_error, CooldownEntry, CooldownPolicy, Suppression};
+use chrono::{DateTime, Duration, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+const MAX_ENTRIES: usize = 100_000;
+// Entries untouched for this long are forgotten, here and in the database.
+const ENTRY_TTL: Duration = Duration::days(1);
+const EVICT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+// Reads are served from `Cooldowns`, the store only needs room for its write path.
+const STORE_CAPACITY: u64 = 1_000;
+
+// Cooldowns live in memory, so checking one is a map lookup on the hot path. Updates
+// are persisted through a `PersistentCache` in the background and read back once at
+// startup.
+pub struct PairCooldownHolder;
+
+impl PairCooldownHolder {
+    pub fn state(minor_mint: &MintAddress, pools: &[PoolAddress]) -> Suppression {
+        Cooldowns.state(&pair_key(minor_mint, pools), Utc::now())
+    }
+
+    pub fn record(minor_mint: &MintAddress, pools: &[PoolAddress], result: &SimulationResult) {
+        let key = pair_key(minor_mint, pools);
+        let updated = Cooldowns.apply(key.clone(), result.err.as_deref(), Utc::now());
+        debug!(
+            "Cooldown for {} updated: failures={}, suppressed_until={}",
+            key, updated.consecutive_failures, updated.suppressed_until
+        );
+        if CooldownWriter.send((key, updated)).is_err() {
+            warn!("Pair cooldown writer stopped, update not persisted");
+        }
+    }
+
+    pub async fn restore() {
+        match KvCacheRepository::list(cache_type()).await {
+            Ok(rows) => {
+                for row in rows {
+                    if let Ok(entry) = serde_json::from_value::<CooldownEntry>(row.value) {
+                        Cooldowns.entries.entry(row.key).or_insert(entry);
+                    }
+                }
+                info!("Restored {} pair cooldowns", Cooldowns.entries.len());
+            }
+            Err(e) => warn!("Failed to restore pair cooldowns: {:?}", e),
+        }
+    }
+}
+
+#[derive(Default)]
+struct PairCooldowns {
+    entries: DashMap<String, CooldownEntry>,
+}
+
+impl PairCooldowns {
+    fn state(&self, key: &str, now: DateTime<Utc>) -> Suppression {
+        let entry = self.entries.get(key);
+        POLICY.suppression(entry.as_deref(), now)
+    }
+
+    // Reads and replaces the entry under its map lock, so concurrent results for the
+    // same pair each build on the other.
+    fn apply(&self, key: String, err: Option<&str>, now: DateTime<Utc>) -> CooldownEntry {
+        match self.entries.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let updated = match err {
+                    None => POLICY.on_success(now),
+                    Some(err) => {
+                        POLICY.on_failure(Some(entry.get().clone()), classify_error(err), now)
+                    }
+                };
+                entry.insert(updated.clone());
+                updated
+            }
+            Entry::Vacant(entry) => {
+                let updated = match err {
+                    None => POLICY.on_success(now),
+                    Some(err) => POLICY.on_failure(None, classify_error(err), now),
+                };
+                entry.insert(updated.clone());
+                updated
+            }
+        }
+    }
+
+    // Forgets entries untouched for `ENTRY_TTL`, then the least recently touched ones
+    // past `MAX_ENTRIES`.
+    fn evict(&self, now: DateTime<Utc>) {
+        self.entries
+            .retain(|_, entry| now - last_touched(entry) < ENTRY_TTL);
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        if excess == 0 {
+            return;
+        }
+        let oldest: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| (last_touched(entry.value()), entry.key().clone()))
+            .sorted()
+            .take(excess)
+            .map(|(_, key)| key)
+            .collect();
+        for key in oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+fn last_touched(entry: &CooldownEntry) -> DateTime<Utc> {
+    [entry.last_failure_at, entry.last_success_at]
+        .into_iter()
+        .flatten()
+        .fold(entry.suppressed_until, DateTime::max)
+}
+
+fn cache_type() -> CacheType {
+    CacheType::Custom("pair_cooldown".to_string())
+}
+
+fn pair_key(minor_mint: &MintAddress, pools: &[PoolAddress]) -> String {
+    let pools = pools.iter().sorted().map(|p| p.to_string()).join(",");
+    format!("{}:{}", minor_mint, pools)
+}
+
+static POLICY: Lazy<CooldownPolicy> = Lazy::new(CooldownPolicy::default);
+
+static Cooldowns: Lazy<PairCooldowns> = Lazy::new(|| {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(EVICT_INTERVAL);
+        loop {
+            interval.tick().await;
+            Cooldowns.evict(Utc::now());
+        }
+    });
+    PairCooldowns::default()
+});
+
+static CooldownStore: Lazy<PersistentCache<String, CooldownEntry>> = Lazy::new(|| {
+    PersistentCache::new(
+        cache_type(),
+        STORE_CAPACITY,
+        ENTRY_TTL.num_seconds(),
+        |_key: String| async move { None },
+    )
+});
+
+// One writer, so the updates of a pair reach the database in the order they were made.
+static CooldownWriter: Lazy<mpsc::UnboundedSender<(String, CooldownEntry)>> = Lazy::new(|| {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<(String, CooldownEntry)>();
+    tokio::spawn(async move {
+        while let Some((key, entry)) = receiver.recv().await {
+            CooldownStore.put(key, entry).await;
+        }
+    });
+    sender
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn test_failures_accumulate_in_memory() {
+        let cooldowns = PairCooldowns::default();
+        let mint = Pubkey::new_unique();
+        let pools = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let key = pair_key(&mint, &pools);
+        let now = Utc::now();
+
+        assert!(matches!(
+            cooldowns.state(&key, now),
+            Suppression::Allowed { .. }
+        ));
+
+        cooldowns.apply(key.clone(), Some("InstructionError(2, Custom(1))"), now);
+        let second = cooldowns.apply(key.clone(), Some("InstructionError(3, Custom(1))"), now);
+        assert_eq!(second.consecutive_failures, 2);
+        // the reversed pool order is the same pair
+        let reversed: Vec<Pubkey> = pools.iter().rev().copied().collect();
+        assert!(matches!(
+            cooldowns.state(&pair_key(&mint, &reversed), now),
+            Suppression::Suppressed { .. }
+        ));
+
+        cooldowns.apply(key.clone(), None, now);
+        assert!(matches!(
+            cooldowns.state(&key, now),
+            Suppression::Allowed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_evicts_stale_then_least_recently_touched() {
+        let cooldowns = PairCooldowns::default();
+        let now = Utc::now();
+        cooldowns.apply(
+            "stale".to_string(),
+            None,
+            now - ENTRY_TTL - Duration::hours(1),
+        );
+        for i in 0..=MAX_ENTRIES {
+            cooldowns.apply(i.to_string(), None, now + Duration::seconds(i as i64));
+        }
+
+        cooldowns.evict(now + Duration::minutes(1));
+        assert_eq!(cooldowns.entries.len(), MAX_ENTRIES);
+        assert!(!cooldowns.entries.contains_key("stale"));
+        assert!(!cooldowns.entries.contains_key("0"));
+        assert!(cooldowns.entries.contains_key(&MAX_ENTRIES.to_string()));
+    }
+}
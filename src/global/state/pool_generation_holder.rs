@@ -0,0 +1,76 @@
+use crate::global::enums::step_type::StepType;
+use crate::global::trace::types::Trace;
+use crate::util::alias::PoolAddress;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde_json::json;
+
+// Counts the updates queued for each pool. Work started for one of them carries its
+// generation and stops at the next step boundary once a newer one is queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolGeneration {
+    pub pool: PoolAddress,
+    pub generation: u64,
+}
+
+impl PoolGeneration {
+    pub fn is_superseded(&self) -> bool {
+        PoolGenerationHolder::current(&self.pool) > self.generation
+    }
+
+    // Records the abort in the trace, so superseded work is not mistaken for a failure.
+    pub fn superseded_at(&self, stage: &str, trace: &Trace) -> bool {
+        if !self.is_superseded() {
+            return false;
+        }
+        trace.step_with_struct(
+            StepType::SupersededByNewerState,
+            "context",
+            &json!({
+                "pool": self.pool,
+                "stage": stage,
+                "generation": self.generation,
+                "latest_generation": PoolGenerationHolder::current(&self.pool),
+            }),
+        );
+        true
+    }
+}
+
+#[allow(non_upper_case_globals)]
+static Generations: Lazy<DashMap<PoolAddress, u64>> = Lazy::new(DashMap::new);
+
+pub struct PoolGenerationHolder;
+
+impl PoolGenerationHolder {
+    pub fn advance(pool: &PoolAddress) -> PoolGeneration {
+        let mut entry = Generations.entry(*pool).or_insert(0);
+        *entry += 1;
+        PoolGeneration {
+            pool: *pool,
+            generation: *entry,
+        }
+    }
+
+    pub fn current(pool: &PoolAddress) -> u64 {
+        Generations.get(pool).map_or(0, |generation| *generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn newer_update_supersedes_older_work() {
+        let pool = Pubkey::new_unique();
+        let first = PoolGenerationHolder::advance(&pool);
+        assert!(!first.is_superseded());
+
+        let second = PoolGenerationHolder::advance(&pool);
+        assert!(first.is_superseded());
+        assert!(!second.is_superseded());
+        assert!(!PoolGenerationHolder::advance(&Pubkey::new_unique()).is_superseded());
+    }
+}
@@ -0,0 +1,43 @@
+use crate::convention::chain::stamp::SlotStamp;
+use crate::util::alias::{MintAddress, PoolAddress};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use solana_program::pubkey::Pubkey;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateKey {
+    Pool(PoolAddress),
+    Balance(Pubkey, MintAddress),
+}
+
+// Cached state written from processed slots, by slot, so it can be rolled back when
+// its slot is skipped.
+#[allow(non_upper_case_globals)]
+static ProcessedState: Lazy<DashMap<u64, HashSet<StateKey>>> = Lazy::new(DashMap::new);
+
+pub struct SlotStateIndex;
+
+impl SlotStateIndex {
+    pub fn record(stamp: &SlotStamp, key: StateKey) {
+        if stamp.can_roll_back() {
+            ProcessedState.entry(stamp.slot).or_default().insert(key);
+        }
+    }
+
+    pub fn take(slot: u64) -> Vec<StateKey> {
+        ProcessedState
+            .remove(&slot)
+            .map(|(_, keys)| keys.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    // Slots at or below a confirmed slot are either on the chain or already rolled back.
+    pub fn settle(confirmed_slot: u64) {
+        ProcessedState.retain(|slot, _| *slot > confirmed_slot);
+    }
+
+    pub fn tracked_slots() -> Vec<u64> {
+        ProcessedState.iter().map(|entry| *entry.key()).collect()
+    }
+}
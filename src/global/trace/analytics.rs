@@ -0,0 +1,224 @@
+use crate::database::pipeline_trace::repository::PipelineTraceRepository;
+use crate::database::pipeline_trace::PipelineTrace;
+use crate::global::enums::dex_type::DexType;
+use crate::util::alias::AResult;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Reports look back at most a day and pull at most this many rows of it.
+const MAX_REPORT_MINUTES: i64 = 24 * 60;
+const MAX_REPORT_ROWS: u64 = 10_000;
+
+// Order independent, so both directions of a route land in the same bucket.
+pub fn dex_pair_label(a: DexType, b: DexType) -> String {
+    let (a, b) = (a.to_string(), b.to_string());
+    if a <= b {
+        format!("{}-{}", a, b)
+    } else {
+        format!("{}-{}", b, a)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransitionLatency {
+    pub from: String,
+    pub to: String,
+    pub count: usize,
+    pub p50_ms: i64,
+    pub p90_ms: i64,
+    pub p99_ms: i64,
+}
+
+// Traces that never fired are only a sample (`TRACE_SAMPLE_PERCENT`) while fired ones are
+// all kept, so their latencies are reported apart and `exits` compares stages with each
+// other rather than giving absolute counts.
+#[derive(Debug, Clone, Serialize)]
+pub struct DexPairReport {
+    pub dex_pair: String,
+    pub traces: usize,
+    pub fired: usize,
+    pub fired_transitions: Vec<TransitionLatency>,
+    pub unfired_transitions: Vec<TransitionLatency>,
+    // last step reached by traces that never fired, most common first
+    pub exits: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyReport {
+    pub since: DateTime<Utc>,
+    pub traces: usize,
+    pub pairs: Vec<DexPairReport>,
+}
+
+pub async fn latency_report(since_minutes: i64, dex_pair: Option<&str>) -> AResult<LatencyReport> {
+    let since = Utc::now() - Duration::minutes(since_minutes.clamp(1, MAX_REPORT_MINUTES));
+    let rows = PipelineTraceRepository::find_since(since, dex_pair, MAX_REPORT_ROWS).await?;
+    Ok(LatencyReport {
+        since,
+        traces: rows.len(),
+        pairs: analyze(&rows),
+    })
+}
+
+type Transitions = BTreeMap<(String, String), Vec<i64>>;
+
+#[derive(Default)]
+struct PairAccumulator {
+    traces: usize,
+    fired: usize,
+    fired_transitions: Transitions,
+    unfired_transitions: Transitions,
+    exits: BTreeMap<String, usize>,
+}
+
+pub fn analyze(rows: &[PipelineTrace]) -> Vec<DexPairReport> {
+    let mut pairs: BTreeMap<&str, PairAccumulator> = BTreeMap::new();
+    for row in rows {
+        let pair = pairs
+            .entry(row.dex_pair.as_deref().unwrap_or("unknown"))
+            .or_default();
+        pair.traces += 1;
+        if row.fired {
+            pair.fired += 1;
+        } else {
+            *pair.exits.entry(row.last_step.clone()).or_default() += 1;
+        }
+
+        let transitions = if row.fired {
+            &mut pair.fired_transitions
+        } else {
+            &mut pair.unfired_transitions
+        };
+        let steps = step_offsets(&row.trace);
+        for window in steps.windows(2) {
+            let ((from, from_ms), (to, to_ms)) = (&window[0], &window[1]);
+            transitions
+                .entry((from.clone(), to.clone()))
+                .or_default()
+                .push(to_ms - from_ms);
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|(dex_pair, pair)| {
+            let mut exits: Vec<_> = pair.exits.into_iter().collect();
+            exits.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            DexPairReport {
+                dex_pair: dex_pair.to_string(),
+                traces: pair.traces,
+                fired: pair.fired,
+                fired_transitions: latencies(pair.fired_transitions),
+                unfired_transitions: latencies(pair.unfired_transitions),
+                exits,
+            }
+        })
+        .collect()
+}
+
+fn latencies(transitions: Transitions) -> Vec<TransitionLatency> {
+    transitions
+        .into_iter()
+        .map(|((from, to), mut samples)| {
+            samples.sort_unstable();
+            TransitionLatency {
+                from,
+                to,
+                count: samples.len(),
+                p50_ms: percentile(&samples, 50),
+                p90_ms: percentile(&samples, 90),
+                p99_ms: percentile(&samples, 99),
+            }
+        })
+        .collect()
+}
+
+// (step type, ms since the first step) as written by `TraceRecord::dump_json`.
+fn step_offsets(trace: &serde_json::Value) -> Vec<(String, i64)> {
+    let Some(steps) = trace["steps"].as_array() else {
+        return vec![];
+    };
+    steps
+        .iter()
+        .filter_map(|step| {
+            Some((
+                step["type"].as_str()?.to_string(),
+                step["relative_ms"].as_i64()?,
+            ))
+        })
+        .collect()
+}
+
+fn percentile(sorted: &[i64], p: usize) -> i64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let idx = (sorted.len() * p).div_ceil(100).saturating_sub(1);
+    sorted[idx.min(sorted.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(dex_pair: Option<&str>, fired: bool, steps: &[(&str, i64)]) -> PipelineTrace {
+        let steps: Vec<_> = steps
+            .iter()
+            .map(|(step, ms)| json!({ "type": step, "relative_ms": ms }))
+            .collect();
+        PipelineTrace {
+            id: 0,
+            trace_id: String::new(),
+            slot: 0,
+            causing_tx: None,
+            dex_pair: dex_pair.map(str::to_string),
+            fired,
+            last_step: steps.last().unwrap()["type"].as_str().unwrap().to_string(),
+            total_duration_ms: steps.last().unwrap()["relative_ms"].as_i64().unwrap(),
+            trace: json!({ "steps": steps }),
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_groups_latencies_and_exits_by_pair() {
+        let rows = vec![
+            row(
+                Some("PumpAmm-RaydiumV4"),
+                true,
+                &[("A", 0), ("B", 4), ("C", 10)],
+            ),
+            row(Some("PumpAmm-RaydiumV4"), false, &[("A", 0), ("B", 8)]),
+            row(Some("PumpAmm-RaydiumV4"), false, &[("A", 0), ("B", 2)]),
+            row(None, false, &[("A", 0)]),
+        ];
+
+        let report = analyze(&rows);
+        assert_eq!(report.len(), 2);
+
+        let pair = &report[0];
+        assert_eq!(pair.dex_pair, "PumpAmm-RaydiumV4");
+        assert_eq!((pair.traces, pair.fired), (3, 1));
+        assert_eq!(pair.exits, vec![("B".to_string(), 2)]);
+        // fired runs are all kept, the rest sampled, so they are never mixed
+        let a_to_b = &pair.fired_transitions[0];
+        assert_eq!((a_to_b.from.as_str(), a_to_b.to.as_str()), ("A", "B"));
+        assert_eq!((a_to_b.count, a_to_b.p50_ms), (1, 4));
+        assert_eq!(pair.fired_transitions[1].to, "C");
+        let a_to_b = &pair.unfired_transitions[0];
+        assert_eq!((a_to_b.count, a_to_b.p50_ms, a_to_b.p99_ms), (2, 2, 8));
+
+        assert_eq!(report[1].dex_pair, "unknown");
+        assert!(report[1].unfired_transitions.is_empty());
+    }
+
+    #[test]
+    fn test_dex_pair_label_ignores_direction() {
+        assert_eq!(
+            dex_pair_label(DexType::RaydiumV4, DexType::PumpAmm),
+            dex_pair_label(DexType::PumpAmm, DexType::RaydiumV4)
+        );
+    }
+}
@@ -1,29 +1,77 @@
 #![allow(dead_code)]
 
+use crate::convention::chain::stamp::Commitment;
+use crate::global::daemon::chain_clock;
 use crate::global::enums::step_type::StepType;
-use crate::global::trace::types::{Step, Trace};
+use crate::global::trace::types::{Step, Trace, TraceRecord};
+use crate::util::metrics_registry::METRICS_REGISTRY;
 use chrono::Utc;
 use serde::Serialize;
 use serde_json::json;
 use solana_program::pubkey::Pubkey;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 static TRACE_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+tokio::task_local! {
+    static ACTIVE_TRACEPARENT: String;
+}
+
+// `traceparent` of the trace whose `in_scope` future is running, for outgoing requests.
+pub fn active_traceparent() -> Option<String> {
+    ACTIVE_TRACEPARENT.try_with(Clone::clone).ok()
+}
+
 impl Trace {
     pub fn new(slot: u64) -> Self {
         let sequence = TRACE_COUNTER.fetch_add(1, Ordering::SeqCst);
+        Self::with_record(format!("trace_{}", sequence), slot, None, false)
+    }
+
+    // The replaced record has no steps yet, so the sink ignores it.
+    pub fn with_causing_tx(self, signature: Option<String>) -> Self {
+        Self::with_record(self.id.clone(), self.slot, signature, self.from_arrival)
+    }
+
+    // Updates delivered at Confirmed or later show up long after their slot started, so
+    // the deadline budgets, sized for Processed, count from their arrival instead.
+    pub fn at_commitment(mut self, commitment: Commitment) -> Self {
+        self.from_arrival = commitment > Commitment::Processed;
+        self
+    }
+
+    fn with_record(
+        id: String,
+        slot: u64,
+        causing_tx: Option<String>,
+        from_arrival: bool,
+    ) -> Self {
         Self {
-            id: format!("trace_{}", sequence),
+            record: Arc::new(TraceRecord {
+                id: id.clone(),
+                slot,
+                causing_tx: causing_tx.clone(),
+                steps: Mutex::new(Vec::new()),
+                tags: Mutex::new(BTreeMap::new()),
+                otel_trace_id: rand::random(),
+                otel_span_id: rand::random(),
+            }),
+            id,
             slot,
-            steps: Arc::new(Mutex::new(Vec::new())),
+            causing_tx,
+            from_arrival,
         }
     }
 
+    pub fn tag(&self, key: &'static str, value: impl Into<String>) {
+        self.record.tags.lock().unwrap().insert(key, value.into());
+    }
+
     pub fn since_last_step(&self) -> u32 {
-        let steps = self.steps.lock().unwrap();
+        let steps = self.record.steps.lock().unwrap();
         if let Some(last_step) = steps.last() {
             (Utc::now() - last_step.happened_at).num_milliseconds() as u32
         } else {
@@ -32,7 +80,7 @@ impl Trace {
     }
 
     pub fn since_begin(&self) -> u32 {
-        let steps = self.steps.lock().unwrap();
+        let steps = self.record.steps.lock().unwrap();
         if let Some(first_step) = steps.first() {
             (Utc::now() - first_step.happened_at).num_milliseconds() as u32
         } else {
@@ -40,17 +88,62 @@ impl Trace {
         }
     }
 
-    pub fn step(&self, step_type: StepType) {
-        let mut steps = self.steps.lock().unwrap();
+    // Measured on the chain clock from the start of the triggering slot, falling back to
+    // the first step while the clock has not seen a slot yet or for traces that count
+    // from their arrival.
+    pub fn since_slot_start(&self) -> u32 {
+        if self.from_arrival {
+            return self.since_begin();
+        }
+        chain_clock::since_slot_start(self.slot)
+            .map(|elapsed| elapsed.as_millis() as u32)
+            .unwrap_or_else(|| self.since_begin())
+    }
+
+    // W3C `traceparent` value, for requests that should show up under this run.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-01",
+            self.record.otel_trace_id, self.record.otel_span_id
+        )
+    }
+
+    // Requests made while `future` runs carry this trace's `traceparent`.
+    pub async fn in_scope<F: Future>(&self, future: F) -> F::Output {
+        ACTIVE_TRACEPARENT.scope(self.traceparent(), future).await
+    }
+
+    // Every step also feeds the latency histogram of the transition from the previous one.
+    fn push_step(&self, step_type: StepType, attributes: HashMap<String, serde_json::Value>) {
+        let happened_at = Utc::now();
+        let mut steps = self.record.steps.lock().unwrap();
+        if let Some(previous) = steps.last() {
+            let elapsed = (happened_at - previous.happened_at)
+                .to_std()
+                .unwrap_or_default();
+            METRICS_REGISTRY.observe(
+                "pipeline_stage_seconds",
+                &[
+                    ("from", previous.step_type.label()),
+                    ("to", step_type.label()),
+                ],
+                elapsed,
+            );
+        }
         let sequence = steps.len() as u32;
         steps.push(Step {
             sequence,
             step_type,
-            attributes: HashMap::new(),
-            happened_at: Utc::now(),
+            attributes,
+            happened_at,
+            slot_time: chain_clock::slot_time(),
         });
     }
 
+    pub fn step(&self, step_type: StepType) {
+        self.push_step(step_type, HashMap::new());
+    }
+
     pub fn step_with_custom(&self, step_type: &str) {
         self.step(StepType::Custom(step_type.to_string()));
     }
@@ -63,15 +156,7 @@ impl Trace {
     ) {
         let mut attributes = HashMap::new();
         attributes.insert(attr_name.into(), json!(attr_value.to_string()));
-
-        let mut steps = self.steps.lock().unwrap();
-        let sequence = steps.len() as u32;
-        steps.push(Step {
-            sequence,
-            step_type,
-            attributes,
-            happened_at: Utc::now(),
-        });
+        self.push_step(step_type, attributes);
     }
 
     pub fn step_with(
@@ -82,15 +167,7 @@ impl Trace {
     ) {
         let mut attributes = HashMap::new();
         attributes.insert(attr_name.into(), json!(attr_value.into()));
-
-        let mut steps = self.steps.lock().unwrap();
-        let sequence = steps.len() as u32;
-        steps.push(Step {
-            sequence,
-            step_type,
-            attributes,
-            happened_at: Utc::now(),
-        });
+        self.push_step(step_type, attributes);
     }
 
     pub fn step_with_json(
@@ -102,15 +179,7 @@ impl Trace {
         let mut attributes = HashMap::new();
         let converted_value = crate::util::serde_pubkey::to_json_value(&attr_value);
         attributes.insert(attr_name.into(), converted_value);
-
-        let mut steps = self.steps.lock().unwrap();
-        let sequence = steps.len() as u32;
-        steps.push(Step {
-            sequence,
-            step_type,
-            attributes,
-            happened_at: Utc::now(),
-        });
+        self.push_step(step_type, attributes);
     }
 
     pub fn step_with_struct<T: Serialize>(
@@ -124,14 +193,6 @@ impl Trace {
             attr_name.into(),
             crate::util::serde_pubkey::to_json_value(attr_value),
         );
-
-        let mut steps = self.steps.lock().unwrap();
-        let sequence = steps.len() as u32;
-        steps.push(Step {
-            sequence,
-            step_type,
-            attributes,
-            happened_at: Utc::now(),
-        });
+        self.push_step(step_type, attributes);
     }
 }
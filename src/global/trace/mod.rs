@@ -1,2 +1,5 @@
+pub mod analytics;
 pub mod facade;
+pub mod otlp;
+pub mod sink;
 pub mod types;
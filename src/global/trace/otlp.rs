@@ -0,0 +1,232 @@
+use crate::global::trace::sink::is_sampled;
+use crate::global::trace::types::TraceRecord;
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use once_cell::sync::OnceCell;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+const BUFFER_SIZE: usize = 10_000;
+const BATCH_SIZE: usize = 512;
+// Spans arriving meanwhile are sent with the next batch.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+const SPAN_KIND_INTERNAL: u8 = 1;
+
+static EXPORTER: OnceCell<mpsc::Sender<Value>> = OnceCell::new();
+// Queued or being exported.
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+// Set by `flush`, so the exporter stops pacing its requests.
+static FLUSHING: AtomicBool = AtomicBool::new(false);
+
+// Called when the last clone of a trace is dropped. Does nothing until `start`. Exports
+// the same runs the trace sink keeps, fired ones always.
+pub(crate) fn on_finished(record: &TraceRecord) {
+    let Some(sender) = EXPORTER.get() else {
+        return;
+    };
+    if !is_sampled(record) {
+        return;
+    }
+    let Some(span) = span_of(record) else {
+        return;
+    };
+    // counted before the writer can see it, so `flush` never reads a wrapped count
+    PENDING.fetch_add(1, Ordering::Relaxed);
+    if sender.try_send(span).is_err() {
+        PENDING.fetch_sub(1, Ordering::Relaxed);
+        METRICS_REGISTRY.inc_counter("trace_export_dropped_total", &[]);
+    }
+}
+
+// One span per run in the OTLP JSON encoding. Steps become span events; the trace
+// tags, such as pool and mint, become span attributes.
+fn span_of(record: &TraceRecord) -> Option<Value> {
+    let steps = record.steps.lock().ok()?;
+    let (first, last) = (steps.first()?, steps.last()?);
+
+    let mut attributes = vec![
+        attribute("trace.id", json!({ "stringValue": record.id })),
+        attribute(
+            "solana.slot",
+            json!({ "intValue": record.slot.to_string() }),
+        ),
+    ];
+    if let Some(causing_tx) = &record.causing_tx {
+        attributes.push(attribute(
+            "solana.causing_tx",
+            json!({ "stringValue": causing_tx }),
+        ));
+    }
+    if let Ok(tags) = record.tags.lock() {
+        for (key, value) in tags.iter() {
+            attributes.push(attribute(key, json!({ "stringValue": value })));
+        }
+    }
+
+    let events: Vec<Value> = steps
+        .iter()
+        .map(|step| {
+            let mut attributes = vec![attribute(
+                "solana.slot",
+                json!({ "intValue": step.slot_time.slot.to_string() }),
+            )];
+            for (key, value) in &step.attributes {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    other => other.to_string(),
+                };
+                attributes.push(attribute(key, json!({ "stringValue": value })));
+            }
+            json!({
+                "name": step.step_type.label(),
+                "timeUnixNano": unix_nanos(step.happened_at),
+                "attributes": attributes,
+            })
+        })
+        .collect();
+
+    Some(json!({
+        "traceId": format!("{:032x}", record.otel_trace_id),
+        "spanId": format!("{:016x}", record.otel_span_id),
+        "name": "pipeline",
+        "kind": SPAN_KIND_INTERNAL,
+        "startTimeUnixNano": unix_nanos(first.happened_at),
+        "endTimeUnixNano": unix_nanos(last.happened_at),
+        "attributes": attributes,
+        "events": events,
+    }))
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+fn unix_nanos(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.timestamp_nanos_opt().unwrap_or_default().to_string()
+}
+
+fn export_request(spans: Vec<Value>) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", json!({ "stringValue": env!("CARGO_PKG_NAME") })),
+                ],
+            },
+            "scopeSpans": [{
+                "scope": { "name": module_path!() },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+pub fn start() {
+    let Some(endpoint) = ENV_CONFIG.otlp_endpoint.clone() else {
+        return;
+    };
+    let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+    if EXPORTER.set(sender).is_err() {
+        return;
+    }
+    let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+    info!("Exporting fired and sampled trace spans to {}", url);
+    tokio::spawn(export_batches(url, receiver));
+}
+
+async fn export_batches(url: String, mut receiver: mpsc::Receiver<Value>) {
+    let client = Client::builder()
+        .timeout(EXPORT_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while receiver.recv_many(&mut batch, BATCH_SIZE).await > 0 {
+        let spans = batch.len();
+        let body = export_request(std::mem::take(&mut batch));
+        let outcome = match client.post(&url).json(&body).send().await {
+            Ok(response) if response.status().is_success() => "success",
+            Ok(response) => {
+                warn!("Collector rejected {} spans: {}", spans, response.status());
+                "rejected"
+            }
+            Err(e) => {
+                warn!("Failed to export {} spans: {}", spans, e);
+                "error"
+            }
+        };
+        METRICS_REGISTRY.add_counter(
+            "trace_spans_exported_total",
+            &[("outcome", outcome)],
+            spans as u64,
+        );
+        PENDING.fetch_sub(spans, Ordering::Relaxed);
+        if !FLUSHING.load(Ordering::Relaxed) {
+            tokio::time::sleep(EXPORT_INTERVAL).await;
+        }
+    }
+}
+
+// Returns how many spans were still unexported when `timeout` ran out.
+pub async fn flush(timeout: Duration) -> usize {
+    FLUSHING.store(true, Ordering::Relaxed);
+    let started = Instant::now();
+    while PENDING.load(Ordering::Relaxed) > 0 && started.elapsed() < timeout {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    PENDING.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global::enums::step_type::StepType;
+    use crate::global::trace::facade::active_traceparent;
+    use crate::global::trace::types::Trace;
+
+    #[test]
+    fn test_span_carries_steps_as_events() {
+        let trace = Trace::new(42);
+        trace.tag("minor_mint", "Mint111");
+        trace.step(StepType::TradeStrategyStarted);
+        trace.step_with(StepType::MevTxSuppressed, "reason", "cooldown".to_string());
+
+        let span = span_of(&trace.record).unwrap();
+        assert_eq!(span["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(span["spanId"].as_str().unwrap().len(), 16);
+        assert_eq!(
+            span["traceId"],
+            trace.dump_json()["otel_trace_id"].as_str().unwrap()
+        );
+        assert!(trace
+            .traceparent()
+            .contains(span["traceId"].as_str().unwrap()));
+
+        let attributes = span["attributes"].as_array().unwrap();
+        assert!(attributes.contains(&attribute("solana.slot", json!({ "intValue": "42" }))));
+        assert!(attributes.contains(&attribute(
+            "minor_mint",
+            json!({ "stringValue": "Mint111" })
+        )));
+
+        let events = span["events"].as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1]["name"], StepType::MevTxSuppressed.label());
+        assert!(events[1]["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&attribute("reason", json!({ "stringValue": "cooldown" }))));
+    }
+
+    #[tokio::test]
+    async fn test_traceparent_is_active_only_in_scope() {
+        let trace = Trace::new(7);
+        let active = trace.in_scope(async { active_traceparent() }).await;
+        assert_eq!(active, Some(trace.traceparent()));
+        assert_eq!(active_traceparent(), None);
+    }
+}
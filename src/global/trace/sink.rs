@@ -0,0 +1,147 @@
+use crate::database::pipeline_trace::repository::PipelineTraceRepository;
+use crate::database::pipeline_trace::PipelineTraceParams;
+use crate::global::enums::step_type::StepType;
+use crate::global::trace::types::{Step, TraceRecord};
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+const BUFFER_SIZE: usize = 10_000;
+const BATCH_SIZE: usize = 500;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+static SINK: OnceCell<mpsc::Sender<PipelineTraceParams>> = OnceCell::new();
+// Queued or being written.
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+// Runs that reached `MevTxFired` are always kept, the rest at `sample_percent`.
+fn should_persist(fired: bool, sample_percent: f64, roll: f64) -> bool {
+    fired || roll * 100.0 < sample_percent
+}
+
+fn has_fired(steps: &[Step]) -> bool {
+    steps
+        .iter()
+        .any(|step| matches!(step.step_type, StepType::MevTxFired))
+}
+
+// Rolled from the random trace id, so every exporter keeps the same runs.
+fn sample_roll(record: &TraceRecord) -> f64 {
+    (record.otel_trace_id % 1_000_000) as f64 / 1_000_000.0
+}
+
+pub(crate) fn is_sampled(record: &TraceRecord) -> bool {
+    let Ok(steps) = record.steps.lock() else {
+        return false;
+    };
+    should_persist(
+        has_fired(&steps),
+        ENV_CONFIG.trace_sample_percent,
+        sample_roll(record),
+    )
+}
+
+// Called when the last clone of a trace is dropped. Does nothing until `start`.
+pub(crate) fn on_finished(record: &TraceRecord) {
+    let Some(sender) = SINK.get() else {
+        return;
+    };
+    let Ok(steps) = record.steps.lock() else {
+        return;
+    };
+    let (Some(first), Some(last)) = (steps.first(), steps.last()) else {
+        return;
+    };
+    let fired = has_fired(&steps);
+    if !should_persist(fired, ENV_CONFIG.trace_sample_percent, sample_roll(record)) {
+        return;
+    }
+    let last_step = last.step_type.label().to_string();
+    let total_duration_ms = (last.happened_at - first.happened_at).num_milliseconds();
+    drop(steps);
+
+    let params = PipelineTraceParams {
+        trace_id: record.id.clone(),
+        slot: record.slot as i64,
+        causing_tx: record.causing_tx.clone(),
+        dex_pair: record
+            .tags
+            .lock()
+            .ok()
+            .and_then(|tags| tags.get("dex_pair").cloned()),
+        fired,
+        last_step,
+        total_duration_ms,
+        trace: record.dump_json(),
+    };
+    // counted before the writer can see it, so `flush` never reads a wrapped count
+    PENDING.fetch_add(1, Ordering::Relaxed);
+    if sender.try_send(params).is_err() {
+        PENDING.fetch_sub(1, Ordering::Relaxed);
+        METRICS_REGISTRY.inc_counter("trace_sink_dropped_total", &[]);
+    }
+}
+
+pub fn start() {
+    let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+    if SINK.set(sender).is_err() {
+        return;
+    }
+    tokio::spawn(write_batches(receiver));
+    tokio::spawn(prune_expired());
+    info!(
+        "Persisting fired traces and {}% of the rest",
+        ENV_CONFIG.trace_sample_percent
+    );
+}
+
+async fn write_batches(mut receiver: mpsc::Receiver<PipelineTraceParams>) {
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while receiver.recv_many(&mut batch, BATCH_SIZE).await > 0 {
+        let written = batch.len();
+        if let Err(e) = PipelineTraceRepository::insert_many(std::mem::take(&mut batch)).await {
+            error!("Failed to persist traces: {}", e);
+        }
+        PENDING.fetch_sub(written, Ordering::Relaxed);
+    }
+}
+
+async fn prune_expired() {
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let cutoff = Utc::now() - chrono::Duration::days(ENV_CONFIG.trace_retention_days);
+        match PipelineTraceRepository::delete_before(cutoff).await {
+            Ok(deleted) if deleted > 0 => info!("Pruned {} traces before {}", deleted, cutoff),
+            Ok(_) => {}
+            Err(e) => error!("Failed to prune traces: {}", e),
+        }
+    }
+}
+
+// Returns how many traces were still unwritten when `timeout` ran out.
+pub async fn flush(timeout: Duration) -> usize {
+    let started = Instant::now();
+    while PENDING.load(Ordering::Relaxed) > 0 && started.elapsed() < timeout {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    PENDING.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_fired_runs_and_samples_the_rest() {
+        assert!(should_persist(true, 0.0, 0.99));
+        assert!(should_persist(false, 5.0, 0.04));
+        assert!(!should_persist(false, 5.0, 0.05));
+        assert!(!should_persist(false, 0.0, 0.0));
+    }
+}
@@ -1,8 +1,9 @@
+use crate::global::daemon::chain_clock::SlotTime;
 use crate::global::enums::step_type::StepType;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
@@ -12,7 +13,25 @@ pub struct WithTrace<T>(pub T, pub Trace);
 pub struct Trace {
     pub id: String,
     pub slot: u64,
-    pub(crate) steps: Arc<Mutex<Vec<Step>>>,
+    // Signature of the transaction that started this trace, when known.
+    pub causing_tx: Option<String>,
+    // Deadlines count from the first step instead of the slot start.
+    pub(crate) from_arrival: bool,
+    pub(crate) record: Arc<TraceRecord>,
+}
+
+// Shared by every clone of a trace. The last clone going away is when the run is over,
+// so dropping the record hands it to the trace sink.
+pub(crate) struct TraceRecord {
+    pub(crate) id: String,
+    pub(crate) slot: u64,
+    pub(crate) causing_tx: Option<String>,
+    pub(crate) steps: Mutex<Vec<Step>>,
+    // Dimensions for analytics, e.g. the DEX pair the run was about.
+    pub(crate) tags: Mutex<BTreeMap<&'static str, String>>,
+    // OpenTelemetry ids of the span the run is exported as.
+    pub(crate) otel_trace_id: u128,
+    pub(crate) otel_span_id: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,14 +40,28 @@ pub struct Step {
     pub step_type: StepType,
     pub attributes: HashMap<String, serde_json::Value>,
     pub happened_at: DateTime<Utc>,
+    // Chain slot the step happened in, and how far into that slot.
+    #[serde(default)]
+    pub slot_time: SlotTime,
 }
 
 impl Trace {
     pub fn dump_json(&self) -> serde_json::Value {
+        self.record.dump_json()
+    }
+
+    pub fn dump_pretty(&self) -> String {
+        serde_json::to_string_pretty(&self.dump_json()).unwrap()
+    }
+}
+
+impl TraceRecord {
+    pub(crate) fn dump_json(&self) -> serde_json::Value {
         let steps = self.steps.lock().unwrap();
         if steps.is_empty() {
             return json!({
                 "trace_id": self.id,
+                "causing_tx": self.causing_tx,
                 "steps": []
             });
         }
@@ -42,12 +75,11 @@ impl Trace {
 
                 let mut step_json = json!({
                     "sequence": step.sequence,
-                    "type": match &step.step_type {
-                        StepType::Custom(s) => s.as_str(),
-                        other => other.as_ref(),
-                    },
+                    "type": step.step_type.label(),
                     "absolute_time": step.happened_at.to_rfc3339(),
                     "relative_ms": relative_ms,
+                    "chain_slot": step.slot_time.slot,
+                    "slot_offset_ms": step.slot_time.offset_ms,
                 });
 
                 if !step.attributes.is_empty() {
@@ -61,12 +93,17 @@ impl Trace {
         json!({
             "slot": self.slot,
             "trace_id": self.id,
+            "otel_trace_id": format!("{:032x}", self.otel_trace_id),
+            "causing_tx": self.causing_tx,
             "total_duration_ms": (steps.last().unwrap().happened_at - first_timestamp).num_milliseconds(),
             "steps": steps_json,
         })
     }
+}
 
-    pub fn dump_pretty(&self) -> String {
-        serde_json::to_string_pretty(&self.dump_json()).unwrap()
+impl Drop for TraceRecord {
+    fn drop(&mut self) {
+        crate::global::trace::sink::on_finished(self);
+        crate::global::trace::otlp::on_finished(self);
     }
 }
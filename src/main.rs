@@ -3,9 +3,9 @@ pub mod test;
 
 use solana_onchain_arbitrage_bot::global;
 use solana_onchain_arbitrage_bot::pipeline::chain_subscriber::registrar::bootstrap_subscriber;
-use solana_onchain_arbitrage_bot::spawn_with_error_handling;
+use solana_onchain_arbitrage_bot::pipeline::shutdown::shutdown;
 use solana_onchain_arbitrage_bot::util;
-use tracing::info;
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -24,20 +24,40 @@ async fn main() -> anyhow::Result<()> {
     info!("Initializing database connection pool...");
     global::client::db::init_db().await?;
     info!("Database connection pool initialized");
+    global::trace::sink::start();
+    global::trace::otlp::start();
 
-    // Initialize blockhash holder with fresh blockhash
-    info!("Initializing blockhash holder...");
-    global::daemon::blockhash::initialize().await?;
-    info!("Blockhash holder initialized");
+    // Initialize chain clock with fresh slot and blockhash
+    info!("Initializing chain clock...");
+    global::daemon::chain_clock::initialize().await?;
+    info!("Chain clock initialized");
+
+    // Before the streams start, so streamed writes land on top of the restored state
+    global::daemon::state_snapshot::restore().await;
+    global::daemon::state_snapshot::initialize();
+    global::state::pair_cooldown_holder::PairCooldownHolder::restore().await;
+
+    info!("Initializing priority fee holder...");
+    global::daemon::priority_fee::initialize().await?;
+    info!("Priority fee holder initialized");
+
+    global::daemon::metrics_server::initialize()?;
+    util::cron::periodic_logger::spawn_registry_logger(std::time::Duration::from_secs(10));
 
     // 2. Start the SolanaMevBotOnchainListener
-    let listener_handle = spawn_with_error_handling!("Subscriber", bootstrap_subscriber());
+    let ingestion = bootstrap_subscriber();
 
-    // 3. Block until Ctrl+C
+    // 3. Block until Ctrl+C, a second one skips the drain
     info!("Press Ctrl+C to shutdown");
     tokio::signal::ctrl_c().await?;
-    info!("Shutting down...");
-
-    listener_handle.abort();
+    info!("Shutting down, press Ctrl+C again to exit immediately...");
+
+    tokio::select! {
+        _ = shutdown(ingestion) => {}
+        _ = tokio::signal::ctrl_c() => {
+            warn!("Forced exit before the pipeline was drained");
+            std::process::exit(130);
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,71 @@
+use crate::database::pool_record::repository::PoolRecordRepository;
+use crate::global::state::account_balance_holder::refresh_balance_of_account;
+use crate::global::state::account_data_holder::AccountDataHolder;
+use crate::global::state::any_pool_holder::AnyPoolHolder;
+use crate::sdk::yellowstone::GapHandler;
+use futures::stream::{self, StreamExt};
+use futures::FutureExt;
+use std::sync::Arc;
+use tracing::{error, info};
+
+const CONCURRENCY: usize = 32;
+
+// Reloads every recorded pool so quotes stop using state from before the gap.
+// Cached dependent accounts such as bin arrays are dropped and reloaded on demand.
+pub async fn refetch_recorded_pools() {
+    let pools = match PoolRecordRepository::find_all().await {
+        Ok(pools) => pools,
+        Err(e) => {
+            error!("Failed to load recorded pools for refetch: {}", e);
+            return;
+        }
+    };
+
+    AccountDataHolder::invalidate_all();
+    let refreshed = stream::iter(pools)
+        .map(|pool| async move { AnyPoolHolder::fresh_get(&pool.address.0).await.is_ok() })
+        .buffer_unordered(CONCURRENCY)
+        .filter(|ok| futures::future::ready(*ok))
+        .count()
+        .await;
+    info!("Refetched {} recorded pools after stream gap", refreshed);
+}
+
+pub async fn refetch_recorded_vaults() {
+    let pools = match PoolRecordRepository::find_all().await {
+        Ok(pools) => pools,
+        Err(e) => {
+            error!("Failed to load recorded pools for refetch: {}", e);
+            return;
+        }
+    };
+
+    let refreshed = stream::iter(pools)
+        .map(|pool| async move {
+            let Some(config) = AnyPoolHolder::get(&pool.address.0).await else {
+                return 0;
+            };
+            let (base_reserve, quote_reserve) = (
+                config.base_reserve_address(),
+                config.quote_reserve_address(),
+            );
+            let (base_mint, quote_mint) = (config.base_mint(), config.quote_mint());
+            let (base, quote) = tokio::join!(
+                refresh_balance_of_account(&base_reserve, &base_mint),
+                refresh_balance_of_account(&quote_reserve, &quote_mint)
+            );
+            base.is_some() as usize + quote.is_some() as usize
+        })
+        .buffer_unordered(CONCURRENCY)
+        .fold(0, |total, count| async move { total + count })
+        .await;
+    info!("Refetched {} recorded vaults after stream gap", refreshed);
+}
+
+pub fn pool_gap_handler() -> GapHandler {
+    Arc::new(|| refetch_recorded_pools().boxed())
+}
+
+pub fn vault_gap_handler() -> GapHandler {
+    Arc::new(|| refetch_recorded_vaults().boxed())
+}
@@ -2,20 +2,26 @@ use crate::dex::raydium_cpmm::RAYDIUM_CPMM_AUTHORITY;
 use crate::global::constant::pool_program::PoolProgram;
 use crate::global::enums::step_type::StepType;
 use crate::global::trace::types::Trace;
+use crate::pipeline::chain_subscriber::gap_refetch::vault_gap_handler;
 use crate::pipeline::event_processor::involved_account_processor::InvolvedAccountTxProcessor;
-use crate::sdk::yellowstone::{GrpcTransactionUpdate, SolanaGrpcClient, TransactionFilter};
+use crate::sdk::pubsub::SolanaPubsubClient;
+use crate::sdk::yellowstone::{GrpcTransactionUpdate, TransactionFilter};
+use crate::sdk::yellowstone_multi::MultiGrpcClient;
 use crate::unit_ok;
+use crate::util::env::env_config::ENV_CONFIG;
 use anyhow::Result;
 use tracing::info;
 
 pub struct InvolvedAccountSubscriber {
-    client: SolanaGrpcClient,
+    client: MultiGrpcClient,
 }
 
 impl InvolvedAccountSubscriber {
     pub fn new() -> Self {
         Self {
-            client: SolanaGrpcClient::from_env().unwrap(),
+            client: MultiGrpcClient::from_env()
+                .with_commitment(ENV_CONFIG.grpc_transaction_commitment)
+                .with_gap_handler(vault_gap_handler()),
         }
     }
 
@@ -26,17 +32,14 @@ impl InvolvedAccountSubscriber {
         );
 
         self.client
-            .subscribe_transactions(
-                TransactionFilter::new("involved_accounts")
-                    .with_programs(&vec![PoolProgram::PUMP_AMM, RAYDIUM_CPMM_AUTHORITY]),
-                Self::handle_transaction_update,
-                true,
-            )
+            .subscribe_transactions(involved_filter(), Self::handle_transaction_update)
             .await
     }
 
     async fn handle_transaction_update(update: GrpcTransactionUpdate) -> Result<()> {
-        let trace = Trace::new(update.slot);
+        let trace = Trace::new(update.slot)
+            .at_commitment(update.commitment)
+            .with_causing_tx(Some(update.signature.clone()));
 
         trace.step_with(
             StepType::Custom("TransactionReceived".to_string()),
@@ -48,7 +51,25 @@ impl InvolvedAccountSubscriber {
     }
 }
 
+fn involved_filter() -> TransactionFilter {
+    TransactionFilter::new("involved_accounts")
+        .with_programs(&[PoolProgram::PUMP_AMM, RAYDIUM_CPMM_AUTHORITY])
+}
+
 pub async fn start_involved_account_monitor() -> Result<()> {
     let subscriber = InvolvedAccountSubscriber::new();
     subscriber.start().await
 }
+
+// Same transactions over RPC WebSocket, for while no Geyser endpoint is reachable.
+pub async fn start_involved_account_fallback() -> Result<()> {
+    info!("Starting involved account PubSub fallback");
+    SolanaPubsubClient::from_env()
+        .with_commitment(ENV_CONFIG.grpc_transaction_commitment)
+        .with_gap_handler(vault_gap_handler())
+        .subscribe_transactions(
+            involved_filter(),
+            InvolvedAccountSubscriber::handle_transaction_update,
+        )
+        .await
+}
@@ -1,4 +1,7 @@
+pub mod gap_refetch;
 pub mod involved_account_subscriber;
 mod legacy;
 pub mod owner_account_subscriber;
 pub mod registrar;
+pub mod slot_subscriber;
+pub mod supervisor;
@@ -1,43 +1,42 @@
-use crate::global::constant::pool_program::PoolProgram;
+use crate::dex::account_layout::subscribed_account_filters;
 use crate::global::enums::step_type::StepType;
 use crate::global::trace::types::{Trace, WithTrace};
+use crate::pipeline::chain_subscriber::gap_refetch::pool_gap_handler;
 use crate::pipeline::event_processor::owner_account_debouncer::OwnerAccountDebouncer;
-use crate::sdk::yellowstone::{AccountFilter, GrpcAccountUpdate, SolanaGrpcClient};
+use crate::sdk::pubsub::SolanaPubsubClient;
+use crate::sdk::yellowstone::GrpcAccountUpdate;
+use crate::sdk::yellowstone_multi::MultiGrpcClient;
 use crate::unit_ok;
+use crate::util::env::env_config::ENV_CONFIG;
 use anyhow::Result;
 use tracing::{debug, info};
 
 #[allow(unused)]
 pub struct OwnerSubscriber {
-    client: SolanaGrpcClient,
+    client: MultiGrpcClient,
 }
 
 impl OwnerSubscriber {
     pub fn new() -> Self {
         Self {
-            client: SolanaGrpcClient::from_env().unwrap(),
+            client: MultiGrpcClient::from_env()
+                .with_commitment(ENV_CONFIG.grpc_account_commitment)
+                .with_gap_handler(pool_gap_handler()),
         }
     }
 
     pub async fn start(self) -> Result<()> {
         info!("Starting owner account subscriber");
 
-        let filter = AccountFilter::new("meteora_pools").with_owners(&[
-            PoolProgram::METEORA_DLMM,
-            PoolProgram::METEORA_DAMM_V2,
-            PoolProgram::PUMP_AMM,
-            PoolProgram::RAYDIUM_CPMM,
-            PoolProgram::RAYDIUM_CLMM,
-            PoolProgram::WHIRLPOOL,
-        ]);
-
         self.client
-            .subscribe_accounts(filter, Self::handle_account_update, true)
+            .subscribe_accounts(subscribed_account_filters(), Self::handle_account_update)
             .await
     }
 
     async fn handle_account_update(update: GrpcAccountUpdate) -> Result<()> {
-        let trace = Trace::new(update.slot);
+        let trace = Trace::new(update.slot)
+            .at_commitment(update.commitment)
+            .with_causing_tx(update.txn_signature.clone());
         trace.step_with_address(
             StepType::AccountUpdateReceived,
             "account_address",
@@ -54,3 +53,16 @@ pub async fn start_owner_account_monitor() -> Result<()> {
     let monitor = OwnerSubscriber::new();
     monitor.start().await
 }
+
+// Same updates over RPC WebSocket, for while no Geyser endpoint is reachable.
+pub async fn start_owner_account_fallback() -> Result<()> {
+    info!("Starting owner account PubSub fallback");
+    SolanaPubsubClient::from_env()
+        .with_commitment(ENV_CONFIG.grpc_account_commitment)
+        .with_gap_handler(pool_gap_handler())
+        .subscribe_accounts(
+            subscribed_account_filters(),
+            OwnerSubscriber::handle_account_update,
+        )
+        .await
+}
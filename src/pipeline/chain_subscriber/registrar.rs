@@ -1,25 +1,36 @@
 use crate::pipeline::chain_subscriber::involved_account_subscriber::start_involved_account_monitor;
 use crate::pipeline::chain_subscriber::owner_account_subscriber::start_owner_account_monitor;
-use crate::unit_ok;
-use anyhow::Result;
+use crate::pipeline::chain_subscriber::slot_subscriber::start_slot_monitor;
+use crate::pipeline::chain_subscriber::supervisor::supervise_subscribers;
+use tokio::task::JoinHandle;
 use tracing::{error, info};
 
-pub async fn bootstrap_subscriber() -> Result<()> {
+// Returns the handles of the account streams, which shutdown stops first. The slot
+// monitor is left running because the chain clock is needed until the very end.
+pub fn bootstrap_subscriber() -> Vec<JoinHandle<()>> {
     // tokio::spawn(async move {
     //     let _ = start_mev_bot_subscriber().await;
     // });
 
     info!("🚀 Starting pool account monitor");
-    tokio::spawn(async move {
+    let owner_account_monitor = tokio::spawn(async move {
         if let Err(e) = start_owner_account_monitor().await {
             error!("Pool monitor failed: {}", e);
         }
     });
     info!("🚀 Starting involved account monitor");
-    tokio::spawn(async move {
+    let involved_account_monitor = tokio::spawn(async move {
         if let Err(e) = start_involved_account_monitor().await {
             error!("Pool monitor failed: {}", e);
         }
     });
-    unit_ok!()
+    info!("🚀 Starting slot monitor");
+    tokio::spawn(async move {
+        if let Err(e) = start_slot_monitor().await {
+            error!("Slot monitor failed: {}", e);
+        }
+    });
+    info!("🚀 Starting subscriber supervisor");
+    let supervisor = tokio::spawn(supervise_subscribers());
+    vec![owner_account_monitor, involved_account_monitor, supervisor]
 }
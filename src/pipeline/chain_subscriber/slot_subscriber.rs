@@ -0,0 +1,187 @@
+use crate::global::state::account_balance_holder::{
+    refresh_balance_of_account, streamed_balance_stamp,
+};
+use crate::global::state::any_pool_holder::AnyPoolHolder;
+use crate::global::state::slot_state_index::{SlotStateIndex, StateKey};
+use crate::pipeline::event_processor::owner_account_debouncer::forget_last_update;
+use crate::sdk::yellowstone::GrpcSlotUpdate;
+use crate::sdk::yellowstone_multi::MultiGrpcClient;
+use crate::unit_ok;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashSet};
+use tracing::{info, warn};
+use yellowstone_grpc_proto::prelude::SlotStatus;
+
+const CONCURRENCY: usize = 16;
+
+// Follows processed slots and their parents. When a slot is confirmed, every processed
+// slot since the previous confirmation that is not one of its ancestors was skipped.
+#[derive(Default)]
+struct ForkTracker {
+    parents: BTreeMap<u64, Option<u64>>,
+    last_confirmed: u64,
+}
+
+impl ForkTracker {
+    fn on_update(&mut self, update: &GrpcSlotUpdate) -> Vec<u64> {
+        match update.status {
+            SlotStatus::SlotDead => {
+                self.parents.remove(&update.slot);
+                vec![update.slot]
+            }
+            SlotStatus::SlotConfirmed => {
+                self.observe(update);
+                self.confirm(update.slot)
+            }
+            SlotStatus::SlotFinalized => vec![],
+            _ => {
+                self.observe(update);
+                vec![]
+            }
+        }
+    }
+
+    fn observe(&mut self, update: &GrpcSlotUpdate) {
+        if update.slot <= self.last_confirmed {
+            return;
+        }
+        let parent = self.parents.entry(update.slot).or_insert(None);
+        if update.parent.is_some() {
+            *parent = update.parent;
+        }
+    }
+
+    fn confirm(&mut self, confirmed: u64) -> Vec<u64> {
+        if confirmed <= self.last_confirmed {
+            return vec![];
+        }
+
+        let mut ancestors = HashSet::new();
+        let mut floor = confirmed;
+        let mut cursor = Some(confirmed);
+        while let Some(slot) = cursor {
+            if slot <= self.last_confirmed {
+                floor = self.last_confirmed;
+                break;
+            }
+            ancestors.insert(slot);
+            floor = slot;
+            cursor = self.parents.get(&slot).copied().flatten();
+        }
+
+        // below `floor` the ancestry is unknown, so nothing there is declared skipped
+        let skipped = self
+            .parents
+            .range(floor..confirmed)
+            .map(|(slot, _)| *slot)
+            .filter(|slot| !ancestors.contains(slot))
+            .collect();
+
+        self.parents = self.parents.split_off(&(confirmed + 1));
+        self.last_confirmed = confirmed;
+        skipped
+    }
+}
+
+#[allow(non_upper_case_globals)]
+static Forks: Lazy<Mutex<ForkTracker>> = Lazy::new(|| Mutex::new(ForkTracker::default()));
+
+async fn handle_slot_update(update: GrpcSlotUpdate) -> Result<()> {
+    let skipped = Forks.lock().on_update(&update);
+    for slot in skipped {
+        let keys = SlotStateIndex::take(slot);
+        if !keys.is_empty() {
+            tokio::spawn(roll_back(slot, keys));
+        }
+    }
+    if update.status == SlotStatus::SlotConfirmed {
+        SlotStateIndex::settle(update.slot);
+    }
+    unit_ok!()
+}
+
+// Reloads state that was last written by a skipped slot. Entries overwritten since
+// then already come from a live fork and are left alone.
+async fn roll_back(slot: u64, keys: Vec<StateKey>) {
+    warn!(
+        "Slot {} was skipped, rolling back {} states",
+        slot,
+        keys.len()
+    );
+    let reloaded = stream::iter(keys)
+        .map(|key| async move {
+            match key {
+                StateKey::Pool(pool) => {
+                    if AnyPoolHolder::stamp_of(&pool).await.map(|s| s.slot) != Some(slot) {
+                        return false;
+                    }
+                    forget_last_update(&pool).await;
+                    AnyPoolHolder::fresh_get(&pool).await.is_ok()
+                }
+                StateKey::Balance(account, mint) => {
+                    if streamed_balance_stamp(&account, &mint)
+                        .await
+                        .map(|s| s.slot)
+                        != Some(slot)
+                    {
+                        return false;
+                    }
+                    refresh_balance_of_account(&account, &mint).await.is_some()
+                }
+            }
+        })
+        .buffer_unordered(CONCURRENCY)
+        .filter(|ok| futures::future::ready(*ok))
+        .count()
+        .await;
+    info!(
+        "Reloaded {} states built on skipped slot {}",
+        reloaded, slot
+    );
+}
+
+pub async fn start_slot_monitor() -> Result<()> {
+    info!("Starting slot monitor");
+    MultiGrpcClient::from_env()
+        .subscribe_slots(handle_slot_update)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(slot: u64, parent: Option<u64>, status: SlotStatus) -> GrpcSlotUpdate {
+        GrpcSlotUpdate {
+            slot,
+            parent,
+            status,
+        }
+    }
+
+    #[test]
+    fn test_detects_skipped_fork() {
+        let mut tracker = ForkTracker::default();
+        assert!(tracker
+            .on_update(&update(100, Some(99), SlotStatus::SlotConfirmed))
+            .is_empty());
+
+        // 101 is processed on a fork that 102 does not build on
+        tracker.on_update(&update(101, Some(100), SlotStatus::SlotProcessed));
+        tracker.on_update(&update(102, Some(100), SlotStatus::SlotProcessed));
+        tracker.on_update(&update(103, Some(102), SlotStatus::SlotProcessed));
+
+        assert_eq!(
+            tracker.on_update(&update(103, Some(102), SlotStatus::SlotConfirmed)),
+            vec![101]
+        );
+        assert_eq!(
+            tracker.on_update(&update(104, None, SlotStatus::SlotDead)),
+            vec![104]
+        );
+        assert!(tracker.parents.is_empty());
+    }
+}
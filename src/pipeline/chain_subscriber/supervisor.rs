@@ -0,0 +1,153 @@
+use crate::global::daemon::chain_clock;
+use crate::pipeline::chain_subscriber::involved_account_subscriber::start_involved_account_fallback;
+use crate::pipeline::chain_subscriber::owner_account_subscriber::start_owner_account_fallback;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use crate::util::structs::abort_on_drop::AbortOnDrop;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// Geyser counts as down after this long without a slot, which also covers a provider
+// that never came up at startup.
+const GRPC_DOWN_AFTER: Duration = Duration::from_secs(10);
+// The fallback stays until gRPC streamed for this long, so a flapping provider does
+// not toggle it every few seconds.
+const GRPC_BACK_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Switch {
+    ToFallback,
+    ToGrpc,
+}
+
+struct FallbackSwitch {
+    fallback_active: bool,
+    down_since: Option<Instant>,
+    up_since: Option<Instant>,
+}
+
+impl FallbackSwitch {
+    fn new(now: Instant) -> Self {
+        Self {
+            fallback_active: false,
+            down_since: Some(now),
+            up_since: None,
+        }
+    }
+
+    fn observe(&mut self, grpc_live: bool, now: Instant) -> Option<Switch> {
+        if grpc_live {
+            self.down_since = None;
+            self.up_since.get_or_insert(now);
+        } else {
+            self.up_since = None;
+            self.down_since.get_or_insert(now);
+        }
+
+        let held = |since: Option<Instant>, period: Duration| {
+            since.is_some_and(|since| now.saturating_duration_since(since) >= period)
+        };
+        if !self.fallback_active && held(self.down_since, GRPC_DOWN_AFTER) {
+            self.fallback_active = true;
+            return Some(Switch::ToFallback);
+        }
+        if self.fallback_active && held(self.up_since, GRPC_BACK_AFTER) {
+            self.fallback_active = false;
+            return Some(Switch::ToGrpc);
+        }
+        None
+    }
+}
+
+fn spawn_fallback() -> Vec<AbortOnDrop<()>> {
+    vec![
+        AbortOnDrop::new(tokio::spawn(async move {
+            if let Err(e) = start_owner_account_fallback().await {
+                error!("Pool monitor fallback failed: {}", e);
+            }
+        })),
+        AbortOnDrop::new(tokio::spawn(async move {
+            if let Err(e) = start_involved_account_fallback().await {
+                error!("Involved account fallback failed: {}", e);
+            }
+        })),
+    ]
+}
+
+// The gRPC monitors keep reconnecting on their own; this only adds the WebSocket
+// subscribers while they are blind and removes them once gRPC is back. The fallback
+// subscribers stop with this task.
+pub async fn supervise_subscribers() {
+    let mut switch = FallbackSwitch::new(Instant::now());
+    let mut fallback: Vec<AbortOnDrop<()>> = Vec::new();
+    let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+        let grpc_live = chain_clock::stream_is_live();
+        let switched = switch.observe(grpc_live, Instant::now());
+        METRICS_REGISTRY.set_gauge("grpc_stream_live", &[], gauge_flag(grpc_live));
+        METRICS_REGISTRY.set_gauge(
+            "pubsub_fallback_active",
+            &[],
+            gauge_flag(switch.fallback_active),
+        );
+        match switched {
+            Some(Switch::ToFallback) => {
+                warn!(
+                    "No gRPC updates for {:?}, switching to PubSub fallback",
+                    GRPC_DOWN_AFTER
+                );
+                fallback = spawn_fallback();
+            }
+            Some(Switch::ToGrpc) => {
+                info!("gRPC streaming again, stopping PubSub fallback");
+                fallback.clear();
+            }
+            None => {}
+        }
+    }
+}
+
+fn gauge_flag(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switches_after_sustained_outage_and_recovery() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut switch = FallbackSwitch::new(start);
+
+        assert_eq!(switch.observe(true, at(1)), None);
+        assert_eq!(switch.observe(false, at(2)), None);
+        assert_eq!(switch.observe(false, at(11)), None);
+        assert_eq!(switch.observe(false, at(12)), Some(Switch::ToFallback));
+        assert_eq!(switch.observe(false, at(13)), None);
+
+        // a short recovery does not end the fallback
+        assert_eq!(switch.observe(true, at(20)), None);
+        assert_eq!(switch.observe(false, at(21)), None);
+        assert_eq!(switch.observe(true, at(22)), None);
+        assert_eq!(switch.observe(true, at(52)), Some(Switch::ToGrpc));
+    }
+
+    #[test]
+    fn falls_back_when_grpc_never_comes_up() {
+        let start = Instant::now();
+        let mut switch = FallbackSwitch::new(start);
+
+        assert_eq!(
+            switch.observe(false, start + GRPC_DOWN_AFTER),
+            Some(Switch::ToFallback)
+        );
+    }
+}
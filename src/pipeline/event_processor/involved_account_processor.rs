@@ -3,9 +3,10 @@ use crate::database::pool_record::repository::PoolRecordRepository;
 use crate::dex::interface::PoolConfig;
 use crate::dex::pump_amm::config::PumpAmmConfig;
 use crate::global::enums::step_type::StepType;
+use crate::global::state::causing_tx_holder::CausingTxHolder;
 use crate::global::trace::types::{Trace, WithTrace};
 use crate::pipeline::event_processor::new_pool_processor::NewPoolProcessor;
-use crate::pipeline::event_processor::pool_update_processor::PoolUpdateProcessor;
+use crate::pipeline::event_processor::pool_update_processor::publish_pool_update;
 use crate::pipeline::event_processor::structs::trigger::Trigger;
 use crate::pipeline::event_processor::token_balance::token_balance_processor::process_token_balance_change;
 use crate::sdk::yellowstone::GrpcTransactionUpdate;
@@ -38,11 +39,12 @@ pub async fn process_involved_account_transaction(update: TxWithTrace) -> AResul
     );
 
     let tx = update.to_unified()?;
+    CausingTxHolder::record(&tx).await;
     let ixs = tx.all_instructions();
     // here I am going to cache the balance changes:
     // this is for raydium vault
     // and for pump amm vault
-    let _ = process_token_balance_change(tx, &trace).await;
+    let _ = process_token_balance_change(tx, update.commitment, &trace).await;
 
     let pump_pools: HashSet<PoolAddress> = ixs
         .iter()
@@ -56,11 +58,7 @@ pub async fn process_involved_account_transaction(update: TxWithTrace) -> AResul
 
     for pool in pump_pools {
         match PoolRecordRepository::is_pool_recorded(&pool).await {
-            true => {
-                PoolUpdateProcessor
-                    .publish(WithTrace(Trigger::PoolAddress(pool), trace.clone()))
-                    .await?
-            }
+            true => publish_pool_update(Trigger::PoolAddress(pool), trace.clone()).await?,
             false => {
                 NewPoolProcessor
                     .publish(WithTrace(Trigger::PoolAddress(pool), trace.clone()))
@@ -4,16 +4,18 @@ use crate::global::enums::step_type::StepType;
 use crate::global::state::any_pool_holder::AnyPoolHolder;
 use crate::global::trace::types::WithTrace;
 use crate::pipeline::event_processor::structs::trigger::Trigger;
+use crate::sdk::rpc::methods::account::AccountFetchError;
+use crate::sdk::rpc::pool::is_rate_limit_error;
 use crate::util::cache::persistent_cache::PersistentCache;
 use crate::util::structs::cache_type::CacheType;
-use crate::util::structs::rate_limiter::RateLimitError;
 use crate::util::worker::pubsub::{PubSubConfig, PubSubProcessor};
 use crate::{lazy_arc, unit_ok};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use solana_program::pubkey::Pubkey;
 use std::sync::Arc;
+use tracing::debug;
 
 #[allow(non_upper_case_globals)]
 pub static NewPoolProcessor: Lazy<Arc<PubSubProcessor<WithTrace<Trigger>>>> = lazy_arc!({
@@ -35,6 +37,7 @@ pub async fn on_new_pool_received(with_trace: WithTrace<Trigger>) -> anyhow::Res
                 compare.pool(),
                 &compare.current.owner,
                 &*compare.current.data,
+                compare.current.stamp(),
             )
             .await?;
             PoolRecordRepository::ensure_exists(compare.pool()).await;
@@ -47,28 +50,38 @@ pub async fn on_new_pool_received(with_trace: WithTrace<Trigger>) -> anyhow::Res
     unit_ok!()
 }
 
+// Pools that failed over a flaky node are looked at again well before the blocklist expires.
+const QUERY_RETRY_AFTER: TimeDelta = TimeDelta::minutes(5);
+
 async fn record_if_real_pool(addr: &Pubkey) {
-    if NonPoolBlocklist.get(addr).await.is_some() {
-        return;
+    if let Some(entry) = NonPoolBlocklist.get(addr).await {
+        if !entry.is_retryable() {
+            return;
+        }
     }
     let result = AnyPoolHolder::fresh_get(addr).await;
-    match result {
-        Ok(c) => {
-            let _ = PoolRecordRepository::ensure_exists(addr).await;
+    let reason = match result {
+        Ok(_) => match PoolRecordRepository::ensure_exists(addr).await {
+            Some(_) => return,
+            None => BlocklistReason::SaveFailed,
+        },
+        // Throttling says nothing about the account, so the next trigger simply retries.
+        Err(e) if is_rate_limit_error(&e) => {
+            debug!(
+                "Rate limited while checking pool {}, not blocklisting",
+                addr
+            );
+            return;
         }
-        Err(e) => {
-            // if it's not rate limit error, we block it.
-            if e.is::<RateLimitError>() {
-                NonPoolBlocklist
-                    .put(*addr, BlocklistEntry::new(BlocklistReason::QueryFailed))
-                    .await;
-            } else {
-                NonPoolBlocklist
-                    .put(*addr, BlocklistEntry::new(BlocklistReason::SaveFailed))
-                    .await;
-            }
-        }
-    }
+        Err(e) => match e.downcast_ref::<AccountFetchError>() {
+            Some(AccountFetchError::Rpc(_)) => BlocklistReason::QueryFailed,
+            Some(AccountFetchError::NotFound(_)) => BlocklistReason::AccountNotFound,
+            None => BlocklistReason::InvalidPoolData,
+        },
+    };
+    NonPoolBlocklist
+        .put(*addr, BlocklistEntry::new(reason))
+        .await;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +100,11 @@ impl BlocklistEntry {
         }
     }
 
+    fn is_retryable(&self) -> bool {
+        matches!(self.reason, BlocklistReason::QueryFailed)
+            && Utc::now() - self.blocklisted_at > QUERY_RETRY_AFTER
+    }
+
     fn with_data_size(mut self, size: usize) -> Self {
         self.data_size = Some(size);
         self
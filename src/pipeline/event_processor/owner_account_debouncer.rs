@@ -1,24 +1,26 @@
 use crate::convention::chain::AccountState;
 use crate::database::pool_record::repository::PoolRecordRepository;
+use crate::dex::account_layout::{account_kind, AccountKind};
 use crate::global::constant::duration::Interval;
-use crate::global::constant::pool_program::PoolProgram;
 use crate::global::enums::step_type::StepType::{AccountUpdateDebounced, DeterminePoolExists};
 use crate::global::state::account_data_holder::AccountDataHolder;
 use crate::global::trace::types::WithTrace;
 use crate::lazy_arc;
 use crate::pipeline::event_processor::new_pool_processor::NewPoolProcessor;
-use crate::pipeline::event_processor::pool_update_processor::PoolUpdateProcessor;
+use crate::pipeline::event_processor::pool_update_processor::publish_pool_update;
 use crate::pipeline::event_processor::structs::pool_update::AccountComparison;
 use crate::pipeline::event_processor::structs::trigger::Trigger;
 use crate::sdk::yellowstone::GrpcAccountUpdate;
 use crate::util::alias::AResult;
 use crate::util::cache::loading_cache::LoadingCache;
 use crate::util::structs::buffered_debouncer::BufferedDebouncer;
-use crate::util::worker::pubsub::{PubSubConfig, PubSubProcessor};
+use crate::util::worker::pubsub::PubSubConfig;
+use crate::util::worker::sharded_pubsub::ShardedPubSubProcessor;
 use once_cell::sync::Lazy;
 use solana_program::pubkey::Pubkey;
 use std::sync::Arc;
 use std::time::Duration;
+use tracing::trace;
 
 #[allow(non_upper_case_globals)]
 pub static OwnerAccountDebouncer: Lazy<
@@ -32,23 +34,38 @@ async fn send_to_router(update: WithTrace<GrpcAccountUpdate>) {
     AccountUpdateRouteProcessor.publish(update).await.ok();
 }
 
+// Sharded by account so the last-update check and write below never interleave.
 #[allow(non_upper_case_globals)]
-pub static AccountUpdateRouteProcessor: Lazy<Arc<PubSubProcessor<WithTrace<GrpcAccountUpdate>>>> =
-    lazy_arc!({
-        PubSubProcessor::new(
-            PubSubConfig {
-                worker_pool_size: 16,
-                channel_buffer_size: 50000,
-                name: "AccountUpdateRouteProcessor".to_string(),
-            },
-            route_pool_update,
-        )
-    });
+pub static AccountUpdateRouteProcessor: Lazy<
+    Arc<ShardedPubSubProcessor<Pubkey, WithTrace<GrpcAccountUpdate>>>,
+> = lazy_arc!({
+    ShardedPubSubProcessor::new(
+        PubSubConfig {
+            worker_pool_size: 16,
+            channel_buffer_size: 50000,
+            name: "AccountUpdateRouteProcessor".to_string(),
+        },
+        |update: &WithTrace<GrpcAccountUpdate>| update.0.account,
+        route_pool_update,
+    )
+});
 
 async fn route_pool_update(update: WithTrace<GrpcAccountUpdate>) -> AResult<()> {
     let WithTrace(update, trace) = update;
     let previous = LastAccountUpdateCache.get_if_present(&update.account).await;
     let updated = AccountState::from_grpc_update(&update);
+    if let Some(previous) = &previous {
+        if !updated.is_newer_than(previous) {
+            // a slower stream delivered a write we have already moved past
+            trace!(
+                "Dropping stale update of {} at {}:{}",
+                update.account,
+                updated.slot,
+                updated.write_version
+            );
+            return Ok(());
+        }
+    }
     LastAccountUpdateCache
         .put(update.account, updated.clone())
         .await;
@@ -59,27 +76,26 @@ async fn route_pool_update(update: WithTrace<GrpcAccountUpdate>) -> AResult<()>
     };
     trace.step_with_address(AccountUpdateDebounced, "account_address", update.account);
 
-    let recorded = PoolRecordRepository::is_pool_recorded(comparison.pool()).await;
-    trace.step_with(DeterminePoolExists, "account_address", recorded.to_string());
-
-    match update.owner {
-        PoolProgram::METEORA_DLMM => {
-            // this is for caching bin arrays
-            AccountDataHolder::update(comparison.current.pubkey, comparison.current.data.clone())
-                .await;
+    match account_kind(&update.owner, &update.data) {
+        Some(AccountKind::Pool) => {}
+        Some(AccountKind::BinArray) => {
+            // cached for quoting, nothing else to do with it
+            AccountDataHolder::update(
+                comparison.current.pubkey,
+                comparison.current.data.clone(),
+                comparison.current.stamp(),
+            )
+            .await;
+            return Ok(());
         }
-        PoolProgram::METEORA_DAMM_V2 => {}
-        PoolProgram::PUMP_AMM => {}
-        PoolProgram::RAYDIUM_CPMM => {}
-        PoolProgram::RAYDIUM_CLMM => {}
-        PoolProgram::WHIRLPOOL => {}
-        _ => {}
+        None => return Ok(()),
     }
 
+    let recorded = PoolRecordRepository::is_pool_recorded(comparison.pool()).await;
+    trace.step_with(DeterminePoolExists, "account_address", recorded.to_string());
+
     if recorded {
-        let _ = PoolUpdateProcessor
-            .publish(WithTrace(Trigger::AccountCompare(comparison), trace))
-            .await;
+        let _ = publish_pool_update(Trigger::AccountCompare(comparison), trace).await;
     } else {
         let _ = NewPoolProcessor
             .publish(WithTrace(Trigger::AccountCompare(comparison), trace))
@@ -88,6 +104,29 @@ async fn route_pool_update(update: WithTrace<GrpcAccountUpdate>) -> AResult<()>
     Ok(())
 }
 
+// Lets the next streamed write through after its slot was rolled back.
+pub async fn forget_last_update(account: &Pubkey) {
+    LastAccountUpdateCache.invalidate(account).await;
+}
+
+pub fn last_updates() -> Vec<AccountState> {
+    LastAccountUpdateCache
+        .entries()
+        .into_iter()
+        .map(|(_, state)| state)
+        .collect()
+}
+
+// Seeds the last write of an account, unless a newer one was streamed in already.
+pub async fn restore_last_update(state: AccountState) {
+    let previous = LastAccountUpdateCache.get_if_present(&state.pubkey).await;
+    if previous.is_none_or(|previous| state.is_newer_than(&previous)) {
+        LastAccountUpdateCache.put(state.pubkey, state).await;
+    }
+}
+
 #[allow(non_upper_case_globals)]
-static LastAccountUpdateCache: Lazy<LoadingCache<Pubkey, AccountState>> =
-    Lazy::new(|| LoadingCache::with_ttl(10_000_000, Interval::HOUR, |_| async move { None }));
+static LastAccountUpdateCache: Lazy<LoadingCache<Pubkey, AccountState>> = Lazy::new(|| {
+    LoadingCache::with_ttl(10_000_000, Interval::HOUR, |_| async move { None })
+        .named("LastAccountUpdateCache")
+});
@@ -1,29 +1,70 @@
 use crate::global::enums::step_type::StepType;
 use crate::global::state::any_pool_holder::AnyPoolHolder;
-use crate::global::trace::types::WithTrace;
+use crate::global::state::pool_generation_holder::{PoolGeneration, PoolGenerationHolder};
+use crate::global::trace::types::{Trace, WithTrace};
 use crate::pipeline::event_processor::structs::trigger::Trigger;
 use crate::pipeline::trade_strategy::entry::on_pool_update;
+use crate::util::alias::AResult;
 use crate::util::traits::option::OptionExt;
-use crate::util::worker::pubsub::{PubSubConfig, PubSubProcessor};
+use crate::util::worker::pubsub::{DrainOutcome, PubSubConfig};
+use crate::util::worker::sharded_pubsub::ShardedPubSubProcessor;
 use crate::{lazy_arc, unit_ok};
 use once_cell::sync::Lazy;
+use solana_program::pubkey::Pubkey;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
 
+pub struct QueuedPoolUpdate {
+    pub trigger: Trigger,
+    pub generation: PoolGeneration,
+}
+
+// Sharded by pool so an older update can never overwrite a newer one in AnyPoolHolder.
 #[allow(non_upper_case_globals)]
-pub static PoolUpdateProcessor: Lazy<Arc<PubSubProcessor<WithTrace<Trigger>>>> = lazy_arc!({
-    PubSubProcessor::new(
-        PubSubConfig {
-            worker_pool_size: 32,
-            channel_buffer_size: 50000,
-            name: "PoolUpdateProcessor".to_string(),
-        },
-        process_pool_update,
-    )
-});
+static PoolUpdateProcessor: Lazy<Arc<ShardedPubSubProcessor<Pubkey, WithTrace<QueuedPoolUpdate>>>> =
+    lazy_arc!({
+        ShardedPubSubProcessor::new(
+            PubSubConfig {
+                worker_pool_size: 32,
+                channel_buffer_size: 50000,
+                name: "PoolUpdateProcessor".to_string(),
+            },
+            |update: &WithTrace<QueuedPoolUpdate>| *update.0.trigger.pool(),
+            process_pool_update,
+        )
+    });
+
+// Queuing an update supersedes whatever work is still running for the previous ones.
+pub async fn publish_pool_update(trigger: Trigger, trace: Trace) -> AResult<()> {
+    let generation = PoolGenerationHolder::advance(trigger.pool());
+    PoolUpdateProcessor
+        .publish(WithTrace(
+            QueuedPoolUpdate {
+                trigger,
+                generation,
+            },
+            trace,
+        ))
+        .await
+}
+
+// None when no update was ever queued. The processor is only closed once the drain is awaited.
+pub fn drain_pool_updates(
+    timeout: Duration,
+) -> Option<impl Future<Output = DrainOutcome> + Send + 'static> {
+    Lazy::get(&PoolUpdateProcessor).map(|processor| processor.drain(timeout))
+}
 
-pub async fn process_pool_update(update: WithTrace<Trigger>) -> anyhow::Result<()> {
-    let WithTrace(trigger, trace) = update;
+pub async fn process_pool_update(update: WithTrace<QueuedPoolUpdate>) -> anyhow::Result<()> {
+    let WithTrace(
+        QueuedPoolUpdate {
+            trigger,
+            generation,
+        },
+        trace,
+    ) = update;
 
     let pool_addr = *trigger.pool();
     trace.step(StepType::ReceivePoolUpdate);
@@ -35,15 +76,22 @@ pub async fn process_pool_update(update: WithTrace<Trigger>) -> anyhow::Result<(
                 update.pool(),
                 &update.current.owner,
                 &update.current.data,
+                update.current.stamp(),
             )
             .await?;
             info!("Pool data changed for: {}", pool_addr);
-            on_pool_update(pool_addr, updated_config, trace).await;
+            // the state is stored either way, only the strategy run is skipped
+            if !generation.superseded_at("strategy", &trace) {
+                on_pool_update(pool_addr, updated_config, generation, trace).await;
+            }
         }
         Trigger::PoolAddress(addr) => {
             info!("Pool triggered directly for: {}", addr);
+            if generation.superseded_at("strategy", &trace) {
+                return Ok(());
+            }
             let updated_config = AnyPoolHolder::get(&pool_addr).await.or_err("")?;
-            on_pool_update(pool_addr, updated_config, trace).await;
+            on_pool_update(pool_addr, updated_config, generation, trace).await;
         }
     }
 
@@ -40,6 +40,11 @@ impl AccountComparison {
             .unwrap_or(0)
     }
 
+    // Transaction whose write produced the current state.
+    pub fn causing_signature(&self) -> Option<&str> {
+        self.current.txn_signature.as_deref()
+    }
+
     pub fn is_initial(&self) -> bool {
         self.previous.is_none()
     }
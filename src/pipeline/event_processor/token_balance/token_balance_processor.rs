@@ -1,4 +1,6 @@
+use crate::convention::chain::stamp::{Commitment, SlotStamp, Stamped};
 use crate::convention::chain::Transaction;
+use crate::global::state::slot_state_index::{SlotStateIndex, StateKey};
 use crate::global::trace::types::Trace;
 use crate::util::alias::{AResult, MintAddress};
 use crate::util::cache::loading_cache::LoadingCache;
@@ -9,7 +11,7 @@ use once_cell::sync::Lazy;
 use solana_program::pubkey::Pubkey;
 use std::time::Duration;
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct TokenAmount {
     pub amount: u64,
     pub decimals: u8,
@@ -21,17 +23,24 @@ impl TokenAmount {
     }
 }
 #[allow(non_upper_case_globals)]
-pub static TokenBalanceShortLivingCache: Lazy<LoadingCache<(Pubkey, MintAddress), TokenAmount>> =
-    Lazy::new(|| {
-        LoadingCache::with_ttl(
-            10_000_000,
-            Duration::from_secs(3600 * 3), // 3 hours TTL
-            |_| async move { None },
-        )
-    });
+pub static TokenBalanceShortLivingCache: Lazy<
+    LoadingCache<(Pubkey, MintAddress), Stamped<TokenAmount>>,
+> = Lazy::new(|| {
+    LoadingCache::with_ttl(
+        10_000_000,
+        Duration::from_secs(3600 * 3), // 3 hours TTL
+        |_| async move { None },
+    )
+    .named("TokenBalanceShortLivingCache")
+});
 
-pub async fn process_token_balance_change(tx: Transaction, trace: &Trace) -> AResult<()> {
+pub async fn process_token_balance_change(
+    tx: Transaction,
+    commitment: Commitment,
+    trace: &Trace,
+) -> AResult<()> {
     trace.step_with_custom("Tracking Token balance change");
+    let stamp = SlotStamp::new(tx.slot, commitment);
     let balances = tx
         .meta
         .map(|t| t.post_token_balances)
@@ -44,8 +53,9 @@ pub async fn process_token_balance_change(tx: Transaction, trace: &Trace) -> ARe
             amount: t.ui_token_amount.amount.parse::<u64>().unwrap_or(0),
             decimals: t.ui_token_amount.decimals,
         };
+        SlotStateIndex::record(&stamp, StateKey::Balance(owner, mint));
         TokenBalanceShortLivingCache
-            .put((owner, mint), amount)
+            .put((owner, mint), Stamped::new(amount, stamp))
             .await;
     }
 
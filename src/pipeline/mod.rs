@@ -1,4 +1,5 @@
 pub mod chain_subscriber;
 pub mod event_processor;
+pub mod shutdown;
 pub mod trade_strategy;
 pub mod uploader;
@@ -0,0 +1,263 @@
+use crate::global::daemon::state_snapshot;
+use crate::global::trace::{otlp, sink};
+use crate::pipeline::event_processor::involved_account_processor::InvolvedAccountTxProcessor;
+use crate::pipeline::event_processor::mev_bot_processor::MevBotTxProcessor;
+use crate::pipeline::event_processor::new_pool_processor::NewPoolProcessor;
+use crate::pipeline::event_processor::owner_account_debouncer::{
+    AccountUpdateRouteProcessor, OwnerAccountDebouncer,
+};
+use crate::pipeline::event_processor::pool_update_processor::drain_pool_updates;
+use crate::pipeline::uploader::landing_tracker::LandingTrackerHolder;
+use crate::pipeline::uploader::nonce_pool::NoncePoolHolder;
+use crate::pipeline::uploader::provider::sends_in_flight;
+use crate::pipeline::uploader::variables::FireMevBotConsumer;
+use crate::util::worker::pubsub::DrainOutcome;
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+const DEBOUNCE_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+const PROCESSOR_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+// A send takes a few slots to confirm, nonce rotation as long to tell whether it landed.
+const LANDING_TIMEOUT: Duration = Duration::from_secs(8);
+const TRACE_FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+type Drain<'a> = Pin<Box<dyn Future<Output = DrainOutcome> + Send + 'a>>;
+
+// One line of the exit summary.
+#[derive(Debug, Clone)]
+pub struct StageOutcome {
+    pub name: String,
+    pub left: u64,
+    pub waited: Duration,
+}
+
+impl From<DrainOutcome> for StageOutcome {
+    fn from(outcome: DrainOutcome) -> Self {
+        Self {
+            name: outcome.name,
+            left: outcome.pending,
+            waited: outcome.waited,
+        }
+    }
+}
+
+// Stops the account streams, then drains the pipeline front to back so every stage
+// is empty before the one it feeds is closed. Processors that were never used are
+// skipped rather than created.
+pub async fn shutdown(ingestion: Vec<JoinHandle<()>>) -> Vec<StageOutcome> {
+    for handle in ingestion {
+        handle.abort();
+    }
+    let mut stages = Vec::new();
+
+    if let Some(debouncer) = Lazy::get(&OwnerAccountDebouncer) {
+        let started = Instant::now();
+        while debouncer.size() > 0 && started.elapsed() < DEBOUNCE_FLUSH_TIMEOUT {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        stages.push(StageOutcome {
+            name: "OwnerAccountDebouncer".to_string(),
+            left: debouncer.size() as u64,
+            waited: started.elapsed(),
+        });
+    }
+
+    let mut processors: Vec<Drain<'static>> = Vec::new();
+    if let Some(processor) = Lazy::get(&AccountUpdateRouteProcessor) {
+        processors.push(Box::pin(processor.drain(PROCESSOR_DRAIN_TIMEOUT)));
+    }
+    if let Some(processor) = Lazy::get(&InvolvedAccountTxProcessor) {
+        processors.push(Box::pin(processor.drain(PROCESSOR_DRAIN_TIMEOUT)));
+    }
+    if let Some(processor) = Lazy::get(&MevBotTxProcessor) {
+        processors.push(Box::pin(processor.drain(PROCESSOR_DRAIN_TIMEOUT)));
+    }
+    if let Some(processor) = Lazy::get(&NewPoolProcessor) {
+        processors.push(Box::pin(processor.drain(PROCESSOR_DRAIN_TIMEOUT)));
+    }
+    if let Some(drain) = drain_pool_updates(PROCESSOR_DRAIN_TIMEOUT) {
+        processors.push(Box::pin(drain));
+    }
+    if let Some(processor) = Lazy::get(&FireMevBotConsumer) {
+        processors.push(Box::pin(processor.drain(PROCESSOR_DRAIN_TIMEOUT)));
+    }
+    stages.extend(drain_in_order(processors).await);
+
+    // Sends can outlive the drain timeout of the processor that made them, and every
+    // send is waited on until it is confirmed or can no longer land.
+    if Lazy::get(&LandingTrackerHolder).is_some() || sends_in_flight() > 0 {
+        let started = Instant::now();
+        let summary = LandingTrackerHolder.settle(LANDING_TIMEOUT).await;
+        info!(
+            "[Shutdown] Sends: {} landed, {} dropped, {} unknown",
+            summary.landed, summary.dropped, summary.unknown
+        );
+        stages.push(StageOutcome {
+            name: "Landings".to_string(),
+            left: summary.unknown as u64,
+            waited: started.elapsed(),
+        });
+    }
+
+    if let Some(pool) = Lazy::get(&NoncePoolHolder) {
+        let started = Instant::now();
+        let left = pool.settle(LANDING_TIMEOUT).await;
+        stages.push(StageOutcome {
+            name: "NonceRotations".to_string(),
+            left: left as u64,
+            waited: started.elapsed(),
+        });
+    }
+
+    // after the drain, so the snapshot holds the last state the pipeline saw
+    let started = Instant::now();
+    let left = state_snapshot::save_on_shutdown().await;
+    stages.push(StageOutcome {
+        name: "StateSnapshot".to_string(),
+        left: left as u64,
+        waited: started.elapsed(),
+    });
+
+    let started = Instant::now();
+    let left = sink::flush(TRACE_FLUSH_TIMEOUT).await;
+    stages.push(StageOutcome {
+        name: "TraceSink".to_string(),
+        left: left as u64,
+        waited: started.elapsed(),
+    });
+    let started = Instant::now();
+    let left = otlp::flush(TRACE_FLUSH_TIMEOUT).await;
+    stages.push(StageOutcome {
+        name: "TraceExport".to_string(),
+        left: left as u64,
+        waited: started.elapsed(),
+    });
+
+    log_summary(&stages);
+    stages
+}
+
+// Drains run one after another in the order given. A drain only closes its processor
+// once it is polled, so the later stages keep accepting what the earlier ones hand on.
+async fn drain_in_order(drains: Vec<Drain<'_>>) -> Vec<StageOutcome> {
+    let mut stages = Vec::with_capacity(drains.len());
+    for drain in drains {
+        stages.push(drain.await.into());
+    }
+    stages
+}
+
+fn log_summary(stages: &[StageOutcome]) {
+    for stage in stages {
+        if stage.left == 0 {
+            info!(
+                "[Shutdown] {} done in {}ms",
+                stage.name,
+                stage.waited.as_millis()
+            );
+        } else {
+            warn!(
+                "[Shutdown] {} gave up after {}ms with {} left",
+                stage.name,
+                stage.waited.as_millis(),
+                stage.left
+            );
+        }
+    }
+    let lost: u64 = stages.iter().map(|stage| stage.left).sum();
+    info!(
+        "[Shutdown] Complete, {} of {} stages clean, {} items left behind",
+        stages.iter().filter(|stage| stage.left == 0).count(),
+        stages.len(),
+        lost
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::worker::pubsub::{PubSubConfig, PubSubProcessor};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    // Counts what it handled and hands every message on to the next stage.
+    fn stage(
+        name: &str,
+        next: Option<Arc<PubSubProcessor<u32>>>,
+        handled: Arc<AtomicU32>,
+    ) -> Arc<PubSubProcessor<u32>> {
+        let config = PubSubConfig {
+            worker_pool_size: 2,
+            channel_buffer_size: 50,
+            name: name.to_string(),
+        };
+        Arc::new(PubSubProcessor::new(config, move |msg: u32| {
+            let next = next.clone();
+            let handled = handled.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                handled.fetch_add(1, Ordering::Relaxed);
+                if let Some(next) = next {
+                    next.publish(msg).await?;
+                }
+                Ok(())
+            }
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_drains_front_to_back_without_losing_handed_on_messages() {
+        let handled = Arc::new(AtomicU32::new(0));
+        let back = stage("TestShutdownBack", None, handled.clone());
+        let middle = stage("TestShutdownMiddle", Some(back.clone()), Arc::default());
+        let front = stage("TestShutdownFront", Some(middle.clone()), Arc::default());
+
+        for i in 0..10 {
+            front.publish(i).await.unwrap();
+        }
+        let stages = drain_in_order(vec![
+            Box::pin(front.drain(PROCESSOR_DRAIN_TIMEOUT)),
+            Box::pin(middle.drain(PROCESSOR_DRAIN_TIMEOUT)),
+            Box::pin(back.drain(PROCESSOR_DRAIN_TIMEOUT)),
+        ])
+        .await;
+
+        let names: Vec<&str> = stages.iter().map(|stage| stage.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "TestShutdownFront",
+                "TestShutdownMiddle",
+                "TestShutdownBack"
+            ]
+        );
+        assert!(stages.iter().all(|stage| stage.left == 0));
+        assert_eq!(handled.load(Ordering::Relaxed), 10);
+    }
+
+    #[tokio::test]
+    async fn test_closing_the_back_first_loses_what_the_front_hands_on() {
+        let handled = Arc::new(AtomicU32::new(0));
+        let back = stage("TestShutdownReversedBack", None, handled.clone());
+        let front = stage(
+            "TestShutdownReversedFront",
+            Some(back.clone()),
+            Arc::default(),
+        );
+
+        for i in 0..4 {
+            front.publish(i).await.unwrap();
+        }
+        drain_in_order(vec![
+            Box::pin(back.drain(PROCESSOR_DRAIN_TIMEOUT)),
+            Box::pin(front.drain(PROCESSOR_DRAIN_TIMEOUT)),
+        ])
+        .await;
+
+        assert_eq!(handled.load(Ordering::Relaxed), 0);
+    }
+}
@@ -2,27 +2,41 @@ use crate::database::pool_record::repository::PoolRecordRepository;
 use crate::database::pool_record::PoolRecord;
 use crate::dex::any_pool_config::AnyPoolConfig;
 use crate::global::constant::mint::Mints;
+use crate::global::enums::dex_type::DexType;
 use crate::global::enums::step_type::StepType;
 use crate::global::state::any_pool_holder::AnyPoolHolder;
+use crate::global::state::causing_tx_holder::CausingTxHolder;
+use crate::global::state::deadline_policy::{DeadlinePolicyHolder, Stage};
+use crate::global::state::pair_cooldown_holder::PairCooldownHolder;
+use crate::global::state::pool_generation_holder::PoolGeneration;
+use crate::global::trace::analytics::dex_pair_label;
 use crate::global::trace::types::Trace;
+use crate::global::wallet::get_wallet;
 use crate::pipeline::event_processor::token_balance::token_balance_processor::TokenAmount;
+use crate::pipeline::uploader::alt::manager::AltManagerHolder;
+use crate::pipeline::uploader::mev_bot::size_estimator::{check_route_fit, RouteFit};
+use crate::pipeline::uploader::entry::LANDING_CHANNEL;
 use crate::pipeline::uploader::variables::{FireMevBotConsumer, MevBotFire};
 use crate::util::alias::{Lamport, MintAddress, PoolAddress, SOLUnitLamportConvert};
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::structs::cooldown::Suppression;
 use crate::util::structs::mint_pair::MintPair;
 use futures::stream::{self, StreamExt};
 use maplit::hashset;
 use serde_json::json;
+use solana_program::address_lookup_table::AddressLookupTableAccount;
 use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
 use std::collections::HashSet;
 use tracing::{info, trace, warn};
 
 const MAX_OPPORTUNITIES: usize = 2;
-const MAX_PROCESSING_TIME_MS: u32 = 10_000;
 const WSOL_LAMPORTS_PER_SOL: u64 = 1_000_000_000;
 
 pub async fn on_pool_update(
     pool_address: PoolAddress,
     updated_config: AnyPoolConfig,
+    generation: PoolGeneration,
     trace: Trace,
 ) -> Option<()> {
     let mints = updated_config.mint_pair();
@@ -38,9 +52,16 @@ pub async fn on_pool_update(
         return None;
     }
     trace.step_with_address(StepType::TradeStrategyStarted, "pool_address", pool_address);
-
-    if trace.since_begin() > MAX_PROCESSING_TIME_MS {
-        warn!("Skipping opportunity calculation - processing time exceeded");
+    // narrowed to the actual pair once an opportunity is picked
+    trace.tag("dex_pair", format!("{}-*", updated_config.dex_type()));
+    trace.tag("pool_address", pool_address.to_string());
+    trace.tag("minor_mint", minor_mint.to_string());
+
+    if DeadlinePolicyHolder.expired(
+        Stage::Strategy,
+        &[updated_config.dex_type().to_string()],
+        &trace,
+    ) {
         return None;
     }
 
@@ -64,7 +85,10 @@ pub async fn on_pool_update(
         opportunities.len().to_string(),
     );
 
-    execute_opportunities(opportunities, minor_mint, trace).await;
+    if generation.superseded_at("execute", &trace) {
+        return None;
+    }
+    execute_opportunities(opportunities, minor_mint, generation, trace).await;
 
     None
 }
@@ -74,6 +98,8 @@ pub struct ArbitrageResult {
     pub first_pool: PoolAddress,
     pub second_pool: PoolAddress,
     pub profit_lamports: Lamport,
+    pub include_create_token_account_ix: bool,
+    pub dex_types: (DexType, DexType),
 }
 
 async fn find_arbitrage_opportunities(
@@ -103,7 +129,7 @@ async fn find_arbitrage_opportunities(
 
     trace.step_with_custom("Completed arbitrage checks");
 
-    select_best_opportunities(opportunities)
+    select_best_opportunities(opportunities, minor_mint).await
 }
 
 async fn load_related_pools(
@@ -181,6 +207,8 @@ async fn check_bidirectional_arbitrage(
                 first_pool: *changed_pool,
                 second_pool: other_pool.address.0,
                 profit_lamports: profit,
+                include_create_token_account_ix: true,
+                dex_types: (changed_config.dex_type(), other_config.dex_type()),
             });
         }
     }
@@ -194,6 +222,8 @@ async fn check_bidirectional_arbitrage(
                 first_pool: other_pool.address.0,
                 second_pool: *changed_pool,
                 profit_lamports: profit,
+                include_create_token_account_ix: true,
+                dex_types: (other_config.dex_type(), changed_config.dex_type()),
             });
         }
     }
@@ -218,10 +248,18 @@ async fn analyze_pool_depth(
 ) -> Option<PoolDepthAnalysis> {
     const MIN_RESERVE_SOL: f64 = 0.01; // 0.01 SOL minimum
 
-    let (base_token_amount, quote_token_amount) = config.get_reserves().await;
-
-    let base_reserve = base_token_amount?;
-    let quote_reserve = quote_token_amount?;
+    let pool_stamp = AnyPoolHolder::stamp_of(&config.pool_address()).await?;
+    let Some((base_reserve, quote_reserve)) = config
+        .get_consistent_reserves(&pool_stamp, ENV_CONFIG.max_quote_slot_distance)
+        .await
+    else {
+        trace!(
+            "Pool {} reserves are out of step with its state at slot {}",
+            config.pool_address(),
+            pool_stamp.slot
+        );
+        return None;
+    };
 
     // Determine which reserves correspond to WSOL and minor token
     let wsol_reserve = if config.base_mint() == Mints::WSOL {
@@ -343,8 +381,9 @@ async fn simulate_swap(
         .ok()
 }
 
-fn select_best_opportunities(
+async fn select_best_opportunities(
     mut opportunities: Vec<ArbitrageResult>,
+    minor_mint: &MintAddress,
 ) -> Option<Vec<ArbitrageResult>> {
     if opportunities.is_empty() {
         return None;
@@ -353,17 +392,32 @@ fn select_best_opportunities(
     // Sort by profit descending
     opportunities.sort_by(|a, b| b.profit_lamports.cmp(&a.profit_lamports));
 
-    // Deduplicate pool pairs and take top opportunities
+    // Deduplicate pool pairs
     let mut seen_pairs = HashSet::new();
-    let unique_opportunities: Vec<_> = opportunities
+    let candidates: Vec<_> = opportunities
         .into_iter()
         .filter(|result| {
             let pair = (result.first_pool, result.second_pool);
             seen_pairs.insert(pair)
         })
-        .take(MAX_OPPORTUNITIES)
         .collect();
 
+    // Take top opportunities whose transaction can actually be sent
+    let payer = get_wallet().pubkey();
+    let alts = AltManagerHolder.candidates().await;
+    let mut unique_opportunities = Vec::new();
+    for mut opportunity in candidates {
+        if unique_opportunities.len() >= MAX_OPPORTUNITIES {
+            break;
+        }
+        if let Some(include_create_token_account_ix) =
+            route_fit(&payer, minor_mint, &opportunity, &alts).await
+        {
+            opportunity.include_create_token_account_ix = include_create_token_account_ix;
+            unique_opportunities.push(opportunity);
+        }
+    }
+
     if unique_opportunities.is_empty() {
         None
     } else {
@@ -371,15 +425,71 @@ fn select_best_opportunities(
     }
 }
 
+async fn route_fit(
+    payer: &Pubkey,
+    minor_mint: &MintAddress,
+    opportunity: &ArbitrageResult,
+    alts: &[AddressLookupTableAccount],
+) -> Option<bool> {
+    let configs: Vec<_> =
+        AnyPoolHolder::batch_get(&[opportunity.first_pool, opportunity.second_pool])
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+
+    match check_route_fit(payer, minor_mint, &configs, alts, &LANDING_CHANNEL).await {
+        Ok(fit) => {
+            if let RouteFit::TooLarge(estimate) = fit {
+                info!(
+                    "Dropping route {} -> {}: {} bytes, {} account locks",
+                    opportunity.first_pool,
+                    opportunity.second_pool,
+                    estimate.size,
+                    estimate.account_locks
+                );
+            }
+            fit.include_create_token_account_ix()
+        }
+        Err(e) => {
+            warn!(
+                "Failed to estimate route {} -> {}: {}",
+                opportunity.first_pool, opportunity.second_pool, e
+            );
+            None
+        }
+    }
+}
+
 async fn execute_opportunities(
     opportunities: Vec<ArbitrageResult>,
     minor_mint: MintAddress,
+    generation: PoolGeneration,
     trace: Trace,
 ) {
+    if let Some(best) = opportunities.first() {
+        attribute_to_causing_tx(&trace);
+        trace.tag(
+            "dex_pair",
+            dex_pair_label(best.dex_types.0, best.dex_types.1),
+        );
+    }
+
     for (i, opportunity) in opportunities.iter().enumerate() {
         let trace = trace.clone();
         let pools_for_mev = vec![opportunity.first_pool, opportunity.second_pool];
 
+        if let Suppression::Suppressed { reason, until } =
+            PairCooldownHolder::state(&minor_mint, &pools_for_mev)
+        {
+            trace.step_with(StepType::MevTxSuppressed, "reason", reason.to_string());
+            info!(
+                "Skipping opportunity {} -> {} until {}: {}",
+                opportunity.first_pool, opportunity.second_pool, until, reason
+            );
+            continue;
+        }
+
         info!(
             "🚀 MEV Opportunity #{}: {} -> {} (profit: {} SOL)",
             i + 1,
@@ -395,16 +505,36 @@ async fn execute_opportunities(
                 {
                     "path": pools_for_mev,
                     "profit": opportunity.profit_lamports.to_literal(),
+                    "caused_by": trace.causing_tx,
                 }
             ),
         );
 
-        let _ = FireMevBotConsumer
-            .publish(MevBotFire {
-                minor_mint,
-                pools: pools_for_mev,
-                trace,
-            })
-            .await;
+        FireMevBotConsumer.publish(MevBotFire {
+            minor_mint,
+            pools: pools_for_mev,
+            include_create_token_account_ix: opportunity.include_create_token_account_ix,
+            expected_profit: opportunity.profit_lamports,
+            dex_types: vec![opportunity.dex_types.0, opportunity.dex_types.1],
+            generation,
+            trace,
+        });
     }
 }
+
+// Resolves the swap behind the update off the hot path; the step lands in the trace
+// before the simulation or landing result is logged in most cases.
+fn attribute_to_causing_tx(trace: &Trace) {
+    let Some(signature) = trace.causing_tx.clone() else {
+        return;
+    };
+    let trace = trace.clone();
+    tokio::spawn(async move {
+        match CausingTxHolder::resolve(&signature).await {
+            Some(causing_tx) => {
+                trace.step_with_struct(StepType::CausingTxResolved, "causing_tx", &causing_tx)
+            }
+            None => warn!("Failed to resolve causing transaction {}", signature),
+        }
+    });
+}
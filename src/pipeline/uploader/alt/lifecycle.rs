@@ -0,0 +1,61 @@
+use crate::global::daemon::chain_clock::get_blockhash;
+use crate::sdk::rpc::methods::block::get_slot;
+use crate::sdk::rpc::methods::transaction::{compile_instruction_to_tx, send_transaction};
+use crate::util::alias::AResult;
+use solana_program::address_lookup_table::instruction::{
+    close_lookup_table, create_lookup_table, deactivate_lookup_table, extend_lookup_table,
+};
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use tracing::info;
+
+// Keeps the extend transaction comfortably below the packet limit.
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+pub const MAX_ADDRESSES_PER_TABLE: usize = 256;
+// A deactivated table can only be closed once its deactivation slot has left SlotHashes.
+pub const DEACTIVATION_COOLDOWN_SLOTS: u64 = 513;
+
+pub async fn create_table(wallet: &Keypair) -> AResult<Pubkey> {
+    let recent_slot = get_slot(CommitmentConfig::finalized()).await?;
+    let (ix, table) = create_lookup_table(wallet.pubkey(), wallet.pubkey(), recent_slot);
+    let signature = send(wallet, vec![ix]).await?;
+    info!("Created lookup table {} in {}", table, signature);
+    Ok(table)
+}
+
+pub async fn extend_table(
+    wallet: &Keypair,
+    table: &Pubkey,
+    addresses: Vec<Pubkey>,
+) -> AResult<Signature> {
+    let count = addresses.len();
+    let ix = extend_lookup_table(*table, wallet.pubkey(), Some(wallet.pubkey()), addresses);
+    let signature = send(wallet, vec![ix]).await?;
+    info!(
+        "Extended lookup table {} with {} addresses in {}",
+        table, count, signature
+    );
+    Ok(signature)
+}
+
+pub async fn deactivate_table(wallet: &Keypair, table: &Pubkey) -> AResult<u64> {
+    let slot = get_slot(CommitmentConfig::confirmed()).await?;
+    let ix = deactivate_lookup_table(*table, wallet.pubkey());
+    let signature = send(wallet, vec![ix]).await?;
+    info!("Deactivated lookup table {} in {}", table, signature);
+    Ok(slot)
+}
+
+pub async fn close_table(wallet: &Keypair, table: &Pubkey) -> AResult<Signature> {
+    let ix = close_lookup_table(*table, wallet.pubkey(), wallet.pubkey());
+    let signature = send(wallet, vec![ix]).await?;
+    info!("Closed lookup table {} in {}", table, signature);
+    Ok(signature)
+}
+
+async fn send(wallet: &Keypair, instructions: Vec<Instruction>) -> AResult<Signature> {
+    let tx = compile_instruction_to_tx(wallet, instructions, &[], get_blockhash().await?)?;
+    send_transaction(&tx).await
+}
@@ -0,0 +1,365 @@
+#![allow(non_upper_case_globals)]
+use crate::convention::chain::util::alt::{get_alt, AltCache};
+use crate::global::constant::duration::Interval;
+use crate::global::wallet::get_wallet;
+use crate::lined_err;
+use crate::pipeline::uploader::alt::lifecycle::{
+    close_table, create_table, deactivate_table, extend_table, DEACTIVATION_COOLDOWN_SLOTS,
+    MAX_ADDRESSES_PER_EXTEND, MAX_ADDRESSES_PER_TABLE,
+};
+use crate::sdk::rpc::methods::block::get_slot;
+use crate::util::alias::AResult;
+use crate::util::cache::persistent_cache::PersistentCache;
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::structs::cache_type::CacheType;
+use futures::future::join_all;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::address_lookup_table::AddressLookupTableAccount;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::interval;
+use tracing::{error, info, warn};
+
+// Shared table we do not own; kept as a candidate for the common program accounts.
+pub const SHARED_ALT: Pubkey = pubkey!("4sKLJ1Qoudh8PJyqBeuKocYdsZvxTcRShUt9aKqwhgvC");
+
+const HOT_ACCOUNT_THRESHOLD: u32 = 3;
+const RETIRE_AFTER: Duration = Interval::DAY;
+const MAINTAIN_INTERVAL: Duration = Interval::MINUTE;
+// A new table is only used once it can be loaded, polled for this long after creation.
+const CREATE_CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+const CREATE_CONFIRM_POLL: Duration = Duration::from_secs(2);
+// Persisted tables that failed to load this many maintenance rounds in a row are dropped.
+const MAX_LOAD_FAILURES: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnedTable {
+    pub address: Pubkey,
+    pub deactivated_at_slot: Option<u64>,
+}
+
+struct ManagedTable {
+    addresses: HashSet<Pubkey>,
+    last_used: Instant,
+    deactivated_at_slot: Option<u64>,
+    load_failures: u32,
+}
+
+impl ManagedTable {
+    fn new(deactivated_at_slot: Option<u64>) -> Self {
+        Self {
+            addresses: HashSet::new(),
+            last_used: Instant::now(),
+            deactivated_at_slot,
+            load_failures: 0,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.deactivated_at_slot.is_none()
+    }
+}
+
+pub struct AltManager {
+    tables: RwLock<HashMap<Pubkey, ManagedTable>>,
+    key_index: RwLock<HashMap<Pubkey, Pubkey>>,
+    usage: RwLock<HashMap<Pubkey, u32>>,
+}
+
+impl AltManager {
+    fn new() -> Self {
+        Self {
+            tables: RwLock::new(HashMap::new()),
+            key_index: RwLock::new(HashMap::new()),
+            usage: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn candidates(&self) -> Vec<AddressLookupTableAccount> {
+        let keys: Vec<Pubkey> = std::iter::once(SHARED_ALT)
+            .chain(
+                self.tables
+                    .read()
+                    .iter()
+                    .filter(|(_, table)| table.is_active())
+                    .map(|(key, _)| *key),
+            )
+            .collect();
+
+        join_all(keys.iter().map(get_alt))
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    pub fn table_of(&self, key: &Pubkey) -> Option<Pubkey> {
+        self.key_index.read().get(key).copied()
+    }
+
+    // Counts every account that still had to be inlined and marks the tables the
+    // compiled transaction actually used, so idle tables can be retired.
+    pub fn record_usage(&self, tx: &VersionedTransaction) {
+        let message = &tx.message;
+        let header = message.header();
+        let static_keys = message.static_account_keys();
+        let program_ids: HashSet<Pubkey> = message
+            .instructions()
+            .iter()
+            .filter_map(|ix| static_keys.get(ix.program_id_index as usize))
+            .copied()
+            .collect();
+
+        {
+            let mut usage = self.usage.write();
+            for key in static_keys
+                .iter()
+                .skip(header.num_required_signatures as usize)
+                .filter(|key| !program_ids.contains(key))
+            {
+                *usage.entry(*key).or_insert(0) += 1;
+            }
+        }
+
+        if let Some(lookups) = message.address_table_lookups() {
+            let mut tables = self.tables.write();
+            for lookup in lookups {
+                if let Some(table) = tables.get_mut(&lookup.account_key) {
+                    table.last_used = Instant::now();
+                }
+            }
+        }
+    }
+
+    async fn maintain(&self, wallet: &Keypair) -> AResult<()> {
+        if self.refresh_tables().await {
+            self.persist_owned(&wallet.pubkey()).await;
+        }
+        if !ENV_CONFIG.enable_send_tx {
+            return Ok(());
+        }
+        self.extend_with_hot_accounts(wallet).await?;
+        self.retire_unused(wallet).await
+    }
+
+    async fn load_owned(&self, wallet: &Pubkey) {
+        let owned = OwnedTablesCache
+            .get(&wallet.to_string())
+            .await
+            .unwrap_or_default();
+        let mut tables = self.tables.write();
+        for table in owned {
+            tables.insert(table.address, ManagedTable::new(table.deactivated_at_slot));
+        }
+        info!("Loaded {} owned lookup tables", tables.len());
+    }
+
+    async fn persist_owned(&self, wallet: &Pubkey) {
+        let owned: Vec<OwnedTable> = self
+            .tables
+            .read()
+            .iter()
+            .map(|(address, table)| OwnedTable {
+                address: *address,
+                deactivated_at_slot: table.deactivated_at_slot,
+            })
+            .collect();
+        OwnedTablesCache.put(wallet.to_string(), owned).await;
+    }
+
+    // On-chain state is the source of truth: extensions that never landed must not
+    // stay in the index, otherwise those keys would never be retried. Tables that keep
+    // failing to load are forgotten, returns true when that happened.
+    async fn refresh_tables(&self) -> bool {
+        let keys: Vec<Pubkey> = self.tables.read().keys().copied().collect();
+        for key in &keys {
+            AltCache.invalidate(key).await;
+        }
+        let fetched = join_all(keys.iter().map(get_alt)).await;
+
+        let mut tables = self.tables.write();
+        let mut key_index = self.key_index.write();
+        key_index.clear();
+        let mut dropped = false;
+        for (key, result) in keys.iter().zip(fetched) {
+            let Some(table) = tables.get_mut(key) else {
+                continue;
+            };
+            match result {
+                Ok(alt) => {
+                    table.load_failures = 0;
+                    table.addresses = alt.addresses.iter().copied().collect();
+                    for address in &alt.addresses {
+                        key_index.insert(*address, *key);
+                    }
+                }
+                Err(e) => {
+                    table.load_failures += 1;
+                    warn!("Failed to refresh lookup table {}: {}", key, e);
+                    if table.load_failures >= MAX_LOAD_FAILURES {
+                        warn!("Forgetting lookup table {} that does not load", key);
+                        tables.remove(key);
+                        dropped = true;
+                    }
+                }
+            }
+        }
+        dropped
+    }
+
+    // The create transaction is not confirmed by sending it, so the table is only
+    // adopted once it can actually be loaded.
+    async fn wait_until_loadable(table: &Pubkey) -> bool {
+        let started = Instant::now();
+        while started.elapsed() < CREATE_CONFIRM_TIMEOUT {
+            tokio::time::sleep(CREATE_CONFIRM_POLL).await;
+            AltCache.invalidate(table).await;
+            if get_alt(table).await.is_ok() {
+                return true;
+            }
+        }
+        false
+    }
+
+    async fn extend_with_hot_accounts(&self, wallet: &Keypair) -> AResult<()> {
+        let shared: HashSet<Pubkey> = get_alt(&SHARED_ALT)
+            .await
+            .map(|alt| alt.addresses.into_iter().collect())
+            .unwrap_or_default();
+
+        let hot: Vec<Pubkey> = {
+            let mut usage = self.usage.write();
+            let key_index = self.key_index.read();
+            let hot = usage
+                .iter()
+                .filter(|(key, count)| {
+                    **count >= HOT_ACCOUNT_THRESHOLD
+                        && !key_index.contains_key(key)
+                        && !shared.contains(key)
+                })
+                .map(|(key, _)| *key)
+                .collect();
+            // decay so that accounts of pools we stopped trading fall out over time
+            usage.retain(|_, count| {
+                *count /= 2;
+                *count > 0
+            });
+            hot
+        };
+
+        for chunk in hot.chunks(MAX_ADDRESSES_PER_EXTEND) {
+            let table = match self.table_with_room(chunk.len()) {
+                Some(table) => table,
+                None => {
+                    let table = create_table(wallet).await?;
+                    if !Self::wait_until_loadable(&table).await {
+                        return Err(lined_err!("lookup table {} did not land", table));
+                    }
+                    self.tables.write().insert(table, ManagedTable::new(None));
+                    self.persist_owned(&wallet.pubkey()).await;
+                    table
+                }
+            };
+
+            extend_table(wallet, &table, chunk.to_vec()).await?;
+
+            let mut tables = self.tables.write();
+            let mut key_index = self.key_index.write();
+            if let Some(managed) = tables.get_mut(&table) {
+                managed.addresses.extend(chunk.iter().copied());
+                managed.last_used = Instant::now();
+            }
+            for key in chunk {
+                key_index.insert(*key, table);
+            }
+        }
+        Ok(())
+    }
+
+    fn table_with_room(&self, needed: usize) -> Option<Pubkey> {
+        self.tables
+            .read()
+            .iter()
+            .filter(|(_, table)| {
+                table.is_active() && table.addresses.len() + needed <= MAX_ADDRESSES_PER_TABLE
+            })
+            .max_by_key(|(_, table)| table.addresses.len())
+            .map(|(key, _)| *key)
+    }
+
+    async fn retire_unused(&self, wallet: &Keypair) -> AResult<()> {
+        let (idle, deactivated): (Vec<Pubkey>, Vec<(Pubkey, u64)>) = {
+            let tables = self.tables.read();
+            let idle = tables
+                .iter()
+                .filter(|(_, table)| table.is_active() && table.last_used.elapsed() > RETIRE_AFTER)
+                .map(|(key, _)| *key)
+                .collect();
+            let deactivated = tables
+                .iter()
+                .filter_map(|(key, table)| table.deactivated_at_slot.map(|slot| (*key, slot)))
+                .collect();
+            (idle, deactivated)
+        };
+
+        if idle.is_empty() && deactivated.is_empty() {
+            return Ok(());
+        }
+
+        for table in idle {
+            let slot = deactivate_table(wallet, &table).await?;
+            if let Some(managed) = self.tables.write().get_mut(&table) {
+                managed.deactivated_at_slot = Some(slot);
+            }
+        }
+
+        let current_slot = get_slot(CommitmentConfig::confirmed()).await?;
+        for (table, deactivated_at) in deactivated {
+            if current_slot > deactivated_at + DEACTIVATION_COOLDOWN_SLOTS {
+                close_table(wallet, &table).await?;
+                self.tables.write().remove(&table);
+                AltCache.invalidate(&table).await;
+            }
+        }
+
+        self.persist_owned(&wallet.pubkey()).await;
+        Ok(())
+    }
+}
+
+pub static AltManagerHolder: Lazy<Arc<AltManager>> = Lazy::new(|| {
+    let manager = Arc::new(AltManager::new());
+    let manager_clone = manager.clone();
+
+    tokio::spawn(async move {
+        let wallet = get_wallet();
+        manager_clone.load_owned(&wallet.pubkey()).await;
+
+        let mut interval = interval(MAINTAIN_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = manager_clone.maintain(&wallet).await {
+                error!("Lookup table maintenance error: {}", e);
+            }
+        }
+    });
+
+    manager
+});
+
+static OwnedTablesCache: Lazy<PersistentCache<String, Vec<OwnedTable>>> = Lazy::new(|| {
+    PersistentCache::new(
+        CacheType::Custom("owned_lookup_tables".to_string()),
+        16,
+        86400 * 365, // owned tables stay relevant until we close them
+        |_wallet: String| async move { None },
+    )
+});
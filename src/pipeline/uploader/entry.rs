@@ -1,44 +1,55 @@
-use crate::convention::chain::util::alt::get_alt_batch;
 use crate::convention::chain::util::simulation::SimulationResult;
 use crate::dex::any_pool_config::AnyPoolConfig;
 use crate::global::constant::mint::Mints;
-use crate::global::daemon::blockhash::get_blockhash;
+use crate::global::daemon::chain_clock::get_blockhash;
+use crate::global::daemon::priority_fee::recommend_priority_fee;
 use crate::global::enums::step_type::StepType;
 use crate::global::state::any_pool_holder::AnyPoolHolder;
+use crate::global::state::pair_cooldown_holder::PairCooldownHolder;
+use crate::global::state::pool_generation_holder::PoolGeneration;
 use crate::global::trace::types::Trace;
 use crate::global::wallet::get_wallet;
+use crate::pipeline::uploader::alt::manager::AltManagerHolder;
 use crate::pipeline::uploader::common::debug;
 use crate::pipeline::uploader::common::simulation_log::log_mev_simulation;
+use crate::pipeline::uploader::landing_tracker::LandingTrackerHolder;
 use crate::pipeline::uploader::mev_bot::construct;
 use crate::pipeline::uploader::mev_bot::construct::compute_limit_ix;
 use crate::pipeline::uploader::mev_bot::sender::simulate_mev_tx;
-use crate::pipeline::uploader::provider::LandingChannel;
-use crate::pipeline::uploader::variables::{MevBotDeduplicator, MevBotRateLimiter};
+use crate::pipeline::uploader::nonce_pool::{NonceLease, NoncePoolHolder};
+use crate::pipeline::uploader::provider::{LandingChannel, SentTx};
+use crate::pipeline::uploader::variables::MevBotDeduplicator;
 use crate::sdk::rpc::methods::transaction::compile_instruction_to_tx;
 use crate::unit_ok;
-use crate::util::alias::{AResult, Literal, SOLUnitLamportConvert, SOLUnitLiteralConvert};
+use crate::util::alias::{AResult, Lamport, Literal, SOLUnitLamportConvert, SOLUnitLiteralConvert};
 use crate::util::env::env_config::ENV_CONFIG;
-use crate::util::traits::pubkey::ToPubkey;
+use crate::util::structs::cooldown::Suppression;
 use construct::build_mev_ix;
 use debug::print_log_to_console;
 use serde_json::json;
+use solana_program::instruction::Instruction;
 use solana_program::pubkey::Pubkey;
 use solana_sdk::signature::Keypair;
 use solana_sdk::signer::Signer;
+use std::time::Duration;
 use tracing::warn;
 
-pub async fn fire_mev_bot(minor_mint: &Pubkey, pools: &Vec<Pubkey>, trace: Trace) -> AResult<()> {
-    if !MevBotDeduplicator.can_send(minor_mint, pools) {
-        warn!(
-            "Duplicate transaction detected for mint {} with pools {:?}, skipping (backoff period active)",
-            minor_mint,
-            pools
-        );
-        return Ok(());
-    }
+pub const COMPUTE_LIMIT: u32 = 300_000;
+// Every opportunity goes out through this channel, routes are sized for it as well.
+pub const LANDING_CHANNEL: LandingChannel = LandingChannel::Jito;
 
-    if !MevBotRateLimiter.try_acquire() {
-        warn!("MEV bot rate limit exceeded, skipping execution");
+pub async fn fire_mev_bot(
+    minor_mint: &Pubkey,
+    pools: &Vec<Pubkey>,
+    include_create_token_account_ix: bool,
+    generation: PoolGeneration,
+    trace: Trace,
+) -> AResult<()> {
+    // Already checked when the message was picked, this only catches a duplicate fired meanwhile.
+    let Some(dedup_window) = pair_dedup_window(minor_mint, pools) else {
+        return Ok(());
+    };
+    if generation.superseded_at("build", &trace) {
         return Ok(());
     }
     trace.step(StepType::MevTxFired);
@@ -51,50 +62,130 @@ pub async fn fire_mev_bot(minor_mint: &Pubkey, pools: &Vec<Pubkey>, trace: Trace
         .collect();
     trace.step(StepType::MevTxReadyToBuild);
 
-    build_and_send(
-        &wallet,
-        minor_mint,
-        300_000,
-        &configs,
-        true,
-        LandingChannel::Jito,
-        trace,
-    )
-    .await
-    .map(|result| print_log_to_console(result.0, &wallet.pubkey(), result.1))?;
+    let nonce = NoncePoolHolder.lease();
+    if nonce.is_none() && NoncePoolHolder.is_enabled() {
+        warn!("No durable nonce available, falling back to recent blockhash");
+    }
+
+    let channel = LANDING_CHANNEL;
+    let channel_name = channel.as_ref().to_string();
+    let context = SendContext {
+        nonce: nonce.as_ref(),
+        channel,
+        generation: &generation,
+        dedup_window,
+        trace: trace.clone(),
+    };
+    let result = trace
+        .in_scope(build_and_send(
+            &wallet,
+            minor_mint,
+            COMPUTE_LIMIT,
+            &configs,
+            include_create_token_account_ix,
+            context,
+        ))
+        .await;
+    // errors before the transaction is handed to the channel leave the nonce unused
+    let sent = match &result {
+        Ok(Some(fired)) => fired.sent.clone(),
+        _ => None,
+    };
+    if let Some(sent) = &sent {
+        LandingTrackerHolder.track(&channel_name, sent.clone(), nonce.is_some());
+    }
+    if let Some(nonce) = nonce {
+        // the rotation settles durable nonce sends, which never expire on their own
+        NoncePoolHolder.release(nonce, sent.is_some(), move |landed| {
+            if let Some(sent) = sent {
+                LandingTrackerHolder.resolve(&sent.signature, landed);
+            }
+        });
+    }
+    let Some(fired) = result? else {
+        return Ok(());
+    };
+    PairCooldownHolder::record(minor_mint, pools, &fired.simulation);
+    print_log_to_console(fired.simulation, &wallet.pubkey(), fired.trace);
     unit_ok!()
 }
 
+// Window the pair must not be simulated again within, or None while it is cooling down or
+// was already simulated within it. Only checked here, the pair is marked once its
+// transaction is actually simulated.
+pub fn pair_dedup_window(minor_mint: &Pubkey, pools: &[Pubkey]) -> Option<Duration> {
+    let dedup_window = match PairCooldownHolder::state(minor_mint, pools) {
+        Suppression::Allowed { dedup_window } => dedup_window.to_std().ok()?,
+        Suppression::Suppressed { reason, until } => {
+            warn!(
+                "Pair for mint {} with pools {:?} is cooling down until {}: {}",
+                minor_mint, pools, until, reason
+            );
+            return None;
+        }
+    };
+    if !MevBotDeduplicator.check_within(minor_mint, pools, dedup_window) {
+        warn!(
+            "Duplicate transaction detected for mint {} with pools {:?}, skipping (backoff period active)",
+            minor_mint,
+            pools
+        );
+        return None;
+    }
+    Some(dedup_window)
+}
+
+// How one opportunity goes out, as opposed to what the transaction does.
+pub struct SendContext<'a> {
+    pub nonce: Option<&'a NonceLease>,
+    pub channel: LandingChannel,
+    pub generation: &'a PoolGeneration,
+    // The same pair is not simulated again within this window.
+    pub dedup_window: Duration,
+    pub trace: Trace,
+}
+
+pub struct Fired {
+    pub simulation: SimulationResult,
+    pub trace: Trace,
+    // None when the transaction was only simulated.
+    pub sent: Option<SentTx>,
+}
+
 pub async fn build_and_send(
     wallet: &Keypair,
     minor_mint: &Pubkey,
     compute_unit_limit: u32,
     pools: &[AnyPoolConfig],
     include_create_token_account_ix: bool,
-    channel: LandingChannel,
-    trace: Trace,
-) -> AResult<(SimulationResult, Trace)> {
+    context: SendContext<'_>,
+) -> AResult<Option<Fired>> {
+    let SendContext {
+        nonce,
+        channel,
+        generation,
+        dedup_window,
+        trace,
+    } = context;
     trace.step(StepType::MevIxBuilding);
-    let (mut instructions, _limit) = compute_limit_ix(compute_unit_limit);
-
-    let (tip_or_unite_price_ix, tip) = channel.tip_ix(&wallet.pubkey(), 30_000);
-    instructions.extend(tip_or_unite_price_ix);
-
-    let minimum_profit = (0.00001 as Literal + tip).to_lamport();
-    let mev_ix = build_mev_ix(
-        wallet,
+    let (instructions, minimum_profit) = build_instructions(
+        &wallet.pubkey(),
         minor_mint,
         compute_unit_limit,
         pools,
-        minimum_profit,
-        false,
         include_create_token_account_ix,
+        nonce,
+        &channel,
     )
     .await?;
-    instructions.extend(mev_ix);
 
-    let alts = get_alt_batch(&["4sKLJ1Qoudh8PJyqBeuKocYdsZvxTcRShUt9aKqwhgvC".to_pubkey()]).await?;
-    let tx = compile_instruction_to_tx(wallet, instructions, &alts, get_blockhash().await?)?;
+    let blockhash = match nonce {
+        Some(nonce) => nonce.nonce,
+        None => get_blockhash().await?,
+    };
+    let alts = AltManagerHolder.candidates().await;
+    let tx = compile_instruction_to_tx(wallet, instructions, &alts, blockhash)?;
+    AltManagerHolder.record_usage(&tx);
     trace.step_with_struct(
         StepType::MevIxBuilt,
         "params",
@@ -103,12 +194,30 @@ pub async fn build_and_send(
         }),
     );
 
-    let simulation_result = simulate_mev_tx(&tx, &trace).await?;
-    if simulation_result.err.is_none() {
-        if ENV_CONFIG.enable_send_tx {
-            channel.send_tx(&tx, &trace).await?;
-        }
+    if generation.superseded_at("simulation", &trace) {
+        return Ok(None);
+    }
+    let pool_addresses: Vec<Pubkey> = pools.iter().map(|pool| pool.pool_address()).collect();
+    if !MevBotDeduplicator.can_send_within(minor_mint, &pool_addresses, dedup_window) {
+        warn!(
+            "Transaction for mint {} with pools {:?} already simulated, skipping",
+            minor_mint, pool_addresses
+        );
+        return Ok(None);
     }
+    let dexes: Vec<String> = pools
+        .iter()
+        .map(|pool| pool.dex_type().to_string())
+        .collect();
+    let simulation_result = simulate_mev_tx(&tx, &dexes, &trace).await?;
+    let sent = if simulation_result.err.is_none()
+        && ENV_CONFIG.enable_send_tx
+        && !generation.superseded_at("send", &trace)
+    {
+        Some(channel.send_tx(&tx, &trace).await?)
+    } else {
+        None
+    };
 
     let _ = log_mev_simulation(
         &simulation_result,
@@ -121,5 +230,58 @@ pub async fn build_and_send(
     )
     .await;
 
-    Ok((simulation_result, trace))
+    Ok(Some(Fired {
+        simulation: simulation_result,
+        trace,
+        sent,
+    }))
+}
+
+async fn build_instructions(
+    payer: &Pubkey,
+    minor_mint: &Pubkey,
+    compute_unit_limit: u32,
+    pools: &[AnyPoolConfig],
+    include_create_token_account_ix: bool,
+    nonce: Option<&NonceLease>,
+    channel: &LandingChannel,
+) -> AResult<(Vec<Instruction>, Lamport)> {
+    let mut instructions: Vec<Instruction> = nonce
+        .map(|nonce| nonce.advance_ix(payer))
+        .into_iter()
+        .collect();
+
+    let (limit_ix, limit) = compute_limit_ix(compute_unit_limit);
+    instructions.extend(limit_ix);
+
+    let writable_accounts: Vec<Pubkey> = pools
+        .iter()
+        .flat_map(|pool| {
+            [
+                pool.pool_address(),
+                pool.base_reserve_address(),
+                pool.quote_reserve_address(),
+            ]
+        })
+        .collect();
+    let priority_fee = recommend_priority_fee(&writable_accounts).await?;
+    let (tip_or_unite_price_ix, tip) = channel.tip_ix(payer, &priority_fee);
+    instructions.extend(tip_or_unite_price_ix);
+
+    // the priority fee is charged whether or not the arbitrage pays, so it has to be covered too
+    let priority_fee_cost = channel.unit_price(&priority_fee) * limit as Lamport / 1_000_000;
+    let minimum_profit = (0.00001 as Literal + tip).to_lamport() + priority_fee_cost;
+    let mev_ix = build_mev_ix(
+        payer,
+        minor_mint,
+        compute_unit_limit,
+        pools,
+        minimum_profit,
+        false,
+        include_create_token_account_ix,
+    )
+    .await?;
+    instructions.extend(mev_ix);
+
+    Ok((instructions, minimum_profit))
 }
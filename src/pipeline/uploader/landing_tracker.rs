@@ -0,0 +1,177 @@
+#![allow(non_upper_case_globals)]
+use crate::global::daemon::chain_clock::current_slot;
+use crate::global::state::deadline_policy::DeadlinePolicyHolder;
+use crate::pipeline::uploader::provider::{sends_in_flight, SentTx};
+use crate::sdk::rpc::methods::transaction::get_signature_statuses;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use solana_sdk::clock::MAX_PROCESSING_AGE;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// getSignatureStatuses takes at most this many signatures per call.
+const STATUS_BATCH: usize = 256;
+// Slots past the blockhash expiry the cluster may still report a landing in.
+const EXPIRY_MARGIN_SLOTS: u64 = 10;
+
+struct PendingLanding {
+    channel: String,
+    sent: SentTx,
+    // None for durable nonce sends, whose rotation tells when they can no longer land.
+    expires_after: Option<u64>,
+}
+
+// How the sends waited on at shutdown turned out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LandingSummary {
+    pub landed: usize,
+    pub dropped: usize,
+    pub unknown: usize,
+}
+
+// Polls the status of every sent transaction until it is confirmed or can no longer
+// land, and feeds the outcome to the deadline policy by the age it was sent at.
+pub struct LandingTracker {
+    pending: Mutex<HashMap<Signature, PendingLanding>>,
+    landed: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+impl LandingTracker {
+    fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+            landed: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn track(&self, channel: &str, sent: SentTx, durable_nonce: bool) {
+        let expires_after = (!durable_nonce)
+            .then(|| current_slot() + MAX_PROCESSING_AGE as u64 + EXPIRY_MARGIN_SLOTS);
+        self.pending.lock().insert(
+            sent.signature,
+            PendingLanding {
+                channel: channel.to_string(),
+                sent,
+                expires_after,
+            },
+        );
+    }
+
+    // For sends whose outcome is learned elsewhere, a no-op once polling settled them.
+    pub fn resolve(&self, signature: &Signature, landed: bool) {
+        let Some(pending) = self.pending.lock().remove(signature) else {
+            return;
+        };
+        self.record(&pending, landed);
+    }
+
+    // Waits for sends still being made and for tracked ones to get a status. Those
+    // without one by the timeout are counted as unknown.
+    pub async fn settle(&self, timeout: Duration) -> LandingSummary {
+        let started = Instant::now();
+        let landed = self.landed.load(Ordering::Relaxed);
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        while sends_in_flight() > 0 && started.elapsed() < timeout {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        LandingSummary {
+            landed: self.landed.load(Ordering::Relaxed) - landed,
+            dropped: self.dropped.load(Ordering::Relaxed) - dropped,
+            unknown: sends_in_flight(),
+        }
+    }
+
+    fn record(&self, pending: &PendingLanding, landed: bool) {
+        let counter = if landed { &self.landed } else { &self.dropped };
+        counter.fetch_add(1, Ordering::Relaxed);
+        DeadlinePolicyHolder.record_landing(&pending.channel, pending.sent.age_ms, landed);
+        METRICS_REGISTRY.inc_counter(
+            "landings_total",
+            &[
+                ("channel", pending.channel.as_str()),
+                ("outcome", if landed { "landed" } else { "dropped" }),
+            ],
+        );
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    async fn poll(&self) {
+        let signatures: Vec<Signature> = self.pending.lock().keys().copied().collect();
+        let slot = current_slot();
+        for chunk in signatures.chunks(STATUS_BATCH) {
+            let statuses = match get_signature_statuses(chunk).await {
+                Ok(statuses) => statuses,
+                Err(e) => {
+                    warn!("Failed to poll landing statuses: {}", e);
+                    return;
+                }
+            };
+            for (signature, status) in chunk.iter().zip(statuses) {
+                let confirmed = status.is_some_and(|status| {
+                    status.satisfies_commitment(CommitmentConfig::confirmed())
+                });
+                let mut pending = self.pending.lock();
+                let settled = pending
+                    .get(signature)
+                    .and_then(|entry| outcome(confirmed, entry.expires_after, slot));
+                if let Some(landed) = settled {
+                    if let Some(entry) = pending.remove(signature) {
+                        self.record(&entry, landed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// None while the transaction can still land.
+fn outcome(confirmed: bool, expires_after: Option<u64>, slot: u64) -> Option<bool> {
+    if confirmed {
+        return Some(true);
+    }
+    expires_after
+        .is_some_and(|expires_after| slot > expires_after)
+        .then_some(false)
+}
+
+pub static LandingTrackerHolder: Lazy<Arc<LandingTracker>> = Lazy::new(|| {
+    let tracker = Arc::new(LandingTracker::new());
+    let tracker_clone = tracker.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if tracker_clone.pending() > 0 {
+                tracker_clone.poll().await;
+            }
+        }
+    });
+    tracker
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_waits_for_confirmation_or_expiry() {
+        assert_eq!(outcome(true, Some(100), 50), Some(true));
+        assert_eq!(outcome(false, Some(100), 100), None);
+        assert_eq!(outcome(false, Some(100), 101), Some(false));
+        // durable nonce sends never expire by slot
+        assert_eq!(outcome(false, None, u64::MAX), None);
+        assert_eq!(outcome(true, None, 0), Some(true));
+    }
+}
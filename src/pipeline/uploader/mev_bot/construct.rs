@@ -11,11 +11,10 @@ use anyhow::Result;
 use solana_program::instruction::Instruction;
 use solana_program::pubkey::Pubkey;
 use solana_sdk::compute_budget::ComputeBudgetInstruction;
-use solana_sdk::signature::{Keypair, Signer};
 use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
 
 pub async fn build_mev_ix(
-    wallet: &Keypair,
+    payer: &Pubkey,
     minor_mint: &Pubkey,
     compute_unit_limit: u32,
     pools: &[AnyPoolConfig],
@@ -31,14 +30,14 @@ pub async fn build_mev_ix(
 
     if include_create_token_account_ix {
         instructions.push(ensure_token_account_exists(
-            &wallet.pubkey(),
+            payer,
             minor_mint,
             &mint_token_program,
         ))
     }
 
     let swap_ix = create_invoke_mev_instruction(
-        &wallet.pubkey(),
+        payer,
         minor_mint,
         &mint_token_program,
         compute_unit_limit,
@@ -1,4 +1,5 @@
 pub mod construct;
 pub mod sender;
+pub mod size_estimator;
 #[cfg(test)]
 mod test;
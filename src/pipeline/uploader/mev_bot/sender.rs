@@ -1,23 +1,22 @@
 use crate::convention::chain::util::simulation::SimulationResult;
 use crate::global::enums::step_type::StepType;
+use crate::global::state::deadline_policy::{DeadlinePolicyHolder, Stage};
 use crate::global::trace::types::Trace;
 use crate::return_error;
 use crate::sdk::rpc::methods::simulation::simulate_transaction_with_config;
+use crate::util::metrics_registry::METRICS_REGISTRY;
 use solana_client::rpc_config::RpcSimulateTransactionConfig;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::transaction::VersionedTransaction;
 use solana_transaction_status::UiTransactionEncoding;
-use tracing::info;
-
+// `dexes` are the DEXes on the route, each may have its own budget.
 pub async fn simulate_mev_tx(
     tx: &VersionedTransaction,
+    dexes: &[String],
     trace: &Trace,
 ) -> anyhow::Result<SimulationResult> {
-    if trace.since_begin() > 300 {
-        info!(
-            "Gave up on simulation tx because it takes {} milliseconds from trigger to now",
-            trace.since_begin()
-        );
+    if DeadlinePolicyHolder.expired(Stage::Simulation, dexes, trace) {
+        record_simulation("gave_up");
         return_error!("Gave up");
     }
     trace.step(StepType::MevSimulationTxRpcCall);
@@ -36,9 +35,19 @@ pub async fn simulate_mev_tx(
             inner_instructions: false,
         },
     )
-    .await?;
+    .await
+    .inspect_err(|_| record_simulation("rpc_error"))?;
     let result = SimulationResult::from(&response.value);
     trace.step(StepType::MevSimulationTxRpcReturned);
+    record_simulation(if result.err.is_none() {
+        "success"
+    } else {
+        "failed"
+    });
 
     Ok(result)
 }
+
+fn record_simulation(outcome: &str) {
+    METRICS_REGISTRY.inc_counter("simulations_total", &[("outcome", outcome)]);
+}
@@ -0,0 +1,187 @@
+use crate::convention::chain::util::alt::select_minimal_alts;
+use crate::database::mint_record::repository::MintRecordRepository;
+use crate::dex::any_pool_config::AnyPoolConfig;
+use crate::global::daemon::priority_fee::PriorityFee;
+use crate::global::state::account_data_holder::AccountDataHolder;
+use crate::pipeline::uploader::entry::COMPUTE_LIMIT;
+use crate::pipeline::uploader::mev_bot::construct::{build_mev_ix, compute_limit_ix};
+use crate::pipeline::uploader::nonce_pool::NoncePoolHolder;
+use crate::pipeline::uploader::provider::LandingChannel;
+use crate::util::alias::{AResult, MintAddress};
+use crate::util::solana::pda::ata;
+use solana_program::address_lookup_table::AddressLookupTableAccount;
+use solana_program::hash::Hash;
+use solana_program::instruction::Instruction;
+use solana_program::message::v0::Message;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::signature::SIGNATURE_BYTES;
+
+// Conservative runtime limit on accounts a single transaction may lock.
+pub const MAX_ACCOUNT_LOCKS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxSizeEstimate {
+    pub size: usize,
+    pub account_locks: usize,
+}
+
+impl TxSizeEstimate {
+    pub fn fits(&self) -> bool {
+        self.size <= PACKET_DATA_SIZE && self.account_locks <= MAX_ACCOUNT_LOCKS
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteFit {
+    Fits(TxSizeEstimate),
+    // Only fits when the idempotent ATA creation is dropped; the ATA already exists.
+    FitsWithoutCreateTokenAccount(TxSizeEstimate),
+    TooLarge(TxSizeEstimate),
+}
+
+impl RouteFit {
+    pub fn include_create_token_account_ix(&self) -> Option<bool> {
+        match self {
+            RouteFit::Fits(_) => Some(true),
+            RouteFit::FitsWithoutCreateTokenAccount(_) => Some(false),
+            RouteFit::TooLarge(_) => None,
+        }
+    }
+}
+
+// Compiles the message exactly like `compile_instruction_to_tx` but without signing,
+// so the result matches the serialized size of the transaction we would send.
+pub fn estimate_instructions(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    alts: &[AddressLookupTableAccount],
+) -> AResult<TxSizeEstimate> {
+    let alts = select_minimal_alts(payer, instructions, alts);
+    let message = Message::try_compile(payer, instructions, &alts, Hash::default())?;
+
+    let signatures = message.header.num_required_signatures as usize;
+    let loaded: usize = message
+        .address_table_lookups
+        .iter()
+        .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+        .sum();
+    let account_locks = message.account_keys.len() + loaded;
+    let message_size = bincode::serialize(&VersionedMessage::V0(message))?.len();
+
+    Ok(TxSizeEstimate {
+        size: short_vec_len(signatures) + signatures * SIGNATURE_BYTES + message_size,
+        account_locks,
+    })
+}
+
+// Lays the route out like `build_instructions`, with the idempotent ATA creation, and
+// estimates it with and without that instruction. The compute budget and tip
+// instructions have a fixed size whatever the fee, so stand-ins take the place of the
+// priority fee lookup.
+pub async fn check_route_fit(
+    payer: &Pubkey,
+    minor_mint: &MintAddress,
+    pools: &[AnyPoolConfig],
+    alts: &[AddressLookupTableAccount],
+    channel: &LandingChannel,
+) -> AResult<RouteFit> {
+    let mut instructions: Vec<Instruction> = NoncePoolHolder
+        .sizing_lease()
+        .map(|nonce| nonce.advance_ix(payer))
+        .into_iter()
+        .collect();
+    instructions.extend(compute_limit_ix(COMPUTE_LIMIT).0);
+    instructions.extend(channel.tip_ix(payer, &PriorityFee::DEFAULT).0);
+    let mev_ix = build_mev_ix(payer, minor_mint, COMPUTE_LIMIT, pools, 0, false, true).await?;
+    instructions.extend(mev_ix);
+    let full = estimate_instructions(payer, &instructions, alts)?;
+    if full.fits() {
+        return Ok(RouteFit::Fits(full));
+    }
+
+    // the ATA creation is the only instruction of the associated token program
+    let lean_instructions: Vec<Instruction> = instructions
+        .into_iter()
+        .filter(|ix| ix.program_id != spl_associated_token_account::ID)
+        .collect();
+    let lean = estimate_instructions(payer, &lean_instructions, alts)?;
+    if lean.fits() && token_account_exists(payer, minor_mint).await? {
+        return Ok(RouteFit::FitsWithoutCreateTokenAccount(lean));
+    }
+
+    Ok(RouteFit::TooLarge(full))
+}
+
+async fn token_account_exists(payer: &Pubkey, minor_mint: &MintAddress) -> AResult<bool> {
+    let token_program = MintRecordRepository::get_mint_or_err(minor_mint)
+        .await?
+        .program
+        .0;
+    Ok(
+        AccountDataHolder::get_account_data(&ata(payer, minor_mint, &token_program))
+            .await
+            .is_some(),
+    )
+}
+
+fn short_vec_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdk::rpc::methods::transaction::compile_instruction_to_tx;
+    use solana_program::instruction::AccountMeta;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    fn instruction_with(accounts: usize) -> Instruction {
+        Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: (0..accounts)
+                .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+                .collect(),
+            data: vec![0; 24],
+        }
+    }
+
+    #[test]
+    fn test_estimate_matches_serialized_tx() {
+        let wallet = Keypair::new();
+        let instructions = vec![instruction_with(10), instruction_with(5)];
+        let alt = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: instructions[0]
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect(),
+        };
+
+        let estimate =
+            estimate_instructions(&wallet.pubkey(), &instructions, std::slice::from_ref(&alt))
+                .unwrap();
+        let tx = compile_instruction_to_tx(&wallet, instructions, &[alt], Hash::default()).unwrap();
+
+        assert_eq!(estimate.size, bincode::serialize(&tx).unwrap().len());
+        // payer + 5 inlined accounts + 2 programs + 10 loaded from the table
+        assert_eq!(estimate.account_locks, 18);
+        assert!(estimate.fits());
+    }
+
+    #[test]
+    fn test_estimate_detects_oversized_tx() {
+        let payer = Pubkey::new_unique();
+        let instructions = vec![instruction_with(40)];
+
+        let estimate = estimate_instructions(&payer, &instructions, &[]).unwrap();
+        assert!(estimate.size > PACKET_DATA_SIZE);
+        assert!(!estimate.fits());
+    }
+}
@@ -1,5 +1,8 @@
+pub mod alt;
 pub mod common;
 pub mod entry;
+pub mod landing_tracker;
 pub mod mev_bot;
+pub mod nonce_pool;
 pub mod provider;
 pub mod variables;
@@ -0,0 +1,236 @@
+#![allow(non_upper_case_globals)]
+use crate::global::daemon::chain_clock::{current_slot, get_blockhash, wait_until_slot};
+use crate::global::wallet::get_wallet;
+use crate::lined_err;
+use crate::sdk::rpc::methods::account::get_account_unbatched;
+use crate::sdk::rpc::methods::transaction::{compile_instruction_to_tx, send_transaction};
+use crate::util::alias::AResult;
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::traits::pubkey::ToPubkey;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use solana_program::hash::Hash;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction::advance_nonce_account;
+use solana_sdk::nonce::state::{State, Versions};
+use solana_sdk::signature::Signer;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+// Slots given to in-flight variants to land before we advance the nonce ourselves.
+const ROTATION_GRACE_SLOTS: u64 = 5;
+const ROTATION_GRACE_TIMEOUT: Duration = Duration::from_secs(4);
+const ROTATION_ATTEMPTS: usize = 10;
+// A lease is only handed out again once its nonce moved, failed rotations are retried
+// a few times before the account is taken out of the pool.
+const ROTATION_RETRY_DELAY: Duration = Duration::from_secs(5);
+const ROTATION_RETRIES: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct NonceLease {
+    pub account: Pubkey,
+    pub nonce: Hash,
+}
+
+impl NonceLease {
+    // Must be the first instruction of the transaction.
+    pub fn advance_ix(&self, authority: &Pubkey) -> Instruction {
+        advance_nonce_account(&self.account, authority)
+    }
+}
+
+struct NonceSlot {
+    nonce: Option<Hash>,
+    leased: bool,
+}
+
+pub struct NoncePool {
+    slots: Mutex<HashMap<Pubkey, NonceSlot>>,
+    // Sent leases whose landing is not known yet.
+    rotating: AtomicUsize,
+}
+
+impl NoncePool {
+    fn new(accounts: Vec<Pubkey>) -> Self {
+        Self {
+            slots: Mutex::new(
+                accounts
+                    .into_iter()
+                    .map(|account| {
+                        (
+                            account,
+                            NonceSlot {
+                                nonce: None,
+                                leased: false,
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+            rotating: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.slots.lock().is_empty()
+    }
+
+    // One lease per sent transaction, its nonce is only handed out again once it is
+    // known whether that transaction landed.
+    pub fn lease(&self) -> Option<NonceLease> {
+        let mut slots = self.slots.lock();
+        let (account, slot) = slots
+            .iter_mut()
+            .find(|(_, slot)| !slot.leased && slot.nonce.is_some())?;
+        slot.leased = true;
+        Some(NonceLease {
+            account: *account,
+            nonce: slot.nonce?,
+        })
+    }
+
+    // Stand-in with the same accounts as a real lease, for transaction sizing.
+    pub fn sizing_lease(&self) -> Option<NonceLease> {
+        self.slots.lock().keys().next().map(|account| NonceLease {
+            account: *account,
+            nonce: Hash::default(),
+        })
+    }
+
+    // A lease that was never sent can be reused as is. Otherwise the nonce has to move
+    // before it is handed out again, or a late variant could still land. `on_rotated`
+    // learns whether a variant landed, i.e. the nonce moved without our help. It is
+    // told "not landed" when that cannot be known anymore.
+    pub fn release(
+        self: &Arc<Self>,
+        lease: NonceLease,
+        sent: bool,
+        on_rotated: impl FnOnce(bool) + Send + 'static,
+    ) {
+        if !sent {
+            self.make_available(&lease.account, Some(lease.nonce));
+            return;
+        }
+
+        let sent_slot = current_slot();
+        let pool = self.clone();
+        self.rotating.fetch_add(1, Ordering::Relaxed);
+        tokio::spawn(async move {
+            let mut advanced = false;
+            for attempt in 1..=ROTATION_RETRIES {
+                match pool.rotate(&lease, sent_slot, &mut advanced).await {
+                    Ok((nonce, landed)) => {
+                        on_rotated(landed);
+                        pool.make_available(&lease.account, Some(nonce));
+                        pool.rotating.fetch_sub(1, Ordering::Relaxed);
+                        return;
+                    }
+                    Err(e) => {
+                        error!("Failed to rotate nonce account {}: {}", lease.account, e);
+                        if attempt < ROTATION_RETRIES {
+                            sleep(ROTATION_RETRY_DELAY).await;
+                        }
+                    }
+                }
+            }
+            error!(
+                "Nonce account {} did not rotate after {} attempts, removing it from the pool",
+                lease.account, ROTATION_RETRIES
+            );
+            pool.slots.lock().remove(&lease.account);
+            pool.rotating.fetch_sub(1, Ordering::Relaxed);
+            on_rotated(false);
+        });
+    }
+
+    // Waits for sent leases to show whether they landed, returns how many did not yet.
+    pub async fn settle(&self, timeout: Duration) -> usize {
+        let started = Instant::now();
+        while self.rotating.load(Ordering::Relaxed) > 0 && started.elapsed() < timeout {
+            sleep(Duration::from_millis(50)).await;
+        }
+        self.rotating.load(Ordering::Relaxed)
+    }
+
+    fn make_available(&self, account: &Pubkey, nonce: Option<Hash>) {
+        if let Some(slot) = self.slots.lock().get_mut(account) {
+            slot.nonce = nonce;
+            slot.leased = false;
+        }
+    }
+
+    // Also tells whether the nonce had already moved, which only a landed variant does.
+    // Once `advanced` is set, an earlier attempt of ours may be what moved it.
+    async fn rotate(
+        &self,
+        lease: &NonceLease,
+        sent_slot: u64,
+        advanced: &mut bool,
+    ) -> AResult<(Hash, bool)> {
+        wait_until_slot(sent_slot + ROTATION_GRACE_SLOTS, ROTATION_GRACE_TIMEOUT).await;
+        let current = fetch_nonce(&lease.account).await?;
+        if current != lease.nonce {
+            return Ok((current, !*advanced));
+        }
+
+        let wallet = get_wallet();
+        let tx = compile_instruction_to_tx(
+            &wallet,
+            vec![lease.advance_ix(&wallet.pubkey())],
+            &[],
+            get_blockhash().await?,
+        )?;
+        *advanced = true;
+        send_transaction(&tx).await?;
+
+        for _ in 0..ROTATION_ATTEMPTS {
+            sleep(Duration::from_millis(500)).await;
+            let current = fetch_nonce(&lease.account).await?;
+            if current != lease.nonce {
+                return Ok((current, false));
+            }
+        }
+        Err(lined_err!("nonce {} did not advance", lease.account))
+    }
+
+    async fn load(&self) {
+        let accounts: Vec<Pubkey> = self.slots.lock().keys().copied().collect();
+        for account in accounts {
+            match fetch_nonce(&account).await {
+                Ok(nonce) => self.make_available(&account, Some(nonce)),
+                Err(e) => warn!("Failed to load nonce account {}: {}", account, e),
+            }
+        }
+        info!("Loaded {} nonce accounts", self.slots.lock().len());
+    }
+}
+
+async fn fetch_nonce(account: &Pubkey) -> AResult<Hash> {
+    let account = get_account_unbatched(account).await?;
+    let versions: Versions = bincode::deserialize(&account.data)?;
+    match versions.state() {
+        State::Initialized(data) => Ok(data.blockhash()),
+        State::Uninitialized => Err(lined_err!("nonce account is not initialized")),
+    }
+}
+
+pub static NoncePoolHolder: Lazy<Arc<NoncePool>> = Lazy::new(|| {
+    let accounts = ENV_CONFIG
+        .nonce_accounts
+        .iter()
+        .map(|account| account.to_pubkey())
+        .collect();
+    let pool = Arc::new(NoncePool::new(accounts));
+
+    if pool.is_enabled() {
+        let pool_clone = pool.clone();
+        tokio::spawn(async move { pool_clone.load().await });
+    }
+
+    pool
+});
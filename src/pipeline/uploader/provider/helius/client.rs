@@ -1,3 +1,4 @@
+use crate::global::trace::facade::active_traceparent;
 use crate::util::alias::AResult;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
@@ -60,13 +61,14 @@ impl HeliusClient {
             ]
         });
 
-        let response = self
+        let mut request = self
             .client
             .post(&self.url)
-            .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
+            .header("Content-Type", "application/json");
+        if let Some(traceparent) = active_traceparent() {
+            request = request.header("traceparent", traceparent);
+        }
+        let response = request.json(&request_body).send().await?;
 
         let response_json: serde_json::Value = response.json().await?;
 
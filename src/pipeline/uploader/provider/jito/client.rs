@@ -1,3 +1,4 @@
+use crate::global::trace::facade::active_traceparent;
 use crate::pipeline::uploader::provider::jito::types::{JitoBundleResponse, TipFloorData};
 use crate::util::alias::AResult;
 use anyhow::anyhow;
@@ -68,13 +69,14 @@ impl JitoClient {
         });
 
         let bundle_url = format!("{}/api/v1/bundles", self.base_url);
-        let response = self
+        let mut request = self
             .client
             .post(&bundle_url)
-            .header("Content-Type", "application/json")
-            .json(&bundle_payload)
-            .send()
-            .await?;
+            .header("Content-Type", "application/json");
+        if let Some(traceparent) = active_traceparent() {
+            request = request.header("traceparent", traceparent);
+        }
+        let response = request.json(&bundle_payload).send().await?;
 
         if !response.status().is_success() {
             let status = response.status();
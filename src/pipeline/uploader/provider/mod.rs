@@ -1,23 +1,66 @@
+use crate::global::daemon::priority_fee::PriorityFee;
 use crate::global::enums::step_type::StepType;
+use crate::global::state::deadline_policy::{DeadlinePolicyHolder, Stage};
 use crate::global::trace::types::Trace;
 use crate::pipeline::uploader::provider::helius::facade::{
     build_helius_jito_tip_ix, build_helius_swqos_tip_ix, send_helius_jito, send_helius_swqos,
 };
 use crate::pipeline::uploader::provider::jito::facade::send_bundle;
 use crate::pipeline::uploader::provider::shyft::facade::send_shyft_transaction;
+use crate::return_error;
 use crate::util::alias::{AResult, Lamport, Literal};
-use crate::{return_error, unit_ok};
+use crate::util::metrics_registry::METRICS_REGISTRY;
 use jito::facade::build_jito_tip_ix;
 use solana_program::instruction::Instruction;
 use solana_program::pubkey::Pubkey;
 use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::signature::Signature;
 use solana_sdk::transaction::VersionedTransaction;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use strum_macros::AsRefStr;
 use tracing::{error, info};
 
 pub mod helius;
 pub mod jito;
 pub mod shyft;
 
+// Sends still being handled, whether or not a nonce is in use.
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+// Counts a send from the provider call until its `SentTx` is dropped, also when the
+// call is cancelled.
+#[derive(Debug)]
+struct InFlightSend;
+
+impl InFlightSend {
+    fn start() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for InFlightSend {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Sends still being made or waiting for their landing status.
+pub fn sends_in_flight() -> usize {
+    IN_FLIGHT.load(Ordering::Relaxed)
+}
+
+// A transaction handed to a landing channel. Shutdown waits until every copy is dropped.
+#[derive(Debug, Clone)]
+pub struct SentTx {
+    // Since the slot started, compared against whether it landed.
+    pub age_ms: u32,
+    pub signature: Signature,
+    _in_flight: Arc<InFlightSend>,
+}
+
+#[derive(AsRefStr)]
 pub enum LandingChannel {
     HeliusSwqos,
     Jito,
@@ -26,10 +69,15 @@ pub enum LandingChannel {
 }
 
 impl LandingChannel {
-    pub fn tip_ix(&self, payer: &Pubkey, unit_price: Lamport) -> (Vec<Instruction>, Literal) {
-        let priority_fee_ix = ComputeBudgetInstruction::set_compute_unit_price(unit_price);
+    pub fn tip_ix(&self, payer: &Pubkey, fee: &PriorityFee) -> (Vec<Instruction>, Literal) {
+        let priority_fee_ix =
+            ComputeBudgetInstruction::set_compute_unit_price(self.unit_price(fee));
         match self {
-            LandingChannel::Jito => build_jito_tip_ix(payer),
+            LandingChannel::Jito => {
+                let (mut ix, tip) = build_jito_tip_ix(payer);
+                ix.push(priority_fee_ix);
+                (ix, tip)
+            }
             LandingChannel::HeliusSwqos => {
                 let (mut ix, tip) = build_helius_swqos_tip_ix(payer);
                 ix.push(priority_fee_ix);
@@ -44,43 +92,56 @@ impl LandingChannel {
         }
     }
 
-    pub async fn send_tx(&self, tx: &VersionedTransaction, trace: &Trace) -> AResult<()> {
-        if trace.since_begin() > 400 {
-            info!(
-                "Gave up on landing tx because it takes {} milliseconds from trigger to now",
-                trace.since_begin()
-            );
+    // Shyft has no tip, so priority fee alone decides whether we land there.
+    pub fn unit_price(&self, fee: &PriorityFee) -> Lamport {
+        match self {
+            LandingChannel::Shyft => fee.p90,
+            _ => fee.p75,
+        }
+    }
+
+    // Errors only when it gave up before handing the transaction over. A failed call may
+    // still have reached the leader, so it is reported in the trace and counts as sent.
+    pub async fn send_tx(&self, tx: &VersionedTransaction, trace: &Trace) -> AResult<SentTx> {
+        if DeadlinePolicyHolder.expired(Stage::Send, &[self.as_ref()], trace) {
+            self.record_send("gave_up");
             return_error!("Gave up");
         }
+        let sent = SentTx {
+            age_ms: trace.since_slot_start(),
+            signature: tx.signatures[0],
+            _in_flight: Arc::new(InFlightSend::start()),
+        };
         trace.step(StepType::MevRealTxRpcCall);
-        match self {
-            LandingChannel::HeliusSwqos => {
-                send_helius_swqos(tx).await?;
-            }
-            LandingChannel::Jito => {
-                let response = send_bundle(tx).await;
-                let _ = match response {
-                    Ok(bundle_id) => {
-                        trace.step_with(
-                            StepType::MevRealTxRpcReturned,
-                            "jito_bundle_id",
-                            bundle_id.clone(),
-                        );
-                        info!("MEV transaction sent successfully: jito id: {}", bundle_id);
-                    }
-                    Err(e) => {
-                        trace.step_with(StepType::MevRealTxRpcReturned, "error", e.to_string());
-                        error!("Failed to send MEV transaction: {}", e);
-                    }
-                };
-            }
-            LandingChannel::HeliusJito => {
-                send_helius_jito(tx).await?;
-            }
-            LandingChannel::Shyft => {
-                send_shyft_transaction(tx).await?;
-            }
+        let result = match self {
+            LandingChannel::HeliusSwqos => send_helius_swqos(tx).await,
+            LandingChannel::Jito => send_bundle(tx).await.map(|bundle_id| {
+                trace.step_with(
+                    StepType::MevRealTxRpcReturned,
+                    "jito_bundle_id",
+                    bundle_id.clone(),
+                );
+                info!("MEV transaction sent successfully: jito id: {}", bundle_id);
+            }),
+            LandingChannel::HeliusJito => send_helius_jito(tx).await,
+            LandingChannel::Shyft => send_shyft_transaction(tx).await.map(|_| ()),
+        };
+        self.record_send(if result.is_ok() { "sent" } else { "failed" });
+        if let Err(e) = result {
+            trace.step_with(StepType::MevRealTxRpcReturned, "error", e.to_string());
+            error!(
+                "Failed to send MEV transaction through {}: {}",
+                self.as_ref(),
+                e
+            );
         }
-        unit_ok!()
+        Ok(sent)
+    }
+
+    fn record_send(&self, outcome: &str) {
+        METRICS_REGISTRY.inc_counter(
+            "sends_total",
+            &[("channel", self.as_ref()), ("outcome", outcome)],
+        );
     }
 }
@@ -1,11 +1,15 @@
 #![allow(non_upper_case_globals)]
+use crate::global::enums::dex_type::DexType;
+use crate::global::state::deadline_policy::{DeadlinePolicyHolder, Stage};
+use crate::global::state::pool_generation_holder::PoolGeneration;
 use crate::global::trace::types::Trace;
 use crate::lazy_arc;
-use crate::pipeline::uploader::entry::fire_mev_bot;
-use crate::util::alias::{MintAddress, PoolAddress};
+use crate::pipeline::uploader::entry::{fire_mev_bot, pair_dedup_window};
+use crate::util::alias::{Lamport, MintAddress, PoolAddress};
 use crate::util::structs::rate_limiter::RateLimiter;
 use crate::util::structs::tx_dedup::TxDeduplicator;
-use crate::util::worker::pubsub::{PubSubConfig, PubSubProcessor};
+use crate::util::worker::priority_pubsub::{Prioritized, PriorityPubSubProcessor};
+use crate::util::worker::pubsub::PubSubConfig;
 use once_cell::sync::Lazy;
 use std::sync::Arc;
 use std::time::Duration;
@@ -13,18 +17,50 @@ use std::time::Duration;
 pub struct MevBotFire {
     pub minor_mint: MintAddress,
     pub pools: Vec<PoolAddress>,
+    pub include_create_token_account_ix: bool,
+    pub expected_profit: Lamport,
+    pub dex_types: Vec<DexType>,
+    pub generation: PoolGeneration,
     pub trace: Trace,
 }
 
-pub static FireMevBotConsumer: Lazy<Arc<PubSubProcessor<MevBotFire>>> = lazy_arc!({
-    PubSubProcessor::new(
+impl Prioritized for MevBotFire {
+    fn priority(&self) -> u64 {
+        self.expected_profit
+    }
+
+    // Too late to simulate, so it could never be sent in time either.
+    fn is_expired(&self) -> bool {
+        let dexes: Vec<String> = self.dex_types.iter().map(DexType::to_string).collect();
+        DeadlinePolicyHolder.expired(Stage::Simulation, &dexes, &self.trace)
+    }
+
+    // A pair that is cooling down or was just simulated is dropped here, so it does not
+    // use up a rate limit token.
+    fn is_superseded(&self) -> bool {
+        self.generation.superseded_at("dequeue", &self.trace)
+            || pair_dedup_window(&self.minor_mint, &self.pools).is_none()
+    }
+}
+
+// The rate limit budget goes to the most profitable opportunities first.
+pub static FireMevBotConsumer: Lazy<Arc<PriorityPubSubProcessor<MevBotFire>>> = lazy_arc!({
+    PriorityPubSubProcessor::new(
         PubSubConfig {
             worker_pool_size: 12,
             channel_buffer_size: 1000,
-            name: "VaultUpdateProcessor".to_string(),
+            name: "FireMevBotConsumer".to_string(),
         },
+        Some(MevBotRateLimiter.clone()),
         |event: MevBotFire| async move {
-            fire_mev_bot(&event.minor_mint, &event.pools, event.trace).await
+            fire_mev_bot(
+                &event.minor_mint,
+                &event.pools,
+                event.include_create_token_account_ix,
+                event.generation,
+                event.trace,
+            )
+            .await
         },
     )
 });
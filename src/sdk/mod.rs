@@ -1,2 +1,4 @@
+pub mod pubsub;
 pub mod rpc;
 pub mod yellowstone;
+pub mod yellowstone_multi;
@@ -0,0 +1,655 @@
+use crate::convention::chain::stamp::Commitment;
+use crate::sdk::rpc::methods::transaction::fetch_encoded_tx;
+use crate::sdk::yellowstone::{
+    AccountFilter, GapHandler, GrpcAccountUpdate, GrpcTransactionUpdate, TransactionFilter,
+};
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::structs::rate_limiter::{RateLimitError, RateLimiter};
+use crate::util::structs::stream_dedup::StreamDeduplicator;
+use anyhow::{anyhow, Result};
+use futures::stream::{select_all, BoxStream};
+use futures::StreamExt;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig,
+    RpcTransactionLogsFilter,
+};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::account::Account;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, UiInstruction, UiLoadedAddresses,
+    UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::{error, info, warn};
+use yellowstone_grpc_proto::prelude::{
+    subscribe_request_filter_accounts_filter, subscribe_request_filter_accounts_filter_memcmp,
+    CompiledInstruction, InnerInstruction, InnerInstructions, Message, MessageAddressTableLookup,
+    MessageHeader, TokenBalance, Transaction, TransactionError, TransactionStatusMeta,
+    UiTokenAmount,
+};
+
+const DEDUP_TTL: Duration = Duration::from_secs(60);
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(200);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+const FETCH_ATTEMPTS: u32 = 3;
+const FETCH_RETRY_DELAY: Duration = Duration::from_millis(300);
+// Busy programs announce far more transactions than should be fetched at once. The
+// single transaction fetch skips the endpoint read limit, so this is the only budget
+// these fetches are held to.
+const MAX_CONCURRENT_FETCHES: usize = 16;
+const FETCHES_PER_SECOND: u32 = 20;
+const FETCH_TOKEN_WAIT: Duration = Duration::from_millis(500);
+
+// Standard RPC WebSocket subscriptions that deliver the same update types as the Geyser
+// client, so the pipeline keeps running on them while no gRPC endpoint is reachable.
+pub struct SolanaPubsubClient {
+    url: String,
+    commitment: Commitment,
+    gap_handler: Option<GapHandler>,
+    // PubSub notifications carry no write version, so arrival order stands in for it.
+    // That order means nothing to other clients, each one is a source of its own.
+    write_version: AtomicU64,
+    source: u64,
+    fetch_permits: Arc<Semaphore>,
+    fetch_limiter: Arc<RateLimiter>,
+}
+
+impl SolanaPubsubClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            commitment: Commitment::Processed,
+            gap_handler: None,
+            write_version: AtomicU64::new(0),
+            source: rand::random(),
+            fetch_permits: Arc::new(Semaphore::new(MAX_CONCURRENT_FETCHES)),
+            fetch_limiter: Arc::new(RateLimiter::new(
+                FETCHES_PER_SECOND,
+                Duration::from_secs(1),
+                FETCHES_PER_SECOND,
+                "PubsubTransactionFetch".to_string(),
+            )),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(ENV_CONFIG.solana_ws_url.clone())
+    }
+
+    pub fn with_gap_handler(mut self, handler: GapHandler) -> Self {
+        self.gap_handler = Some(handler);
+        self
+    }
+
+    pub fn with_commitment(mut self, commitment: Commitment) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    // programSubscribe per owner and accountSubscribe per listed account of every filter.
+    pub async fn subscribe_accounts<F, Fut>(
+        self,
+        filters: Vec<AccountFilter>,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: Fn(GrpcAccountUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let name = AccountFilter::names(&filters);
+        let mut delay = MIN_RETRY_DELAY;
+        loop {
+            let started = Instant::now();
+            match self.stream_accounts(&filters, &callback).await {
+                Ok(_) => warn!("[{}] PubSub subscription to {} ended", name, self.url),
+                Err(e) => error!(
+                    "[{}] PubSub subscription to {} failed: {}",
+                    name, self.url, e
+                ),
+            }
+            delay = self.backoff(started, delay).await;
+        }
+    }
+
+    // logsSubscribe per program, then the full transaction is fetched over RPC. Transactions
+    // cannot be fetched before they are confirmed, so processed is raised to confirmed.
+    pub async fn subscribe_transactions<F, Fut>(
+        self,
+        filter: TransactionFilter,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: Fn(GrpcTransactionUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let dedup = Arc::new(StreamDeduplicator::new(DEDUP_TTL));
+        let mut delay = MIN_RETRY_DELAY;
+        loop {
+            let started = Instant::now();
+            match self.stream_transactions(&filter, &dedup, &callback).await {
+                Ok(_) => warn!(
+                    "[{}] PubSub subscription to {} ended",
+                    filter.name, self.url
+                ),
+                Err(e) => error!(
+                    "[{}] PubSub subscription to {} failed: {}",
+                    filter.name, self.url, e
+                ),
+            }
+            delay = self.backoff(started, delay).await;
+        }
+    }
+
+    async fn stream_accounts<F, Fut>(&self, filters: &[AccountFilter], callback: &F) -> Result<()>
+    where
+        F: Fn(GrpcAccountUpdate) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let client = PubsubClient::new(&self.url).await?;
+        let account_config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(self.commitment.to_config()),
+            ..Default::default()
+        };
+
+        let mut streams: Vec<BoxStream<'_, (Pubkey, UiAccount, u64)>> = Vec::new();
+        for filter in filters {
+            let program_config = RpcProgramAccountsConfig {
+                filters: Some(rpc_filters(filter)),
+                account_config: account_config.clone(),
+                ..Default::default()
+            };
+            for owner in &filter.owner {
+                let (stream, _) = client
+                    .program_subscribe(&Pubkey::from_str(owner)?, Some(program_config.clone()))
+                    .await?;
+                streams.push(
+                    stream
+                        .filter_map(|response| async move {
+                            let address = Pubkey::from_str(&response.value.pubkey).ok()?;
+                            Some((address, response.value.account, response.context.slot))
+                        })
+                        .boxed(),
+                );
+            }
+            for account in &filter.account {
+                let address = Pubkey::from_str(account)?;
+                let (stream, _) = client
+                    .account_subscribe(&address, Some(account_config.clone()))
+                    .await?;
+                streams.push(
+                    stream
+                        .map(move |response| (address, response.value, response.context.slot))
+                        .boxed(),
+                );
+            }
+        }
+        info!(
+            "[{}] Streaming {} PubSub subscriptions from {}",
+            AccountFilter::names(filters),
+            streams.len(),
+            self.url
+        );
+        self.on_connected().await;
+
+        let mut updates = select_all(streams);
+        while let Some((address, account, slot)) = updates.next().await {
+            let write_version = self.write_version.fetch_add(1, Ordering::Relaxed) + 1;
+            let Some(update) = account_update(
+                address,
+                &account,
+                slot,
+                write_version,
+                self.source,
+                self.commitment,
+            ) else {
+                warn!("Undecodable PubSub account update for {}", address);
+                continue;
+            };
+            if let Err(e) = callback(update).await {
+                error!("Callback error: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn stream_transactions<F, Fut>(
+        &self,
+        filter: &TransactionFilter,
+        dedup: &Arc<StreamDeduplicator<String>>,
+        callback: &F,
+    ) -> Result<()>
+    where
+        F: Fn(GrpcTransactionUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let commitment = match self.commitment {
+            Commitment::Processed => Commitment::Confirmed,
+            commitment => commitment,
+        };
+        let client = PubsubClient::new(&self.url).await?;
+
+        // A single address per subscription is all the mentions filter accepts.
+        let mut streams = Vec::new();
+        for account in &filter.account_include {
+            let (stream, _) = client
+                .logs_subscribe(
+                    RpcTransactionLogsFilter::Mentions(vec![account.clone()]),
+                    RpcTransactionLogsConfig {
+                        commitment: Some(commitment.to_config()),
+                    },
+                )
+                .await?;
+            streams.push(stream);
+        }
+        info!(
+            "[{}] Streaming {} PubSub log subscriptions from {}",
+            filter.name,
+            streams.len(),
+            self.url
+        );
+        self.on_connected().await;
+
+        let mut logs = select_all(streams);
+        while let Some(response) = logs.next().await {
+            let failed = response.value.err.is_some();
+            if filter.failed == Some(false) && failed {
+                continue;
+            }
+            let signature = response.value.signature;
+            if !dedup.first_seen(signature.clone()) {
+                continue;
+            }
+            // Waiting here leaves the backlog with the node rather than in spawned tasks.
+            let permit = self.fetch_permits.clone().acquire_owned().await?;
+            let limiter = self.fetch_limiter.clone();
+            let callback = callback.clone();
+            let slot = response.context.slot;
+            tokio::spawn(async move {
+                let _permit = permit;
+                match fetch_transaction_update(&signature, slot, commitment, &limiter).await {
+                    Ok(update) => {
+                        if let Err(e) = callback(update).await {
+                            error!("Callback error: {}", e);
+                        }
+                    }
+                    Err(e) => warn!("Dropping PubSub transaction {}: {}", signature, e),
+                }
+            });
+        }
+        Ok(())
+    }
+
+    // Every (re)connect follows a period without updates, the switch from gRPC included.
+    async fn on_connected(&self) {
+        if let Some(handler) = &self.gap_handler {
+            handler().await;
+        }
+    }
+
+    async fn backoff(&self, started: Instant, delay: Duration) -> Duration {
+        let delay = if started.elapsed() > MAX_RETRY_DELAY * 6 {
+            MIN_RETRY_DELAY
+        } else {
+            delay
+        };
+        tokio::time::sleep(delay).await;
+        (delay * 2).min(MAX_RETRY_DELAY)
+    }
+}
+
+fn rpc_filters(filter: &AccountFilter) -> Vec<RpcFilterType> {
+    use subscribe_request_filter_accounts_filter::Filter;
+    use subscribe_request_filter_accounts_filter_memcmp::Data;
+
+    filter
+        .filters
+        .iter()
+        .filter_map(|f| match f.filter.as_ref()? {
+            Filter::Datasize(size) => Some(RpcFilterType::DataSize(*size)),
+            Filter::Memcmp(memcmp) => match memcmp.data.as_ref()? {
+                Data::Bytes(bytes) => Some(RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    memcmp.offset as usize,
+                    bytes.clone(),
+                ))),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+fn account_update(
+    address: Pubkey,
+    account: &UiAccount,
+    slot: u64,
+    write_version: u64,
+    source: u64,
+    commitment: Commitment,
+) -> Option<GrpcAccountUpdate> {
+    let account: Account = account.decode()?;
+    Some(GrpcAccountUpdate {
+        account: address,
+        slot,
+        data: account.data,
+        owner: account.owner,
+        lamports: account.lamports,
+        executable: account.executable,
+        rent_epoch: account.rent_epoch,
+        write_version,
+        source,
+        txn_signature: None,
+        commitment,
+    })
+}
+
+// The RPC node may not serve a transaction the instant its logs were announced.
+async fn fetch_transaction_update(
+    signature: &str,
+    slot: u64,
+    commitment: Commitment,
+    limiter: &RateLimiter,
+) -> Result<GrpcTransactionUpdate> {
+    let mut attempt = 1;
+    loop {
+        if !limiter.acquire(FETCH_TOKEN_WAIT).await {
+            return Err(RateLimitError::ExceededLimit {}.into());
+        }
+        match fetch_encoded_tx(signature, commitment.to_config()).await {
+            Ok(encoded) => return transaction_update(encoded, slot, commitment),
+            Err(e) if attempt >= FETCH_ATTEMPTS => return Err(e),
+            Err(_) => {
+                attempt += 1;
+                tokio::time::sleep(FETCH_RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+fn transaction_update(
+    encoded: EncodedConfirmedTransactionWithStatusMeta,
+    slot: u64,
+    commitment: Commitment,
+) -> Result<GrpcTransactionUpdate> {
+    let tx = encoded
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("Transaction is not binary encoded"))?;
+    let signature = tx
+        .signatures
+        .first()
+        .ok_or_else(|| anyhow!("Transaction has no signatures"))?
+        .to_string();
+
+    let message = &tx.message;
+    let header = message.header();
+    let transaction = Transaction {
+        signatures: tx.signatures.iter().map(|s| s.as_ref().to_vec()).collect(),
+        message: Some(Message {
+            header: Some(MessageHeader {
+                num_required_signatures: header.num_required_signatures as u32,
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts as u32,
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts as u32,
+            }),
+            account_keys: message
+                .static_account_keys()
+                .iter()
+                .map(|key| key.to_bytes().to_vec())
+                .collect(),
+            recent_blockhash: message.recent_blockhash().to_bytes().to_vec(),
+            instructions: message
+                .instructions()
+                .iter()
+                .map(|ix| CompiledInstruction {
+                    program_id_index: ix.program_id_index as u32,
+                    accounts: ix.accounts.clone(),
+                    data: ix.data.clone(),
+                })
+                .collect(),
+            versioned: matches!(message, VersionedMessage::V0(_)),
+            address_table_lookups: message
+                .address_table_lookups()
+                .unwrap_or_default()
+                .iter()
+                .map(|lookup| MessageAddressTableLookup {
+                    account_key: lookup.account_key.to_bytes().to_vec(),
+                    writable_indexes: lookup.writable_indexes.clone(),
+                    readonly_indexes: lookup.readonly_indexes.clone(),
+                })
+                .collect(),
+        }),
+    };
+
+    Ok(GrpcTransactionUpdate {
+        signature,
+        slot: encoded.slot.max(slot),
+        is_vote: false,
+        transaction: Some(transaction),
+        meta: encoded.transaction.meta.map(status_meta).transpose()?,
+        commitment,
+    })
+}
+
+fn status_meta(meta: UiTransactionStatusMeta) -> Result<TransactionStatusMeta> {
+    let inner_instructions = Option::<Vec<_>>::from(meta.inner_instructions)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|inner| {
+            let instructions = inner
+                .instructions
+                .into_iter()
+                .filter_map(|ix| match ix {
+                    UiInstruction::Compiled(ix) => Some(ix),
+                    UiInstruction::Parsed(_) => None,
+                })
+                .map(|ix| {
+                    Ok(InnerInstruction {
+                        program_id_index: ix.program_id_index as u32,
+                        accounts: ix.accounts,
+                        data: bs58::decode(&ix.data).into_vec()?,
+                        stack_height: ix.stack_height,
+                    })
+                })
+                .collect::<Result<_>>()?;
+            Ok(InnerInstructions {
+                index: inner.index as u32,
+                instructions,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let loaded_addresses =
+        Option::<UiLoadedAddresses>::from(meta.loaded_addresses).unwrap_or_default();
+    let to_bytes = |addresses: Vec<String>| -> Result<Vec<Vec<u8>>> {
+        addresses
+            .iter()
+            .map(|address| Ok(Pubkey::from_str(address)?.to_bytes().to_vec()))
+            .collect()
+    };
+
+    Ok(TransactionStatusMeta {
+        err: meta
+            .err
+            .map(|e| bincode::serialize(&e))
+            .transpose()?
+            .map(|err| TransactionError { err }),
+        fee: meta.fee,
+        pre_balances: meta.pre_balances,
+        post_balances: meta.post_balances,
+        inner_instructions,
+        log_messages: Option::from(meta.log_messages).unwrap_or_default(),
+        pre_token_balances: token_balances(meta.pre_token_balances.into()),
+        post_token_balances: token_balances(meta.post_token_balances.into()),
+        loaded_writable_addresses: to_bytes(loaded_addresses.writable)?,
+        loaded_readonly_addresses: to_bytes(loaded_addresses.readonly)?,
+        compute_units_consumed: meta.compute_units_consumed.into(),
+        ..Default::default()
+    })
+}
+
+fn token_balances(balances: Option<Vec<UiTransactionTokenBalance>>) -> Vec<TokenBalance> {
+    balances
+        .unwrap_or_default()
+        .into_iter()
+        .map(|balance| TokenBalance {
+            account_index: balance.account_index as u32,
+            mint: balance.mint,
+            ui_token_amount: Some(UiTokenAmount {
+                ui_amount: balance.ui_token_amount.ui_amount.unwrap_or_default(),
+                decimals: balance.ui_token_amount.decimals as u32,
+                amount: balance.ui_token_amount.amount,
+                ui_amount_string: balance.ui_token_amount.ui_amount_string,
+            }),
+            owner: Option::from(balance.owner).unwrap_or_default(),
+            program_id: Option::from(balance.program_id).unwrap_or_default(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use serde_json::{json, Value};
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::message::Message as LegacyMessage;
+    use solana_sdk::signature::Signature;
+    use solana_sdk::transaction::VersionedTransaction;
+
+    fn encoded_fixture(payer: Pubkey, program: Pubkey, vault: Pubkey, mint: Pubkey) -> Value {
+        let ix = Instruction::new_with_bytes(
+            program,
+            &[7, 1, 2],
+            vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(vault, false),
+            ],
+        );
+        let message = LegacyMessage::new_with_blockhash(&[ix], Some(&payer), &Hash::default());
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::from([9; 64])],
+            message: VersionedMessage::Legacy(message),
+        };
+        let tx = base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&tx).unwrap());
+        let balance = |amount: &str| {
+            json!([{
+                "accountIndex": 1,
+                "mint": mint.to_string(),
+                "owner": payer.to_string(),
+                "programId": spl_token::ID.to_string(),
+                "uiTokenAmount": {
+                    "amount": amount,
+                    "decimals": 6,
+                    "uiAmount": 0.5,
+                    "uiAmountString": "0.5",
+                },
+            }])
+        };
+
+        json!({
+            "slot": 310,
+            "blockTime": null,
+            "transaction": [tx, "base64"],
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5000,
+                "preBalances": [1_000_000, 0, 1],
+                "postBalances": [995_000, 0, 1],
+                "innerInstructions": [{
+                    "index": 0,
+                    "instructions": [{
+                        "programIdIndex": 2,
+                        "accounts": [1],
+                        "data": bs58::encode([4, 2]).into_string(),
+                        "stackHeight": 2,
+                    }],
+                }],
+                "logMessages": ["Program log: swap"],
+                "preTokenBalances": balance("0"),
+                "postTokenBalances": balance("500000"),
+                "rewards": [],
+                "loadedAddresses": { "writable": [vault.to_string()], "readonly": [] },
+                "computeUnitsConsumed": 1234,
+            },
+        })
+    }
+
+    #[test]
+    fn test_transaction_update_from_encoded_transaction() {
+        let (payer, program, vault, mint) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let encoded: EncodedConfirmedTransactionWithStatusMeta =
+            serde_json::from_value(encoded_fixture(payer, program, vault, mint)).unwrap();
+
+        let update = transaction_update(encoded, 300, Commitment::Confirmed).unwrap();
+        assert_eq!(update.signature, Signature::from([9; 64]).to_string());
+        // the slot the transaction landed in wins over the notification slot
+        assert_eq!(update.slot, 310);
+        assert_eq!(update.commitment, Commitment::Confirmed);
+
+        let message = update.transaction.unwrap().message.unwrap();
+        assert_eq!(message.account_keys[0], payer.to_bytes().to_vec());
+        assert_eq!(message.instructions[0].program_id_index, 2);
+        assert_eq!(message.instructions[0].data, vec![7, 1, 2]);
+        assert!(!message.versioned);
+
+        let meta = update.meta.unwrap();
+        assert_eq!(meta.err, None);
+        assert_eq!(meta.fee, 5000);
+        assert_eq!(meta.post_balances, vec![995_000, 0, 1]);
+        assert_eq!(meta.inner_instructions[0].instructions[0].data, vec![4, 2]);
+        assert_eq!(
+            meta.inner_instructions[0].instructions[0].stack_height,
+            Some(2)
+        );
+        assert_eq!(meta.log_messages, vec!["Program log: swap".to_string()]);
+        assert_eq!(
+            meta.loaded_writable_addresses,
+            vec![vault.to_bytes().to_vec()]
+        );
+        assert_eq!(meta.compute_units_consumed, Some(1234));
+
+        let post = &meta.post_token_balances[0];
+        assert_eq!(post.account_index, 1);
+        assert_eq!(post.mint, mint.to_string());
+        assert_eq!(post.owner, payer.to_string());
+        assert_eq!(post.ui_token_amount.as_ref().unwrap().amount, "500000");
+        assert_eq!(post.ui_token_amount.as_ref().unwrap().decimals, 6);
+    }
+
+    #[test]
+    fn test_token_balances_default_missing_owner() {
+        let balances: Vec<UiTransactionTokenBalance> = serde_json::from_value(json!([{
+            "accountIndex": 3,
+            "mint": Pubkey::new_unique().to_string(),
+            "uiTokenAmount": {
+                "amount": "1",
+                "decimals": 0,
+                "uiAmount": 1.0,
+                "uiAmountString": "1",
+            },
+        }]))
+        .unwrap();
+
+        let converted = token_balances(Some(balances));
+        assert_eq!(converted[0].account_index, 3);
+        assert_eq!(converted[0].owner, "");
+        assert!(token_balances(None).is_empty());
+    }
+}
@@ -1,20 +1,77 @@
+use crate::sdk::rpc::pool::{RpcEndpoint, RpcLease, RpcPool, RpcRole};
+use crate::util::alias::AResult;
 use crate::util::env::env_config::ENV_CONFIG;
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
-use solana_client::nonblocking::rpc_client;
 use solana_client::rpc_config::RpcTransactionConfig;
 use solana_sdk::commitment_config::CommitmentConfig;
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::Builder;
+use tokio::time::interval;
 
-static RPC_HOLDER: Lazy<RwLock<Arc<rpc_client::RpcClient>>> = Lazy::new(|| {
-    RwLock::new(Arc::new(rpc_client::RpcClient::new_with_commitment(
-        ENV_CONFIG.solana_rpc_url.clone(),
-        CommitmentConfig::processed(),
-    )))
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const HEALTH_LOG_EVERY: u32 = 12;
+
+static RPC_POOL: Lazy<RwLock<Arc<RpcPool>>> = Lazy::new(|| {
+    let endpoints = ENV_CONFIG
+        .rpc_endpoints()
+        .into_iter()
+        .enumerate()
+        .map(|(index, (url, roles, requests_per_second))| {
+            let roles = RpcRole::parse_set(&roles).expect("Invalid RPC endpoint roles");
+            RpcEndpoint::new(
+                index,
+                url,
+                roles,
+                requests_per_second,
+                ENV_CONFIG.rpc_commitment.to_config(),
+            )
+        })
+        .collect();
+    start_health_checker();
+    RwLock::new(Arc::new(RpcPool::new(endpoints)))
 });
 
-pub(super) fn rpc_client() -> Arc<rpc_client::RpcClient> {
-    RPC_HOLDER.read().clone()
+// Every call takes a lease for its role and hands the result back through `finish`.
+pub(super) fn rpc(role: RpcRole) -> AResult<RpcLease> {
+    RPC_POOL.read().acquire(role)
+}
+
+// Same, skipping the endpoint limit; see `RpcPool::acquire_unthrottled`.
+pub(super) fn rpc_unthrottled(role: RpcRole) -> AResult<RpcLease> {
+    RPC_POOL.read().acquire_unthrottled(role)
+}
+
+fn start_health_checker() {
+    // Spawn a dedicated OS thread so probes are not delayed by a busy runtime
+    thread::Builder::new()
+        .name("rpc-health-checker".to_string())
+        .spawn(move || {
+            let runtime = Builder::new_current_thread()
+                .enable_all()
+                .thread_name("rpc-health-runtime")
+                .build()
+                .expect("Failed to create runtime for RPC health checker");
+
+            runtime.block_on(async move {
+                let mut interval = interval(HEALTH_CHECK_INTERVAL);
+                let mut rounds = 0u32;
+
+                loop {
+                    interval.tick().await;
+                    let pool = RPC_POOL.read().clone();
+                    pool.check_health().await;
+                    pool.export_health();
+                    rounds += 1;
+                    if rounds.is_multiple_of(HEALTH_LOG_EVERY) {
+                        pool.log_health();
+                    }
+                }
+            });
+        })
+        .expect("Failed to spawn RPC health checker thread");
 }
 
 pub fn json_config() -> RpcTransactionConfig {
@@ -27,8 +84,11 @@ pub fn json_config() -> RpcTransactionConfig {
 
 #[cfg(test)]
 pub fn _set_test_client() {
-    *RPC_HOLDER.write() = Arc::new(rpc_client::RpcClient::new_with_commitment(
+    *RPC_POOL.write() = Arc::new(RpcPool::new(vec![RpcEndpoint::new(
+        0,
         "http://127.0.0.1:8899".to_string(),
+        RpcRole::ALL.to_vec(),
+        1000,
         CommitmentConfig::processed(),
-    ));
+    )]));
 }
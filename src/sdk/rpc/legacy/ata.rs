@@ -1,5 +1,7 @@
 use crate::global::constant::token_program::TokenProgram;
-use crate::sdk::rpc::client;
+use crate::sdk::rpc::client::rpc;
+use crate::sdk::rpc::methods::block::get_latest_blockhash;
+use crate::sdk::rpc::pool::RpcRole;
 use crate::util::solana::pda::ata;
 use solana_program::pubkey::Pubkey;
 use solana_sdk::compute_budget::ComputeBudgetInstruction;
@@ -9,7 +11,9 @@ use tracing::info;
 
 pub async fn ensure_mint_account_exists(mint: &Pubkey, wallet: &Keypair) -> anyhow::Result<bool> {
     let owner = &wallet.pubkey();
-    let mint_owner = client::rpc_client().get_account(mint).await?.owner;
+    let lease = rpc(RpcRole::Read)?;
+    let result = lease.client().get_account(mint).await;
+    let mint_owner = lease.finish(result)?.owner;
     if mint_owner != TokenProgram::TOKEN_2022 && mint_owner != TokenProgram::SPL_TOKEN {
         return Err(anyhow::anyhow!(
             "mint owner should be Token2022 or SPL Token program but instead it's: {}",
@@ -18,10 +22,9 @@ pub async fn ensure_mint_account_exists(mint: &Pubkey, wallet: &Keypair) -> anyh
     }
 
     let mint_account = ata(owner, mint, &mint_owner);
-    let mint_account_exists = client::rpc_client()
-        .get_account(&mint_account)
-        .await
-        .is_ok();
+    let lease = rpc(RpcRole::Read)?;
+    let result = lease.client().get_account(&mint_account).await;
+    let mint_account_exists = lease.finish(result).is_ok();
     if !mint_account_exists {
         let tx = solana_sdk::transaction::Transaction::new_signed_with_payer(
             &[
@@ -31,11 +34,15 @@ pub async fn ensure_mint_account_exists(mint: &Pubkey, wallet: &Keypair) -> anyh
             ],
             Some(owner),
             &[wallet],
-            client::rpc_client().get_latest_blockhash().await?,
+            get_latest_blockhash().await?,
         );
-        let signature = client::rpc_client()
+        let lease = rpc(RpcRole::Send)?;
+        let result = lease
+            .client()
             .send_and_confirm_transaction_with_spinner(&tx)
-            .await
+            .await;
+        let signature = lease
+            .finish(result)
             .map_err(|e| anyhow::anyhow!("Failed to send transaction: {}", e))?;
         info!(
             "Created token account for mint: {} owner: {} tx: {}",
@@ -1,69 +1,200 @@
 use crate::lined_err;
-use crate::sdk::rpc::client::rpc_client;
-use crate::sdk::rpc::methods::limiter::QueryRateLimiter;
+use crate::sdk::rpc::client::{rpc, rpc_unthrottled};
+use crate::sdk::rpc::pool::{is_rate_limit_error, is_rate_limited, RpcRole};
 use crate::util::alias::AResult;
+use crate::util::cron::periodic_logger::{MetricKey, MetricsHandle, PeriodicLogger};
+use crate::util::structs::rate_limiter::RateLimitError;
 use crate::util::traits::option::OptionExt;
-use anyhow::anyhow;
+use dashmap::DashMap;
 use mpsc::{channel, Receiver};
+use once_cell::sync::Lazy;
 use solana_sdk::account::Account;
 use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::{mpsc, OnceCell};
 use tokio::{select, time};
 use tracing::info;
 
+const MAX_BATCH_SIZE: usize = 100;
+
+// Lets callers tell a missing account apart from a node that could not answer.
+#[derive(Debug, Clone)]
+pub enum AccountFetchError {
+    NotFound(Pubkey),
+    Rpc(String),
+}
+
+impl fmt::Display for AccountFetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountFetchError::NotFound(pubkey) => write!(f, "{} Not found", pubkey),
+            AccountFetchError::Rpc(error) => write!(f, "RPC error: {}", error),
+        }
+    }
+}
+
+impl Error for AccountFetchError {}
+
+// Hot-path misses are flushed almost immediately, while background refreshes wait
+// for a full batch to save RPC calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BatchLane {
+    Hot,
+    Background,
+}
+
+impl BatchLane {
+    const ALL: [BatchLane; 2] = [BatchLane::Hot, BatchLane::Background];
+
+    fn max_wait(&self) -> Duration {
+        match self {
+            BatchLane::Hot => Duration::from_millis(3),
+            BatchLane::Background => Duration::from_millis(400),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BatchLane::Hot => "hot",
+            BatchLane::Background => "background",
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+enum BatchMetric {
+    Requests,
+    // Same address asked for twice within one batch.
+    MergedInBatch,
+    // Address already being fetched by an earlier batch.
+    JoinedInFlight,
+    Batches,
+    BatchedKeys,
+    WaitMs,
+    RpcMs,
+    RateLimited,
+    Errors,
+}
+
+impl std::fmt::Display for BatchMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Requests => write!(f, "requests"),
+            Self::MergedInBatch => write!(f, "merged_in_batch"),
+            Self::JoinedInFlight => write!(f, "joined_in_flight"),
+            Self::Batches => write!(f, "batches"),
+            Self::BatchedKeys => write!(f, "batched_keys"),
+            Self::WaitMs => write!(f, "wait_ms_total"),
+            Self::RpcMs => write!(f, "rpc_ms_total"),
+            Self::RateLimited => write!(f, "rate_limited"),
+            Self::Errors => write!(f, "errors"),
+        }
+    }
+}
+
+impl MetricKey for BatchMetric {}
+
+type Responder = Sender<AResult<(Account, u64)>>;
+
+// Accounts are answered together with the slot the RPC node read them at.
 struct Request {
     address: Pubkey,
-    on_response: Sender<AResult<Account>>,
+    on_response: Responder,
+    queued_at: Instant,
 }
 
-static CHANNEL: OnceCell<Sender<Request>> = OnceCell::const_new();
+// Addresses with an RPC call on the way, and everyone waiting for its answer.
+static IN_FLIGHT: Lazy<DashMap<Pubkey, Vec<Responder>>> = Lazy::new(DashMap::new);
 
-async fn get_sender() -> &'static Sender<Request> {
-    CHANNEL
+static CHANNELS: OnceCell<HashMap<BatchLane, Sender<Request>>> = OnceCell::const_new();
+
+async fn get_sender(lane: BatchLane) -> &'static Sender<Request> {
+    let channels = CHANNELS
         .get_or_init(|| async {
-            let (tx, rx) = channel::<Request>(1000);
+            let mut senders = HashMap::new();
+            let mut receivers = Vec::new();
+            for lane in BatchLane::ALL {
+                let (tx, rx) = channel::<Request>(1000);
+                senders.insert(lane, tx);
+                receivers.push((lane, rx));
+            }
 
-            // Spawn a dedicated thread with its own runtime for the buffered get account loop
+            // Spawn a dedicated thread with its own runtime for the buffered get account loops
             thread::spawn(move || {
                 let runtime = Runtime::new()
                     .expect("Failed to create dedicated runtime for buffered_get_account");
                 runtime.block_on(async {
                     info!("Started dedicated buffered_get_account worker thread");
-                    loop_forever(rx).await;
+                    let loops: Vec<_> = receivers
+                        .into_iter()
+                        .map(|(lane, rx)| tokio::spawn(loop_forever(lane, rx)))
+                        .collect();
+                    futures::future::join_all(loops).await;
                 });
             });
 
-            tx
+            senders
         })
-        .await
+        .await;
+    &channels[&lane]
+}
+
+// Straight from the node and past the endpoint limit, for nonce accounts a send waits on.
+pub async fn get_account_unbatched(address: &Pubkey) -> AResult<Account> {
+    let lease = rpc_unthrottled(RpcRole::Read)?;
+    let result = lease.client().get_account(address).await;
+    Ok(lease.finish(result)?)
 }
 
 pub async fn buffered_get_account(address: &Pubkey) -> AResult<Account> {
-    let (tx, mut rx) = channel::<AResult<Account>>(1);
+    buffered_get_account_in(address, BatchLane::Hot).await
+}
+
+pub async fn buffered_get_account_in(address: &Pubkey, lane: BatchLane) -> AResult<Account> {
+    buffered_get_account_with_slot_in(address, lane)
+        .await
+        .map(|(account, _)| account)
+}
+
+pub async fn buffered_get_account_with_slot(address: &Pubkey) -> AResult<(Account, u64)> {
+    buffered_get_account_with_slot_in(address, BatchLane::Hot).await
+}
+
+pub async fn buffered_get_account_with_slot_in(
+    address: &Pubkey,
+    lane: BatchLane,
+) -> AResult<(Account, u64)> {
+    let (tx, mut rx) = channel::<AResult<(Account, u64)>>(1);
     let request = Request {
         address: *address,
         on_response: tx,
+        queued_at: Instant::now(),
     };
-    let _ = get_sender().await.send(request).await?;
+    let _ = get_sender(lane).await.send(request).await?;
     rx.recv()
         .await
         .or_else_err(lined_err!("channel closed unexpectedly"))?
 }
 
-pub async fn buffered_get_account_batch(addresses: &[Pubkey]) -> AResult<Vec<Option<Account>>> {
+pub async fn buffered_get_account_batch(
+    addresses: &[Pubkey],
+    lane: BatchLane,
+) -> AResult<Vec<Option<Account>>> {
     let mut receivers = Vec::with_capacity(addresses.len());
-    let sender = get_sender().await;
+    let sender = get_sender(lane).await;
 
     for address in addresses {
-        let (tx, rx) = channel::<AResult<Account>>(1);
+        let (tx, rx) = channel::<AResult<(Account, u64)>>(1);
         let request = Request {
             address: *address,
             on_response: tx,
+            queued_at: Instant::now(),
         };
         sender.send(request).await?;
         receivers.push(rx);
@@ -72,7 +203,7 @@ pub async fn buffered_get_account_batch(addresses: &[Pubkey]) -> AResult<Vec<Opt
     let mut results = Vec::with_capacity(addresses.len());
     for mut rx in receivers {
         let result = rx.recv().await.or_err("channel closed unexpectedly")?;
-        results.push(result.ok());
+        results.push(result.ok().map(|(account, _)| account));
     }
 
     Ok(results)
@@ -80,8 +211,9 @@ pub async fn buffered_get_account_batch(addresses: &[Pubkey]) -> AResult<Vec<Opt
 
 pub async fn buffered_get_account_batch_map(
     addresses: &[Pubkey],
+    lane: BatchLane,
 ) -> AResult<HashMap<Pubkey, Account>> {
-    let batch_results = buffered_get_account_batch(addresses).await?;
+    let batch_results = buffered_get_account_batch(addresses, lane).await?;
 
     let mut map = HashMap::with_capacity(addresses.len());
     for (address, account_opt) in addresses.iter().zip(batch_results.into_iter()) {
@@ -93,75 +225,192 @@ pub async fn buffered_get_account_batch_map(
     Ok(map)
 }
 
-async fn loop_forever(mut pipeline: Receiver<Request>) {
+async fn loop_forever(lane: BatchLane, mut pipeline: Receiver<Request>) {
+    let logger = PeriodicLogger::<BatchMetric>::new(
+        format!("AccountBatch:{}", lane.name()),
+        Duration::from_secs(10),
+    );
+    let metrics = logger.metrics_handle();
+    logger.spawn();
+
     loop {
-        every_batch(&mut pipeline).await;
+        let Some(batch) = collect_batch(lane, &mut pipeline, &metrics).await else {
+            return;
+        };
+        dispatch_batch(batch, &metrics).await;
     }
 }
 
-async fn every_batch(pipeline: &mut Receiver<Request>) {
-    let mut ticker = time::interval(Duration::from_millis(400));
+// The window opens with the first request, so an idle lane costs nothing.
+async fn collect_batch(
+    lane: BatchLane,
+    pipeline: &mut Receiver<Request>,
+    metrics: &MetricsHandle<BatchMetric>,
+) -> Option<HashMap<Pubkey, Vec<Request>>> {
+    let first = pipeline.recv().await?;
+    let deadline = time::sleep(lane.max_wait());
+    tokio::pin!(deadline);
+
     let mut current_batch: HashMap<Pubkey, Vec<Request>> = HashMap::new();
+    current_batch.insert(first.address, vec![first]);
+    metrics.inc(BatchMetric::Requests);
 
-    loop {
-        if current_batch.len() >= 100 {
-            break;
-        }
+    while current_batch.len() < MAX_BATCH_SIZE {
         select! {
             Some(req) = pipeline.recv() => {
-                current_batch.entry(req.address).or_insert_with(Vec::new).push(req);
+                metrics.inc(BatchMetric::Requests);
+                let requests = current_batch.entry(req.address).or_default();
+                if !requests.is_empty() {
+                    metrics.inc(BatchMetric::MergedInBatch);
+                }
+                requests.push(req);
             }
-            _ = ticker.tick() => {
+            _ = &mut deadline => {
                 break
             }
         }
     }
 
-    if current_batch.len() == 0 {
-        return;
-    }
+    let now = Instant::now();
+    let waited_ms: u128 = current_batch
+        .values()
+        .flatten()
+        .map(|req| now.duration_since(req.queued_at).as_millis())
+        .sum();
+    metrics.add(BatchMetric::WaitMs, waited_ms as u64);
+    Some(current_batch)
+}
 
-    if let Err(rate_limit_err) = QueryRateLimiter.try_acquire_err() {
-        for (_, requests) in current_batch {
-            for request in requests {
-                let _ = request
-                    .on_response
-                    .send(Err(rate_limit_err.clone().into()))
-                    .await;
+async fn dispatch_batch(
+    current_batch: HashMap<Pubkey, Vec<Request>>,
+    metrics: &MetricsHandle<BatchMetric>,
+) {
+    // Addresses another batch is already fetching only wait for that answer.
+    let mut public_keys = Vec::with_capacity(current_batch.len());
+    for (address, requests) in current_batch {
+        let responders = requests.into_iter().map(|req| req.on_response);
+        match IN_FLIGHT.entry(address) {
+            dashmap::Entry::Occupied(mut entry) => {
+                metrics.inc(BatchMetric::JoinedInFlight);
+                entry.get_mut().extend(responders);
+            }
+            dashmap::Entry::Vacant(entry) => {
+                entry.insert(responders.collect());
+                public_keys.push(address);
             }
         }
+    }
+
+    if public_keys.is_empty() {
         return;
     }
 
-    let public_keys = current_batch.keys().cloned().collect::<Vec<_>>();
-    let response = rpc_client().get_multiple_accounts(&public_keys).await;
-
-    match response {
-        Ok(accounts) => {
-            for (pubkey, account_option) in public_keys.iter().zip(accounts.iter()) {
-                if let Some(requests) = current_batch.remove(pubkey) {
-                    for request in requests {
-                        let result = match account_option {
-                            Some(account) => Ok(account.clone()),
-                            None => Err(anyhow!("{} Not found", pubkey)),
-                        };
-
-                        if let Err(e) = request.on_response.send(result).await {
-                            info!("on_response dropped before send: {} ({})", e, pubkey)
-                        }
-                    }
-                }
+    let lease = match rpc(RpcRole::Read) {
+        Ok(lease) => lease,
+        Err(e) => {
+            metrics.inc(BatchMetric::RateLimited);
+            let rate_limited = is_rate_limit_error(&e);
+            for pubkey in public_keys {
+                respond(&pubkey, |_| failure(rate_limited, &e)).await;
             }
+            return;
         }
-        Err(e) => {
-            for (_, requests) in current_batch {
-                for request in requests {
-                    let _ = request
-                        .on_response
-                        .send(Err(anyhow::anyhow!("RPC error: {}", e)))
-                        .await;
+    };
+
+    metrics.inc(BatchMetric::Batches);
+    metrics.add(BatchMetric::BatchedKeys, public_keys.len() as u64);
+
+    // Later batches keep forming while this one is on the wire.
+    let metrics = metrics.clone();
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let client = lease.client();
+        let response = client
+            .get_multiple_accounts_with_commitment(&public_keys, client.commitment())
+            .await;
+        let response = lease.finish(response);
+        metrics.add(BatchMetric::RpcMs, started.elapsed().as_millis() as u64);
+
+        match response {
+            Ok(response) => {
+                let slot = response.context.slot;
+                for (pubkey, account_option) in public_keys.iter().zip(response.value.iter()) {
+                    respond(pubkey, |_| match account_option {
+                        Some(account) => Ok((account.clone(), slot)),
+                        None => Err(AccountFetchError::NotFound(*pubkey).into()),
+                    })
+                    .await;
+                }
+            }
+            Err(e) => {
+                metrics.inc(BatchMetric::Errors);
+                let rate_limited = is_rate_limited(&e);
+                for pubkey in public_keys {
+                    respond(&pubkey, |_| failure(rate_limited, &e)).await;
                 }
             }
         }
+    });
+}
+
+// Rate limiting stays recognizable to callers, so they can retry instead of giving up.
+fn failure(rate_limited: bool, error: &dyn std::fmt::Display) -> AResult<(Account, u64)> {
+    if rate_limited {
+        return Err(RateLimitError::ExceededLimit {}.into());
+    }
+    Err(AccountFetchError::Rpc(error.to_string()).into())
+}
+
+async fn respond<F>(pubkey: &Pubkey, result: F)
+where
+    F: Fn(&Pubkey) -> AResult<(Account, u64)>,
+{
+    let Some((_, responders)) = IN_FLIGHT.remove(pubkey) else {
+        return;
+    };
+    for responder in responders {
+        if let Err(e) = responder.send(result(pubkey)).await {
+            info!("on_response dropped before send: {} ({})", e, pubkey)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(address: Pubkey) -> (Request, Receiver<AResult<(Account, u64)>>) {
+        let (tx, rx) = channel(1);
+        let request = Request {
+            address,
+            on_response: tx,
+            queued_at: Instant::now(),
+        };
+        (request, rx)
+    }
+
+    #[tokio::test]
+    async fn hot_lane_flushes_without_waiting_for_a_full_batch() {
+        let metrics =
+            PeriodicLogger::<BatchMetric>::new("test", Duration::from_secs(5)).metrics_handle();
+        let (tx, mut rx) = channel::<Request>(10);
+        let address = Pubkey::new_unique();
+        let (first, _first_rx) = request(address);
+        let (second, _second_rx) = request(address);
+        let (other, _other_rx) = request(Pubkey::new_unique());
+        tx.send(first).await.unwrap();
+        tx.send(second).await.unwrap();
+        tx.send(other).await.unwrap();
+
+        let started = Instant::now();
+        let batch = collect_batch(BatchLane::Hot, &mut rx, &metrics)
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() < BatchLane::Background.max_wait());
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[&address].len(), 2);
+        assert_eq!(metrics.get(&BatchMetric::Requests), 3);
+        assert_eq!(metrics.get(&BatchMetric::MergedInBatch), 1);
     }
 }
@@ -1,6 +1,29 @@
-use crate::sdk::rpc::client::rpc_client;
+use crate::sdk::rpc::client::{rpc, rpc_unthrottled};
+use crate::sdk::rpc::pool::RpcRole;
+use crate::util::alias::AResult;
 use solana_program::hash::Hash;
+use solana_sdk::commitment_config::CommitmentConfig;
 
-pub async fn get_latest_blockhash() -> Result<Hash, solana_client::client_error::ClientError> {
-    rpc_client().get_latest_blockhash().await
+pub async fn get_latest_blockhash() -> AResult<Hash> {
+    let lease = rpc_unthrottled(RpcRole::Read)?;
+    let result = lease.client().get_latest_blockhash().await;
+    Ok(lease.finish(result)?)
+}
+
+pub async fn get_slot(commitment: CommitmentConfig) -> AResult<u64> {
+    let lease = rpc(RpcRole::Read)?;
+    let result = lease.client().get_slot_with_commitment(commitment).await;
+    Ok(lease.finish(result)?)
+}
+
+// Blockhash together with the last block height at which it is still accepted.
+pub async fn get_latest_blockhash_with_height(
+    commitment: CommitmentConfig,
+) -> AResult<(Hash, u64)> {
+    let lease = rpc_unthrottled(RpcRole::Read)?;
+    let result = lease
+        .client()
+        .get_latest_blockhash_with_commitment(commitment)
+        .await;
+    Ok(lease.finish(result)?)
 }
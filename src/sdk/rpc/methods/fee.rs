@@ -0,0 +1,18 @@
+use crate::sdk::rpc::client::rpc;
+use crate::sdk::rpc::pool::RpcRole;
+use crate::util::alias::AResult;
+use solana_client::rpc_response::RpcPrioritizationFee;
+use solana_sdk::pubkey::Pubkey;
+
+// The node reports, per recent slot, the lowest fee paid by transactions that
+// write-locked the given accounts. An empty list returns the global minimum.
+pub async fn get_recent_prioritization_fees(
+    accounts: &[Pubkey],
+) -> AResult<Vec<RpcPrioritizationFee>> {
+    let lease = rpc(RpcRole::Read)?;
+    let result = lease
+        .client()
+        .get_recent_prioritization_fees(accounts)
+        .await;
+    Ok(lease.finish(result)?)
+}
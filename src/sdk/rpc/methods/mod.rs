@@ -1,5 +1,5 @@
 pub mod account;
 pub mod block;
-mod limiter;
+pub mod fee;
 pub mod simulation;
 pub mod transaction;
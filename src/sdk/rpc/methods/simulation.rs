@@ -1,4 +1,6 @@
-use crate::sdk::rpc::client::rpc_client;
+use crate::sdk::rpc::client::rpc;
+use crate::sdk::rpc::pool::RpcRole;
+use crate::util::alias::AResult;
 use solana_client::rpc_config::RpcSimulateTransactionConfig;
 use solana_client::rpc_response::{Response, RpcSimulateTransactionResult};
 use solana_sdk::transaction::VersionedTransaction;
@@ -6,8 +8,11 @@ use solana_sdk::transaction::VersionedTransaction;
 pub async fn simulate_transaction_with_config(
     transaction: &VersionedTransaction,
     config: RpcSimulateTransactionConfig,
-) -> Result<Response<RpcSimulateTransactionResult>, solana_client::client_error::ClientError> {
-    rpc_client()
+) -> AResult<Response<RpcSimulateTransactionResult>> {
+    let lease = rpc(RpcRole::Simulate)?;
+    let result = lease
+        .client()
         .simulate_transaction_with_config(transaction, config)
-        .await
+        .await;
+    Ok(lease.finish(result)?)
 }
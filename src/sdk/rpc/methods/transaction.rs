@@ -1,17 +1,23 @@
 use crate::convention::chain::mapper::traits::ToUnified;
+use crate::convention::chain::util::alt::select_minimal_alts;
 use crate::convention::chain::Transaction;
 use crate::lined_err;
 use crate::sdk::rpc::client;
-use crate::sdk::rpc::client::rpc_client;
+use crate::sdk::rpc::client::rpc;
+use crate::sdk::rpc::pool::RpcRole;
 use crate::util::alias::AResult;
 use crate::util::traits::signature::ToSignature;
+use solana_client::rpc_config::RpcTransactionConfig;
 use solana_program::address_lookup_table::AddressLookupTableAccount;
 use solana_program::hash::Hash;
 use solana_program::instruction::Instruction;
 use solana_program::message::v0::Message;
-use solana_sdk::commitment_config::CommitmentLevel;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
 use solana_sdk::signature::{Keypair, Signature, Signer};
 use solana_sdk::transaction::VersionedTransaction;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionStatus, UiTransactionEncoding,
+};
 use tracing::info;
 
 pub async fn send_transaction(tx: &VersionedTransaction) -> AResult<Signature> {
@@ -19,7 +25,9 @@ pub async fn send_transaction(tx: &VersionedTransaction) -> AResult<Signature> {
     let tx_size = tx_bytes.len();
     info!("Transaction size after compilation: {} bytes", tx_size);
 
-    rpc_client()
+    let lease = rpc(RpcRole::Send)?;
+    let result = lease
+        .client()
         .send_transaction_with_config(
             tx,
             solana_client::rpc_config::RpcSendTransactionConfig {
@@ -28,25 +36,61 @@ pub async fn send_transaction(tx: &VersionedTransaction) -> AResult<Signature> {
                 ..Default::default()
             },
         )
-        .await
-        .map_err(Into::into)
+        .await;
+    Ok(lease.finish(result)?)
 }
 
 pub async fn fetch_tx(signature: &str) -> AResult<Transaction> {
-    client::rpc_client()
+    let lease = rpc(RpcRole::Read)?;
+    let result = lease
+        .client()
         .get_transaction_with_config(&signature.to_sig(), client::json_config())
-        .await
+        .await;
+    lease
+        .finish(result)
         .map_err(|e| lined_err!("Failed to fetch transaction: {}", e))?
         .to_unified()
 }
 
+// Binary encoding keeps the message and inner instructions compiled, as Geyser delivers them.
+pub async fn fetch_encoded_tx(
+    signature: &str,
+    commitment: CommitmentConfig,
+) -> AResult<EncodedConfirmedTransactionWithStatusMeta> {
+    let lease = rpc(RpcRole::Read)?;
+    let result = lease
+        .client()
+        .get_transaction_with_config(
+            &signature.to_sig(),
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(commitment),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await;
+    lease
+        .finish(result)
+        .map_err(|e| lined_err!("Failed to fetch transaction: {}", e))
+}
+
+// One status per signature, None when the cluster has not seen it. At most 256 at a time.
+pub async fn get_signature_statuses(
+    signatures: &[Signature],
+) -> AResult<Vec<Option<TransactionStatus>>> {
+    let lease = rpc(RpcRole::Read)?;
+    let result = lease.client().get_signature_statuses(signatures).await;
+    Ok(lease.finish(result)?.value)
+}
+
 pub fn compile_instruction_to_tx(
     wallet: &Keypair,
     instructions: Vec<Instruction>,
     alts: &[AddressLookupTableAccount],
     blockhash: Hash,
 ) -> AResult<VersionedTransaction> {
-    let message = Message::try_compile(&wallet.pubkey(), &instructions, alts, blockhash)?;
+    let alts = select_minimal_alts(&wallet.pubkey(), &instructions, alts);
+    let message = Message::try_compile(&wallet.pubkey(), &instructions, &alts, blockhash)?;
     let tx = VersionedTransaction::try_new(
         solana_sdk::message::VersionedMessage::V0(message),
         &[wallet],
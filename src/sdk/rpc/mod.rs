@@ -1,3 +1,4 @@
 pub mod client;
 pub mod legacy;
 pub mod methods;
+pub mod pool;
@@ -0,0 +1,414 @@
+use crate::global::trace::facade::active_traceparent;
+use crate::lined_err;
+use crate::util::alias::AResult;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use crate::util::random::weighted_select;
+use crate::util::structs::rate_limiter::{AdaptiveConfig, RateLimitError, RateLimiter};
+use parking_lot::Mutex;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::RpcClientConfig;
+use solana_client::rpc_request::RpcError;
+use solana_rpc_client::http_sender::HttpSender;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const LATENCY_SMOOTHING: f64 = 0.2;
+const ERROR_SMOOTHING: f64 = 0.1;
+const INITIAL_LATENCY_MS: f64 = 100.0;
+const EVICT_AFTER_FAILURES: u32 = 5;
+const EVICTION: Duration = Duration::from_secs(30);
+// ~8 seconds behind the most advanced endpoint
+const MAX_SLOT_LAG: u64 = 20;
+// Same as the default solana `HttpSender`.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RpcRole {
+    Read,
+    Simulate,
+    Send,
+}
+
+impl RpcRole {
+    pub const ALL: [RpcRole; 3] = [RpcRole::Read, RpcRole::Simulate, RpcRole::Send];
+
+    // "read+simulate" style lists; an empty list means every role.
+    pub fn parse_set(value: &str) -> AResult<Vec<RpcRole>> {
+        let roles = value
+            .split('+')
+            .map(str::trim)
+            .filter(|role| !role.is_empty())
+            .map(|role| match role.to_lowercase().as_str() {
+                "read" => Ok(RpcRole::Read),
+                "simulate" => Ok(RpcRole::Simulate),
+                "send" => Ok(RpcRole::Send),
+                other => Err(lined_err!("unknown RPC role {}", other)),
+            })
+            .collect::<AResult<Vec<_>>>()?;
+        if roles.is_empty() {
+            return Ok(RpcRole::ALL.to_vec());
+        }
+        Ok(roles)
+    }
+}
+
+struct EndpointStats {
+    latency_ms: f64,
+    error_rate: f64,
+    consecutive_failures: u32,
+    slot: u64,
+    evicted_until: Option<Instant>,
+}
+
+// Calls made inside a trace carry its `traceparent`, so they show up under the run.
+fn traced_client(url: &str, commitment: CommitmentConfig) -> RpcClient {
+    let client = reqwest::Client::builder()
+        .default_headers(HttpSender::default_headers())
+        .timeout(REQUEST_TIMEOUT)
+        .pool_idle_timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("Failed to build RPC HTTP client");
+    let client = reqwest_middleware::ClientBuilder::new(client)
+        .with_init(
+            |request: reqwest_middleware::RequestBuilder| match active_traceparent() {
+                Some(traceparent) => request.header("traceparent", traceparent),
+                None => request,
+            },
+        )
+        .build();
+    RpcClient::new_sender(
+        HttpSender::new_with_client_with_middleware(url, client),
+        RpcClientConfig::with_commitment(commitment),
+    )
+}
+
+pub struct RpcEndpoint {
+    pub url: String,
+    // URLs often carry an API key, so metrics only ever see the configured position.
+    label: String,
+    roles: Vec<RpcRole>,
+    client: Arc<RpcClient>,
+    limiter: RateLimiter,
+    stats: Mutex<EndpointStats>,
+}
+
+impl RpcEndpoint {
+    pub fn new(
+        index: usize,
+        url: String,
+        roles: Vec<RpcRole>,
+        requests_per_second: u32,
+        commitment: CommitmentConfig,
+    ) -> Self {
+        Self {
+            client: Arc::new(traced_client(&url, commitment)),
+            limiter: RateLimiter::new(
+                requests_per_second,
+                Duration::from_secs(1),
+                requests_per_second + requests_per_second / 2,
+                format!("RpcRateLimiter:{}", index),
+            )
+            .with_adaptive(AdaptiveConfig {
+                min_requests: (requests_per_second / 10).max(1),
+                decrease_factor: 0.5,
+                increase_per_window: (requests_per_second as f64 / 20.0).max(1.0),
+            }),
+            url,
+            label: index.to_string(),
+            roles,
+            stats: Mutex::new(EndpointStats {
+                latency_ms: INITIAL_LATENCY_MS,
+                error_rate: 0.0,
+                consecutive_failures: 0,
+                slot: 0,
+                evicted_until: None,
+            }),
+        }
+    }
+
+    fn serves(&self, role: RpcRole) -> bool {
+        self.roles.contains(&role)
+    }
+
+    fn is_evicted(&self, now: Instant) -> bool {
+        self.stats
+            .lock()
+            .evicted_until
+            .is_some_and(|until| now < until)
+    }
+
+    // Fast endpoints get most of the traffic, failing ones only a trickle.
+    fn weight(&self) -> f64 {
+        let stats = self.stats.lock();
+        (1.0 - stats.error_rate).max(0.05) / stats.latency_ms.max(1.0)
+    }
+
+    fn record(&self, elapsed: Duration, failed: bool) {
+        let mut stats = self.stats.lock();
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        stats.latency_ms += (elapsed_ms - stats.latency_ms) * LATENCY_SMOOTHING;
+        let outcome = if failed { 1.0 } else { 0.0 };
+        stats.error_rate += (outcome - stats.error_rate) * ERROR_SMOOTHING;
+
+        if !failed {
+            stats.consecutive_failures = 0;
+            return;
+        }
+        stats.consecutive_failures += 1;
+        if stats.consecutive_failures >= EVICT_AFTER_FAILURES {
+            stats.consecutive_failures = 0;
+            stats.evicted_until = Some(Instant::now() + EVICTION);
+            warn!(
+                "Evicting RPC endpoint #{} after {} consecutive failures",
+                self.label, EVICT_AFTER_FAILURES
+            );
+        }
+    }
+
+    fn evict(&self, reason: &str) {
+        self.stats.lock().evicted_until = Some(Instant::now() + EVICTION);
+        warn!("Evicting RPC endpoint #{}: {}", self.label, reason);
+    }
+}
+
+// HTTP 429, or a JSON-RPC error some providers send instead of it.
+pub fn is_rate_limited(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Reqwest(e) => e.status().is_some_and(|status| status.as_u16() == 429),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. }) => {
+            let message = message.to_lowercase();
+            *code == 429
+                || *code == -32429
+                || message.contains("rate limit")
+                || message.contains("too many requests")
+        }
+        _ => false,
+    }
+}
+
+// Both our own limiter running dry and the provider throttling us.
+pub fn is_rate_limit_error(error: &anyhow::Error) -> bool {
+    error.is::<RateLimitError>()
+        || error
+            .downcast_ref::<ClientError>()
+            .is_some_and(is_rate_limited)
+}
+
+// Only failures of the endpoint itself count, not errors the node reports about the request
+// or throttling, which the limiter absorbs.
+fn is_endpoint_failure(error: &ClientError) -> bool {
+    !is_rate_limited(error)
+        && matches!(
+            error.kind(),
+            ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) | ClientErrorKind::Middleware(_)
+        )
+}
+
+pub struct RpcLease {
+    endpoint: Arc<RpcEndpoint>,
+    started: Instant,
+}
+
+impl RpcLease {
+    pub fn client(&self) -> &RpcClient {
+        &self.endpoint.client
+    }
+
+    pub fn url(&self) -> &str {
+        &self.endpoint.url
+    }
+
+    // Hands the result back after feeding its latency and outcome to the endpoint stats.
+    #[allow(clippy::result_large_err)]
+    pub fn finish<T>(self, result: Result<T, ClientError>) -> Result<T, ClientError> {
+        if result.as_ref().is_err_and(is_rate_limited) {
+            self.endpoint.limiter.on_throttled();
+        }
+        let failed = result.as_ref().is_err_and(is_endpoint_failure);
+        self.endpoint.record(self.started.elapsed(), failed);
+        result
+    }
+}
+
+pub struct RpcPool {
+    endpoints: Vec<Arc<RpcEndpoint>>,
+}
+
+impl RpcPool {
+    pub fn new(endpoints: Vec<RpcEndpoint>) -> Self {
+        Self {
+            endpoints: endpoints.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    // Every role counts against the limit of the endpoint it lands on.
+    pub fn acquire(&self, role: RpcRole) -> AResult<RpcLease> {
+        self.acquire_with(role, true)
+    }
+
+    // Only for the blockhash and nonce calls a send waits on, which must not fail while
+    // background reads use up the budget.
+    pub fn acquire_unthrottled(&self, role: RpcRole) -> AResult<RpcLease> {
+        self.acquire_with(role, false)
+    }
+
+    fn acquire_with(&self, role: RpcRole, limited: bool) -> AResult<RpcLease> {
+        let now = Instant::now();
+        let serving: Vec<Arc<RpcEndpoint>> = self
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.serves(role))
+            .cloned()
+            .collect();
+        if serving.is_empty() {
+            return Err(lined_err!("no RPC endpoint configured for {:?}", role));
+        }
+
+        // With every endpoint evicted, a degraded one still beats failing outright.
+        let mut candidates: Vec<Arc<RpcEndpoint>> = serving
+            .iter()
+            .filter(|endpoint| !endpoint.is_evicted(now))
+            .cloned()
+            .collect();
+        if candidates.is_empty() {
+            candidates = serving;
+        }
+
+        while let Some(endpoint) = weighted_select(&candidates, |e| e.weight()).cloned() {
+            if !limited || endpoint.limiter.try_acquire() {
+                return Ok(RpcLease {
+                    endpoint,
+                    started: Instant::now(),
+                });
+            }
+            candidates.retain(|candidate| !Arc::ptr_eq(candidate, &endpoint));
+        }
+        Err(RateLimitError::ExceededLimit {}.into())
+    }
+
+    pub async fn check_health(&self) {
+        let probes = self.endpoints.iter().map(|endpoint| async move {
+            let started = Instant::now();
+            let result = endpoint.client.get_slot().await;
+            endpoint.record(started.elapsed(), result.is_err());
+            if let Ok(slot) = result {
+                endpoint.stats.lock().slot = slot;
+            }
+        });
+        futures::future::join_all(probes).await;
+
+        let highest = self
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.stats.lock().slot)
+            .max()
+            .unwrap_or(0);
+        for endpoint in &self.endpoints {
+            let lag = highest.saturating_sub(endpoint.stats.lock().slot);
+            if lag > MAX_SLOT_LAG {
+                endpoint.evict(&format!("{} slots behind", lag));
+            }
+        }
+    }
+
+    pub fn export_health(&self) {
+        let now = Instant::now();
+        for endpoint in &self.endpoints {
+            let labels = [("endpoint", endpoint.label.as_str())];
+            let evicted = endpoint.is_evicted(now);
+            let stats = endpoint.stats.lock();
+            METRICS_REGISTRY.set_gauge(
+                "rpc_endpoint_latency_seconds",
+                &labels,
+                stats.latency_ms / 1000.0,
+            );
+            METRICS_REGISTRY.set_gauge("rpc_endpoint_error_rate", &labels, stats.error_rate);
+            METRICS_REGISTRY.set_gauge("rpc_endpoint_slot", &labels, stats.slot as f64);
+            METRICS_REGISTRY.set_gauge(
+                "rpc_endpoint_evicted",
+                &labels,
+                if evicted { 1.0 } else { 0.0 },
+            );
+        }
+    }
+
+    pub fn log_health(&self) {
+        let summary: Vec<String> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                let stats = endpoint.stats.lock();
+                format!(
+                    "#{}: latency={:.0}ms errors={:.0}% rate={:.0}/s slot={} evicted={}",
+                    endpoint.label,
+                    stats.latency_ms,
+                    stats.error_rate * 100.0,
+                    endpoint.limiter.current_rate(),
+                    stats.slot,
+                    stats
+                        .evicted_until
+                        .is_some_and(|until| Instant::now() < until),
+                )
+            })
+            .collect();
+        info!("RPC endpoints | {}", summary.join(" | "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(url: &str, roles: Vec<RpcRole>, requests_per_second: u32) -> RpcEndpoint {
+        RpcEndpoint::new(
+            0,
+            url.to_string(),
+            roles,
+            requests_per_second,
+            CommitmentConfig::processed(),
+        )
+    }
+
+    #[test]
+    fn routes_by_role_and_skips_evicted_and_exhausted_endpoints() {
+        let pool = RpcPool::new(vec![
+            endpoint("http://reader", vec![RpcRole::Read], 2),
+            endpoint("http://sender", vec![RpcRole::Send], 1),
+            endpoint("http://evicted", vec![RpcRole::Read], 100),
+        ]);
+        pool.endpoints[2].evict("test");
+
+        // burst capacity of the only healthy reader is 3
+        for _ in 0..3 {
+            assert_eq!(pool.acquire(RpcRole::Read).unwrap().url(), "http://reader");
+        }
+        assert!(pool.acquire(RpcRole::Read).is_err());
+        assert_eq!(
+            pool.acquire_unthrottled(RpcRole::Read).unwrap().url(),
+            "http://reader"
+        );
+        // sends are limited as well
+        assert_eq!(pool.acquire(RpcRole::Send).unwrap().url(), "http://sender");
+        assert!(pool.acquire(RpcRole::Send).is_err());
+        assert!(pool.acquire(RpcRole::Simulate).is_err());
+    }
+
+    #[test]
+    fn evicts_after_consecutive_failures() {
+        let endpoint = endpoint("http://flaky", RpcRole::ALL.to_vec(), 10);
+        let healthy_weight = endpoint.weight();
+        for _ in 0..EVICT_AFTER_FAILURES {
+            endpoint.record(Duration::from_millis(100), true);
+        }
+
+        assert!(endpoint.is_evicted(Instant::now()));
+        assert!(endpoint.weight() < healthy_weight);
+        assert_eq!(
+            RpcRole::parse_set("read+send").unwrap(),
+            vec![RpcRole::Read, RpcRole::Send]
+        );
+    }
+}
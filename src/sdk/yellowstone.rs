@@ -1,29 +1,90 @@
+use crate::convention::chain::stamp::Commitment;
 use crate::util::env::env_config::ENV_CONFIG;
 use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use solana_sdk::hash::Hash;
 use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 use std::error::Error;
+use std::hash::{DefaultHasher, Hash as _, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio_stream::StreamExt;
 use tracing::{error, info, warn};
 use yellowstone_grpc_client::GeyserGrpcClient;
 use yellowstone_grpc_proto::prelude::*;
 
+// Called after a reconnect that could not replay the missed slots.
+pub type GapHandler = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>;
+
+// Last slot whose updates were fully handed to the callback. Resubscribing from it
+// replays that slot again, which is harmless, instead of losing what came after it.
+#[derive(Default)]
+pub struct SubscriptionCursor {
+    last_slot: AtomicU64,
+    skip_replay: AtomicBool,
+}
+
+impl SubscriptionCursor {
+    pub fn last_slot(&self) -> Option<u64> {
+        match self.last_slot.load(Ordering::Relaxed) {
+            0 => None,
+            slot => Some(slot),
+        }
+    }
+
+    fn record(&self, slot: u64) {
+        self.last_slot.fetch_max(slot, Ordering::Relaxed);
+    }
+
+    fn resume_slot(&self) -> Option<u64> {
+        if self.skip_replay.swap(false, Ordering::Relaxed) {
+            return None;
+        }
+        self.last_slot()
+    }
+}
+
 pub struct SolanaGrpcClient {
     endpoint: String,
     token: String,
     client: Option<GeyserGrpcClient<yellowstone_grpc_client::InterceptorXToken>>,
+    cursor: Arc<SubscriptionCursor>,
+    gap_handler: Option<GapHandler>,
+    commitment: Commitment,
+    source: u64,
 }
 
 impl SolanaGrpcClient {
     pub fn new(endpoint: String, token: String) -> Self {
         Self {
+            source: stream_source(&endpoint),
             endpoint,
             token,
             client: None,
+            cursor: Arc::new(SubscriptionCursor::default()),
+            gap_handler: None,
+            commitment: Commitment::Processed,
         }
     }
 
+    pub fn with_commitment(mut self, commitment: Commitment) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    // Share a cursor between short-lived clients of the same subscription.
+    pub fn with_cursor(mut self, cursor: Arc<SubscriptionCursor>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    pub fn with_gap_handler(mut self, handler: GapHandler) -> Self {
+        self.gap_handler = Some(handler);
+        self
+    }
+
     pub fn from_env() -> Result<Self> {
         let endpoint = ENV_CONFIG.grpc_url.clone();
         let token = ENV_CONFIG.grpc_token.clone();
@@ -48,9 +109,10 @@ impl SolanaGrpcClient {
         }
     }
 
+    // Filters are combined with OR; the conditions inside one filter with AND.
     pub async fn subscribe_accounts<F, Fut>(
         mut self,
-        filter: AccountFilter,
+        filters: Vec<AccountFilter>,
         callback: F,
         auto_retry: bool,
     ) -> Result<()>
@@ -59,11 +121,71 @@ impl SolanaGrpcClient {
         Fut: std::future::Future<Output = Result<()>> + Send,
     {
         if auto_retry {
-            self.subscribe_accounts_with_retry_internal(filter, callback)
+            self.subscribe_accounts_with_retry_internal(filters, callback)
                 .await
         } else {
             self.connect_if_needed().await?;
-            self.subscribe_accounts_once(filter, callback).await
+            self.subscribe_accounts_once(filters, callback).await
+        }
+    }
+
+    // Every status change of every slot, regardless of the client commitment.
+    pub async fn subscribe_slots<F, Fut>(mut self, callback: F, auto_retry: bool) -> Result<()>
+    where
+        F: Fn(GrpcSlotUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send,
+    {
+        if !auto_retry {
+            self.connect_if_needed().await?;
+            return self.subscribe_slots_once(callback).await;
+        }
+
+        loop {
+            if let Err(e) = self.connect_if_needed().await {
+                error!("Failed to connect: {}, retrying in 5 seconds...", e);
+            } else if let Err(e) = self.subscribe_slots_once(callback.clone()).await {
+                error!(
+                    "Slot subscription error: {}, reconnecting in 5 seconds...",
+                    e
+                );
+            } else {
+                warn!("Slot subscription ended, reconnecting in 5 seconds...");
+            }
+
+            self.client = None;
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        }
+    }
+
+    // One update per block once it is produced, carrying its blockhash and height.
+    pub async fn subscribe_blocks_meta<F, Fut>(
+        mut self,
+        callback: F,
+        auto_retry: bool,
+    ) -> Result<()>
+    where
+        F: Fn(GrpcBlockMetaUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send,
+    {
+        if !auto_retry {
+            self.connect_if_needed().await?;
+            return self.subscribe_blocks_meta_once(callback).await;
+        }
+
+        loop {
+            if let Err(e) = self.connect_if_needed().await {
+                error!("Failed to connect: {}, retrying in 5 seconds...", e);
+            } else if let Err(e) = self.subscribe_blocks_meta_once(callback.clone()).await {
+                error!(
+                    "Block meta subscription error: {}, reconnecting in 5 seconds...",
+                    e
+                );
+            } else {
+                warn!("Block meta subscription ended, reconnecting in 5 seconds...");
+            }
+
+            self.client = None;
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
         }
     }
 
@@ -156,11 +278,35 @@ impl SolanaGrpcClient {
         Ok(())
     }
 
+    fn on_replay_rejected(&self, from_slot: Option<u64>) {
+        if let Some(slot) = from_slot {
+            warn!(
+                "{} did not replay from slot {}, resubscribing live and refetching instead",
+                self.endpoint, slot
+            );
+            self.cursor.skip_replay.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn fill_gap_if_needed(&self, from_slot: Option<u64>) {
+        if from_slot.is_some() || self.cursor.last_slot().is_none() {
+            return;
+        }
+        if let Some(handler) = self.gap_handler.clone() {
+            info!(
+                "Refetching accounts missed while {} was down",
+                self.endpoint
+            );
+            tokio::spawn(async move { handler().await });
+        }
+    }
+
     async fn subscribe_once<F, Fut>(&mut self, filter: TransactionFilter, callback: F) -> Result<()>
     where
         F: Fn(GrpcTransactionUpdate) -> Fut + Send + Sync + 'static,
         Fut: std::future::Future<Output = Result<()>> + Send,
     {
+        let from_slot = self.cursor.resume_slot();
         let client = self.client.as_mut().context("Client not connected")?;
 
         let filter_name = filter.name.clone();
@@ -175,32 +321,42 @@ impl SolanaGrpcClient {
             blocks: HashMap::new(),
             blocks_meta: HashMap::new(),
             entry: HashMap::new(),
-            commitment: Some(CommitmentLevel::Processed as i32),
+            commitment: Some(grpc_commitment(self.commitment) as i32),
             accounts_data_slice: vec![],
             ping: None,
-            from_slot: None,
+            from_slot,
         };
 
-        let (_subscribe_tx, response) = client
-            .subscribe_with_request(Some(subscribe_request))
-            .await
-            .context("Failed to subscribe")?;
+        let (_subscribe_tx, response) =
+            match client.subscribe_with_request(Some(subscribe_request)).await {
+                Ok(subscription) => subscription,
+                Err(e) => {
+                    self.on_replay_rejected(from_slot);
+                    return Err(e).context("Failed to subscribe");
+                }
+            };
+        self.fill_gap_if_needed(from_slot);
 
         info!("Subscription established for filter: {}", filter_name);
 
         let callback = Arc::new(callback);
         let mut stream = response;
+        let mut received = false;
 
         while let Some(message) = stream.next().await {
             match message {
                 Ok(update) => {
+                    received = true;
                     if let Some(update) = update.update_oneof {
                         match update {
                             subscribe_update::UpdateOneof::Transaction(tx) => {
-                                let transaction_update = GrpcTransactionUpdate::from_grpc(tx);
+                                let transaction_update =
+                                    GrpcTransactionUpdate::from_grpc(tx, self.commitment);
+                                let slot = transaction_update.slot;
                                 if let Err(e) = callback(transaction_update).await {
                                     error!("Callback error: {}", e);
                                 }
+                                self.cursor.record(slot);
                             }
                             subscribe_update::UpdateOneof::Ping(_) => {
                                 info!("Received ping from gRPC");
@@ -211,6 +367,9 @@ impl SolanaGrpcClient {
                 }
                 Err(e) => {
                     error!("Stream error: {}", e);
+                    if !received {
+                        self.on_replay_rejected(from_slot);
+                    }
                     return Err(anyhow::anyhow!("Stream error: {}", e));
                 }
             }
@@ -258,18 +417,21 @@ impl SolanaGrpcClient {
 
     async fn subscribe_accounts_once<F, Fut>(
         &mut self,
-        filter: AccountFilter,
+        filters: Vec<AccountFilter>,
         callback: F,
     ) -> Result<()>
     where
         F: Fn(GrpcAccountUpdate) -> Fut + Send + Sync + 'static,
         Fut: std::future::Future<Output = Result<()>> + Send,
     {
+        let from_slot = self.cursor.resume_slot();
         let client = self.client.as_mut().context("Client not connected")?;
 
-        let filter_name = filter.name.clone();
-        let mut accounts = HashMap::new();
-        accounts.insert(filter_name.clone(), filter.into_request_filter());
+        let filter_name = AccountFilter::names(&filters);
+        let accounts: HashMap<String, SubscribeRequestFilterAccounts> = filters
+            .into_iter()
+            .map(|filter| (filter.name.clone(), filter.into_request_filter()))
+            .collect();
 
         let subscribe_request = SubscribeRequest {
             accounts,
@@ -279,16 +441,21 @@ impl SolanaGrpcClient {
             blocks: HashMap::new(),
             blocks_meta: HashMap::new(),
             entry: HashMap::new(),
-            commitment: Some(CommitmentLevel::Confirmed as i32),
+            commitment: Some(grpc_commitment(self.commitment) as i32),
             accounts_data_slice: vec![],
             ping: None,
-            from_slot: None,
+            from_slot,
         };
 
-        let (_subscribe_tx, response) = client
-            .subscribe_with_request(Some(subscribe_request))
-            .await
-            .context("Failed to subscribe")?;
+        let (_subscribe_tx, response) =
+            match client.subscribe_with_request(Some(subscribe_request)).await {
+                Ok(subscription) => subscription,
+                Err(e) => {
+                    self.on_replay_rejected(from_slot);
+                    return Err(e).context("Failed to subscribe");
+                }
+            };
+        self.fill_gap_if_needed(from_slot);
 
         info!(
             "Account subscription established for filter: {}",
@@ -297,17 +464,25 @@ impl SolanaGrpcClient {
 
         let callback = Arc::new(callback);
         let mut stream = response;
+        let mut received = false;
 
         while let Some(message) = stream.next().await {
             match message {
                 Ok(update) => {
+                    received = true;
                     if let Some(update) = update.update_oneof {
                         match update {
                             subscribe_update::UpdateOneof::Account(account) => {
-                                let account_update = GrpcAccountUpdate::from_grpc(account);
+                                let account_update = GrpcAccountUpdate::from_grpc(
+                                    account,
+                                    self.commitment,
+                                    self.source,
+                                );
+                                let slot = account_update.slot;
                                 if let Err(e) = callback(account_update).await {
                                     error!("Callback error: {}", e);
                                 }
+                                self.cursor.record(slot);
                             }
                             subscribe_update::UpdateOneof::Ping(_) => {
                                 info!("Received ping from gRPC");
@@ -316,6 +491,121 @@ impl SolanaGrpcClient {
                         }
                     }
                 }
+                Err(e) => {
+                    error!("Stream error: {}", e);
+                    if !received {
+                        self.on_replay_rejected(from_slot);
+                    }
+                    return Err(anyhow::anyhow!("Stream error: {}", e));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn subscribe_slots_once<F, Fut>(&mut self, callback: F) -> Result<()>
+    where
+        F: Fn(GrpcSlotUpdate) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send,
+    {
+        let client = self.client.as_mut().context("Client not connected")?;
+
+        let mut slots = HashMap::new();
+        slots.insert(
+            "slots".to_string(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(false),
+                interslot_updates: Some(false),
+            },
+        );
+
+        let subscribe_request = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots,
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta: HashMap::new(),
+            entry: HashMap::new(),
+            commitment: Some(CommitmentLevel::Processed as i32),
+            accounts_data_slice: vec![],
+            ping: None,
+            from_slot: None,
+        };
+
+        let (_subscribe_tx, mut stream) = client
+            .subscribe_with_request(Some(subscribe_request))
+            .await
+            .context("Failed to subscribe")?;
+        info!("Slot subscription established");
+
+        while let Some(message) = stream.next().await {
+            match message {
+                Ok(update) => {
+                    if let Some(subscribe_update::UpdateOneof::Slot(slot)) = update.update_oneof {
+                        if let Err(e) = callback(GrpcSlotUpdate::from_grpc(slot)).await {
+                            error!("Callback error: {}", e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Stream error: {}", e);
+                    return Err(anyhow::anyhow!("Stream error: {}", e));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn subscribe_blocks_meta_once<F, Fut>(&mut self, callback: F) -> Result<()>
+    where
+        F: Fn(GrpcBlockMetaUpdate) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send,
+    {
+        let client = self.client.as_mut().context("Client not connected")?;
+
+        let mut blocks_meta = HashMap::new();
+        blocks_meta.insert(
+            "blocks_meta".to_string(),
+            SubscribeRequestFilterBlocksMeta {},
+        );
+
+        let subscribe_request = SubscribeRequest {
+            accounts: HashMap::new(),
+            slots: HashMap::new(),
+            transactions: HashMap::new(),
+            transactions_status: HashMap::new(),
+            blocks: HashMap::new(),
+            blocks_meta,
+            entry: HashMap::new(),
+            commitment: Some(grpc_commitment(self.commitment) as i32),
+            accounts_data_slice: vec![],
+            ping: None,
+            from_slot: None,
+        };
+
+        let (_subscribe_tx, mut stream) = client
+            .subscribe_with_request(Some(subscribe_request))
+            .await
+            .context("Failed to subscribe")?;
+        info!("Block meta subscription established");
+
+        while let Some(message) = stream.next().await {
+            match message {
+                Ok(update) => {
+                    if let Some(subscribe_update::UpdateOneof::BlockMeta(meta)) =
+                        update.update_oneof
+                    {
+                        let Some(meta) = GrpcBlockMetaUpdate::from_grpc(meta) else {
+                            continue;
+                        };
+                        if let Err(e) = callback(meta).await {
+                            error!("Callback error: {}", e);
+                        }
+                    }
+                }
                 Err(e) => {
                     error!("Stream error: {}", e);
                     return Err(anyhow::anyhow!("Stream error: {}", e));
@@ -328,7 +618,7 @@ impl SolanaGrpcClient {
 
     async fn subscribe_accounts_with_retry_internal<F, Fut>(
         mut self,
-        filter: AccountFilter,
+        filters: Vec<AccountFilter>,
         callback: F,
     ) -> Result<()>
     where
@@ -350,7 +640,7 @@ impl SolanaGrpcClient {
             }
 
             match self
-                .subscribe_accounts_once(filter.clone(), callback.clone())
+                .subscribe_accounts_once(filters.clone(), callback.clone())
                 .await
             {
                 Ok(_) => {
@@ -466,6 +756,14 @@ impl AccountFilter {
         self
     }
 
+    pub fn names(filters: &[AccountFilter]) -> String {
+        filters
+            .iter()
+            .map(|filter| filter.name.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
     pub fn with_owner(mut self, owner: &Pubkey) -> Self {
         self.owner.push(owner.to_string());
         self
@@ -478,6 +776,31 @@ impl AccountFilter {
         self
     }
 
+    pub fn with_datasize(mut self, size: u64) -> Self {
+        self.filters.push(SubscribeRequestFilterAccountsFilter {
+            filter: Some(subscribe_request_filter_accounts_filter::Filter::Datasize(
+                size,
+            )),
+        });
+        self
+    }
+
+    pub fn with_memcmp(mut self, offset: u64, bytes: &[u8]) -> Self {
+        self.filters.push(SubscribeRequestFilterAccountsFilter {
+            filter: Some(subscribe_request_filter_accounts_filter::Filter::Memcmp(
+                SubscribeRequestFilterAccountsFilterMemcmp {
+                    offset,
+                    data: Some(
+                        subscribe_request_filter_accounts_filter_memcmp::Data::Bytes(
+                            bytes.to_vec(),
+                        ),
+                    ),
+                },
+            )),
+        });
+        self
+    }
+
     fn into_request_filter(self) -> SubscribeRequestFilterAccounts {
         SubscribeRequestFilterAccounts {
             account: self.account,
@@ -497,10 +820,16 @@ pub struct GrpcAccountUpdate {
     pub lamports: u64,
     pub executable: bool,
     pub rent_epoch: u64,
+    pub write_version: u64,
+    // Stream the write came from, write versions only order writes of the same source.
+    pub source: u64,
+    // Transaction that produced this write, absent for startup snapshots.
+    pub txn_signature: Option<String>,
+    pub commitment: Commitment,
 }
 
 impl GrpcAccountUpdate {
-    fn from_grpc(update: SubscribeUpdateAccount) -> Self {
+    fn from_grpc(update: SubscribeUpdateAccount, commitment: Commitment, source: u64) -> Self {
         let account = update.account.as_ref();
         let pubkey = account
             .map(|a| Pubkey::try_from(a.pubkey.as_slice()).unwrap_or_default())
@@ -514,6 +843,10 @@ impl GrpcAccountUpdate {
         let lamports = account.map(|a| a.lamports).unwrap_or_default();
         let executable = account.map(|a| a.executable).unwrap_or_default();
         let rent_epoch = account.map(|a| a.rent_epoch).unwrap_or_default();
+        let write_version = account.map(|a| a.write_version).unwrap_or_default();
+        let txn_signature = account
+            .and_then(|a| a.txn_signature.as_ref())
+            .map(|sig| bs58::encode(sig).into_string());
 
         Self {
             account: pubkey,
@@ -523,10 +856,22 @@ impl GrpcAccountUpdate {
             lamports,
             executable,
             rent_epoch,
+            write_version,
+            source,
+            txn_signature,
+            commitment,
         }
     }
 }
 
+// Geyser write versions count the writes of one validator, so every endpoint URL is its
+// own source.
+pub fn stream_source(endpoint: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    endpoint.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[derive(Clone)]
 pub struct GrpcTransactionUpdate {
     pub signature: String,
@@ -534,10 +879,11 @@ pub struct GrpcTransactionUpdate {
     pub is_vote: bool,
     pub transaction: Option<Transaction>,
     pub meta: Option<TransactionStatusMeta>,
+    pub commitment: Commitment,
 }
 
 impl GrpcTransactionUpdate {
-    fn from_grpc(update: SubscribeUpdateTransaction) -> Self {
+    fn from_grpc(update: SubscribeUpdateTransaction, commitment: Commitment) -> Self {
         let tx = update.transaction.as_ref();
         let signature = tx
             .map(|t| bs58::encode(&t.signature).into_string())
@@ -552,6 +898,51 @@ impl GrpcTransactionUpdate {
             is_vote: tx.map(|t| t.is_vote).unwrap_or(false),
             transaction,
             meta,
+            commitment,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GrpcSlotUpdate {
+    pub slot: u64,
+    pub parent: Option<u64>,
+    pub status: SlotStatus,
+}
+
+impl GrpcSlotUpdate {
+    fn from_grpc(update: SubscribeUpdateSlot) -> Self {
+        Self {
+            slot: update.slot,
+            parent: update.parent,
+            status: SlotStatus::try_from(update.status).unwrap_or(SlotStatus::SlotProcessed),
         }
     }
 }
+
+#[derive(Clone)]
+pub struct GrpcBlockMetaUpdate {
+    pub slot: u64,
+    pub parent_slot: u64,
+    pub blockhash: Hash,
+    pub block_height: u64,
+}
+
+impl GrpcBlockMetaUpdate {
+    fn from_grpc(update: SubscribeUpdateBlockMeta) -> Option<Self> {
+        Some(Self {
+            slot: update.slot,
+            parent_slot: update.parent_slot,
+            blockhash: Hash::from_str(&update.blockhash).ok()?,
+            block_height: update.block_height?.block_height,
+        })
+    }
+}
+
+fn grpc_commitment(commitment: Commitment) -> CommitmentLevel {
+    match commitment {
+        Commitment::Processed => CommitmentLevel::Processed,
+        Commitment::Confirmed => CommitmentLevel::Confirmed,
+        Commitment::Finalized => CommitmentLevel::Finalized,
+    }
+}
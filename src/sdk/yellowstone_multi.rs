@@ -0,0 +1,521 @@
+use crate::convention::chain::stamp::Commitment;
+use crate::sdk::yellowstone::{
+    AccountFilter, GapHandler, GrpcAccountUpdate, GrpcBlockMetaUpdate, GrpcSlotUpdate,
+    GrpcTransactionUpdate, SolanaGrpcClient, SubscriptionCursor, TransactionFilter,
+};
+use crate::util::env::env_config::ENV_CONFIG;
+use crate::util::metrics_registry::METRICS_REGISTRY;
+use crate::util::structs::abort_on_drop::AbortOnDrop;
+use crate::util::structs::stream_dedup::StreamDeduplicator;
+use anyhow::Result;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use solana_sdk::pubkey::Pubkey;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+const DEDUP_TTL: Duration = Duration::from_secs(60);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// ~20 seconds behind the fastest endpoint
+const MAX_LAG_SLOTS: u64 = 50;
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(200);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Hash, Eq, PartialEq)]
+pub enum StreamKey {
+    // Keyed by the Geyser write version, which endpoints fed by the same validator
+    // lineage report alike. A repeated state is a new write with its own version.
+    Account(Pubkey, u64, u64),
+    Transaction(String),
+    Slot(u64, i32),
+    BlockMeta(u64),
+}
+
+pub trait StreamUpdate: Send + 'static {
+    fn stream_key(&self) -> StreamKey;
+    fn slot(&self) -> u64;
+}
+
+impl StreamUpdate for GrpcAccountUpdate {
+    fn stream_key(&self) -> StreamKey {
+        StreamKey::Account(self.account, self.slot, self.write_version)
+    }
+
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+}
+
+impl StreamUpdate for GrpcSlotUpdate {
+    fn stream_key(&self) -> StreamKey {
+        StreamKey::Slot(self.slot, self.status as i32)
+    }
+
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+}
+
+impl StreamUpdate for GrpcBlockMetaUpdate {
+    fn stream_key(&self) -> StreamKey {
+        StreamKey::BlockMeta(self.slot)
+    }
+
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+}
+
+impl StreamUpdate for GrpcTransactionUpdate {
+    fn stream_key(&self) -> StreamKey {
+        StreamKey::Transaction(self.signature.clone())
+    }
+
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+}
+
+type Forward<U> = Arc<dyn Fn(U) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+struct EndpointHealth {
+    url: String,
+    // URLs often carry an API key, so metrics only ever see the configured position.
+    label: String,
+    // survives resubscribes so every endpoint replays from where it stopped
+    cursor: Arc<SubscriptionCursor>,
+    received: AtomicU64,
+    forwarded: AtomicU64,
+    errors: AtomicU64,
+    highest_slot: AtomicU64,
+    last_message_ms: AtomicU64,
+}
+
+impl EndpointHealth {
+    fn new(index: usize, url: String) -> Self {
+        Self {
+            url,
+            label: index.to_string(),
+            cursor: Arc::new(SubscriptionCursor::default()),
+            received: AtomicU64::new(0),
+            forwarded: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            highest_slot: AtomicU64::new(0),
+            last_message_ms: AtomicU64::new(0),
+        }
+    }
+
+    // A new subscription starts over, so its lag is measured from its own first update
+    // and it is not flagged again before it had a chance to deliver one.
+    fn resubscribed(&self, now_ms: u64) {
+        self.highest_slot.store(0, Ordering::Relaxed);
+        self.last_message_ms.store(now_ms, Ordering::Relaxed);
+    }
+}
+
+struct FanIn {
+    name: String,
+    started: Instant,
+    dedup: StreamDeduplicator<StreamKey>,
+    endpoints: Vec<EndpointHealth>,
+    gap_handler: Option<GapHandler>,
+    commitment: Commitment,
+}
+
+impl FanIn {
+    fn accept<U: StreamUpdate>(&self, endpoint: usize, update: &U) -> bool {
+        let health = &self.endpoints[endpoint];
+        health.received.fetch_add(1, Ordering::Relaxed);
+        health
+            .highest_slot
+            .fetch_max(update.slot(), Ordering::Relaxed);
+        health
+            .last_message_ms
+            .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+
+        let first = self.dedup.first_seen(update.stream_key());
+        if first {
+            health.forwarded.fetch_add(1, Ordering::Relaxed);
+        }
+        first
+    }
+
+    fn highest_slot(&self) -> u64 {
+        self.endpoints
+            .iter()
+            .map(|e| e.highest_slot.load(Ordering::Relaxed))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn unhealthy_reason(&self, endpoint: usize) -> Option<String> {
+        let health = &self.endpoints[endpoint];
+        let slot = health.highest_slot.load(Ordering::Relaxed);
+        let lag = self.highest_slot().saturating_sub(slot);
+        // nothing received since the last resubscribe, only a stall can tell
+        if slot > 0 && lag > MAX_LAG_SLOTS {
+            return Some(format!("{} slots behind", lag));
+        }
+
+        let idle = self.idle(endpoint);
+        if idle > STALL_TIMEOUT && self.others_alive(endpoint) {
+            return Some(format!("silent for {:?}", idle));
+        }
+        None
+    }
+
+    fn idle(&self, endpoint: usize) -> Duration {
+        let now_ms = self.started.elapsed().as_millis() as u64;
+        let last_ms = self.endpoints[endpoint]
+            .last_message_ms
+            .load(Ordering::Relaxed);
+        Duration::from_millis(now_ms.saturating_sub(last_ms))
+    }
+
+    fn others_alive(&self, endpoint: usize) -> bool {
+        (0..self.endpoints.len()).any(|i| i != endpoint && self.idle(i) < STALL_TIMEOUT)
+    }
+
+    // While another endpoint kept streaming nothing was missed, so skip the refetch.
+    fn gap_handler_for(self: &Arc<Self>, endpoint: usize) -> Option<GapHandler> {
+        let handler = self.gap_handler.clone()?;
+        let fan_in = self.clone();
+        Some(Arc::new(move || {
+            if fan_in.others_alive(endpoint) {
+                async {}.boxed()
+            } else {
+                handler()
+            }
+        }))
+    }
+
+    // Logs the counts since the previous report and adds them to the exported totals.
+    fn report_health(&self) {
+        let highest = self.highest_slot();
+        let summary: Vec<String> = self
+            .endpoints
+            .iter()
+            .map(|e| {
+                let received = e.received.swap(0, Ordering::Relaxed);
+                let forwarded = e.forwarded.swap(0, Ordering::Relaxed);
+                let errors = e.errors.load(Ordering::Relaxed);
+                let lag = highest.saturating_sub(e.highest_slot.load(Ordering::Relaxed));
+
+                let labels = [
+                    ("stream", self.name.as_str()),
+                    ("endpoint", e.label.as_str()),
+                ];
+                METRICS_REGISTRY.add_counter("grpc_updates_received_total", &labels, received);
+                METRICS_REGISTRY.add_counter("grpc_updates_forwarded_total", &labels, forwarded);
+                METRICS_REGISTRY.set_counter("grpc_resubscribes_total", &labels, errors);
+                METRICS_REGISTRY.set_gauge("grpc_endpoint_lag_slots", &labels, lag as f64);
+
+                format!(
+                    "{}: received={} first={} errors={} lag={}",
+                    e.url, received, forwarded, errors, lag,
+                )
+            })
+            .collect();
+        info!("[{}] gRPC endpoints | {}", self.name, summary.join(" | "));
+    }
+}
+
+// Keeps the same subscription open on every configured Geyser endpoint and forwards
+// each update once, from whichever endpoint delivered it first. A lagging or silent
+// endpoint is resubscribed while the others keep the stream going.
+pub struct MultiGrpcClient {
+    endpoints: Vec<(String, String)>,
+    gap_handler: Option<GapHandler>,
+    commitment: Commitment,
+}
+
+impl MultiGrpcClient {
+    pub fn new(endpoints: Vec<(String, String)>) -> Self {
+        Self {
+            endpoints,
+            gap_handler: None,
+            commitment: Commitment::Processed,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(ENV_CONFIG.grpc_endpoints())
+    }
+
+    pub fn with_gap_handler(mut self, handler: GapHandler) -> Self {
+        self.gap_handler = Some(handler);
+        self
+    }
+
+    pub fn with_commitment(mut self, commitment: Commitment) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    pub async fn subscribe_accounts<F, Fut>(
+        self,
+        filters: Vec<AccountFilter>,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: Fn(GrpcAccountUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let name = AccountFilter::names(&filters);
+        self.supervise(
+            name,
+            move |client: SolanaGrpcClient, forward: Forward<GrpcAccountUpdate>| {
+                client.subscribe_accounts(filters.clone(), move |u| forward(u), false)
+            },
+            callback,
+        )
+        .await
+    }
+
+    pub async fn subscribe_transactions<F, Fut>(
+        self,
+        filter: TransactionFilter,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: Fn(GrpcTransactionUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let name = filter.name.clone();
+        self.supervise(
+            name,
+            move |client: SolanaGrpcClient, forward: Forward<GrpcTransactionUpdate>| {
+                client.subscribe_transactions(filter.clone(), move |u| forward(u), false)
+            },
+            callback,
+        )
+        .await
+    }
+
+    pub async fn subscribe_slots<F, Fut>(self, callback: F) -> Result<()>
+    where
+        F: Fn(GrpcSlotUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.supervise(
+            "slots".to_string(),
+            move |client: SolanaGrpcClient, forward: Forward<GrpcSlotUpdate>| {
+                client.subscribe_slots(move |u| forward(u), false)
+            },
+            callback,
+        )
+        .await
+    }
+
+    pub async fn subscribe_blocks_meta<F, Fut>(self, callback: F) -> Result<()>
+    where
+        F: Fn(GrpcBlockMetaUpdate) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.supervise(
+            "blocks_meta".to_string(),
+            move |client: SolanaGrpcClient, forward: Forward<GrpcBlockMetaUpdate>| {
+                client.subscribe_blocks_meta(move |u| forward(u), false)
+            },
+            callback,
+        )
+        .await
+    }
+
+    async fn supervise<U, S, SFut, F, Fut>(
+        self,
+        name: String,
+        subscribe: S,
+        callback: F,
+    ) -> Result<()>
+    where
+        U: StreamUpdate,
+        S: Fn(SolanaGrpcClient, Forward<U>) -> SFut + Send + Sync + Clone + 'static,
+        SFut: Future<Output = Result<()>> + Send + 'static,
+        F: Fn(U) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let fan_in = Arc::new(FanIn {
+            name,
+            started: Instant::now(),
+            dedup: StreamDeduplicator::new(DEDUP_TTL),
+            endpoints: self
+                .endpoints
+                .iter()
+                .enumerate()
+                .map(|(index, (url, _))| EndpointHealth::new(index, url.clone()))
+                .collect(),
+            gap_handler: self.gap_handler.clone(),
+            commitment: self.commitment,
+        });
+
+        // Dropped with this future, so aborting the subscription stops every endpoint.
+        let spawn_endpoint = |idx: usize| -> AbortOnDrop<()> {
+            let (url, token) = self.endpoints[idx].clone();
+            let fan_in = fan_in.clone();
+            let subscribe = subscribe.clone();
+            let callback = callback.clone();
+            AbortOnDrop::new(tokio::spawn(run_endpoint(
+                idx, url, token, fan_in, subscribe, callback,
+            )))
+        };
+
+        let mut handles: Vec<AbortOnDrop<()>> =
+            (0..self.endpoints.len()).map(&spawn_endpoint).collect();
+        info!(
+            "[{}] Streaming from {} gRPC endpoints",
+            fan_in.name,
+            handles.len()
+        );
+
+        let mut ticker = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            fan_in.report_health();
+            if handles.len() < 2 {
+                continue;
+            }
+            for (idx, handle) in handles.iter_mut().enumerate() {
+                if let Some(reason) = fan_in.unhealthy_reason(idx) {
+                    warn!(
+                        "[{}] Resubscribing unhealthy gRPC endpoint {}: {}",
+                        fan_in.name, fan_in.endpoints[idx].url, reason
+                    );
+                    fan_in.endpoints[idx].errors.fetch_add(1, Ordering::Relaxed);
+                    fan_in.endpoints[idx]
+                        .resubscribed(fan_in.started.elapsed().as_millis() as u64);
+                    // replacing the handle aborts the old subscription
+                    *handle = spawn_endpoint(idx);
+                }
+            }
+        }
+    }
+}
+
+async fn run_endpoint<U, S, SFut, F, Fut>(
+    idx: usize,
+    url: String,
+    token: String,
+    fan_in: Arc<FanIn>,
+    subscribe: S,
+    callback: F,
+) where
+    U: StreamUpdate,
+    S: Fn(SolanaGrpcClient, Forward<U>) -> SFut,
+    SFut: Future<Output = Result<()>>,
+    F: Fn(U) -> Fut + Send + Sync + Clone + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let forward: Forward<U> = {
+        let fan_in = fan_in.clone();
+        Arc::new(move |update: U| {
+            if fan_in.accept(idx, &update) {
+                callback(update).boxed()
+            } else {
+                async { Ok(()) }.boxed()
+            }
+        })
+    };
+
+    let mut delay = MIN_RETRY_DELAY;
+    loop {
+        let started = Instant::now();
+        let mut client = SolanaGrpcClient::new(url.clone(), token.clone())
+            .with_cursor(fan_in.endpoints[idx].cursor.clone())
+            .with_commitment(fan_in.commitment);
+        if let Some(handler) = fan_in.gap_handler_for(idx) {
+            client = client.with_gap_handler(handler);
+        }
+        match subscribe(client, forward.clone()).await {
+            Ok(_) => warn!("[{}] Subscription to {} ended", fan_in.name, url),
+            Err(e) => error!("[{}] Subscription to {} failed: {}", fan_in.name, url, e),
+        }
+        fan_in.endpoints[idx].errors.fetch_add(1, Ordering::Relaxed);
+        // the replacement subscription is measured from its own first update
+        fan_in.endpoints[idx].highest_slot.store(0, Ordering::Relaxed);
+
+        // other endpoints keep the stream alive, so a short backoff is enough
+        if started.elapsed() > MAX_RETRY_DELAY * 6 {
+            delay = MIN_RETRY_DELAY;
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fan_in(endpoints: usize) -> FanIn {
+        FanIn {
+            name: "test".to_string(),
+            started: Instant::now(),
+            dedup: StreamDeduplicator::new(DEDUP_TTL),
+            endpoints: (0..endpoints)
+                .map(|i| EndpointHealth::new(i, format!("endpoint-{}", i)))
+                .collect(),
+            gap_handler: None,
+            commitment: Commitment::Processed,
+        }
+    }
+
+    fn account_update(
+        account: Pubkey,
+        slot: u64,
+        data: Vec<u8>,
+        write_version: u64,
+    ) -> GrpcAccountUpdate {
+        GrpcAccountUpdate {
+            account,
+            slot,
+            data,
+            owner: Pubkey::default(),
+            lamports: 0,
+            executable: false,
+            rent_epoch: 0,
+            write_version,
+            source: 0,
+            txn_signature: None,
+            commitment: Commitment::Processed,
+        }
+    }
+
+    #[test]
+    fn test_forwards_first_copy_only() {
+        let fan_in = fan_in(2);
+        let account = Pubkey::new_unique();
+
+        assert!(fan_in.accept(1, &account_update(account, 10, vec![1], 7)));
+        assert!(!fan_in.accept(0, &account_update(account, 10, vec![1], 7)));
+        // writing the same state again in the slot is still a new update
+        assert!(fan_in.accept(0, &account_update(account, 10, vec![1], 8)));
+
+        assert_eq!(fan_in.endpoints[0].forwarded.load(Ordering::Relaxed), 1);
+        assert_eq!(fan_in.endpoints[1].forwarded.load(Ordering::Relaxed), 1);
+        assert_eq!(fan_in.endpoints[0].received.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_lagging_endpoint_is_unhealthy() {
+        let fan_in = fan_in(2);
+        let account = Pubkey::new_unique();
+
+        fan_in.accept(0, &account_update(account, 1_000, vec![], 1));
+        fan_in.accept(1, &account_update(account, 1_000 - MAX_LAG_SLOTS - 1, vec![], 1));
+
+        assert!(fan_in.unhealthy_reason(0).is_none());
+        assert!(fan_in.unhealthy_reason(1).is_some());
+
+        // the new subscription is judged on what it delivers, not on the old stream
+        fan_in.endpoints[1].resubscribed(fan_in.started.elapsed().as_millis() as u64);
+        assert!(fan_in.unhealthy_reason(1).is_none());
+        fan_in.accept(1, &account_update(account, 1_000, vec![1], 1));
+        assert!(fan_in.unhealthy_reason(1).is_none());
+    }
+}
@@ -1,16 +1,25 @@
+use crate::util::metrics_registry::{MetricsRegistry, METRICS_REGISTRY};
 use moka::future::{Cache, CacheBuilder};
 use std::future::Future;
 use std::hash::Hash;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 type LoaderFn<K, V> =
     Arc<dyn Fn(&K) -> Pin<Box<dyn Future<Output = Option<V>> + Send>> + Send + Sync>;
 
+#[derive(Default)]
+struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
 pub struct LoadingCache<K, V> {
     cache: Cache<K, Arc<V>>,
     loader: LoaderFn<K, V>,
+    stats: Arc<CacheStats>,
 }
 
 impl<K, V> LoadingCache<K, V>
@@ -31,7 +40,11 @@ where
             },
         );
 
-        Self { cache, loader }
+        Self {
+            cache,
+            loader,
+            stats: Arc::default(),
+        }
     }
 
     pub fn with_ttl<F, Fut>(max_capacity: u64, ttl: Duration, loader: F) -> Self
@@ -49,7 +62,11 @@ where
             },
         );
 
-        Self { cache, loader }
+        Self {
+            cache,
+            loader,
+            stats: Arc::default(),
+        }
     }
 
     pub fn with_ttl_and_tti<F, Fut>(
@@ -73,27 +90,78 @@ where
             },
         );
 
-        Self { cache, loader }
+        Self {
+            cache,
+            loader,
+            stats: Arc::default(),
+        }
+    }
+
+    // Registers entry count and hit rate with the metrics registry under `name`.
+    pub fn named(self, name: &str) -> Self {
+        self.named_in(name, &METRICS_REGISTRY)
+    }
+
+    pub fn named_in(self, name: &str, registry: &'static MetricsRegistry) -> Self {
+        let cache = self.cache.clone();
+        let stats = self.stats.clone();
+        let name = name.to_string();
+        registry.register_collector(move || {
+            let hits = stats.hits.load(Ordering::Relaxed);
+            let misses = stats.misses.load(Ordering::Relaxed);
+            let labels = [("cache", name.as_str())];
+            registry.set_gauge("cache_entries", &labels, cache.entry_count() as f64);
+            registry.set_counter(
+                "cache_requests_total",
+                &[("cache", name.as_str()), ("result", "hit")],
+                hits,
+            );
+            registry.set_counter(
+                "cache_requests_total",
+                &[("cache", name.as_str()), ("result", "miss")],
+                misses,
+            );
+            if hits + misses > 0 {
+                let ratio = hits as f64 / (hits + misses) as f64;
+                registry.set_gauge("cache_hit_ratio", &labels, ratio);
+            }
+            true
+        });
+        self
     }
 
     pub async fn get(&self, key: &K) -> Option<V> {
         let loader = self.loader.clone();
         let key_clone = key.clone();
-        
-        self.cache
-            .try_get_with(key.clone(), async move {
+
+        let entry = self
+            .cache
+            .entry(key.clone())
+            .or_try_insert_with(async move {
                 match (loader)(&key_clone).await {
                     Some(value) => Ok(Arc::new(value)),
                     None => Err(()),
                 }
             })
             .await
-            .ok()
-            .map(|arc| (*arc).clone())
+            .ok();
+        self.record(entry.as_ref().is_some_and(|entry| !entry.is_fresh()));
+        entry.map(|entry| (*entry.into_value()).clone())
     }
 
     pub async fn get_if_present(&self, key: &K) -> Option<V> {
-        self.cache.get(key).await.map(|arc| (*arc).clone())
+        let value = self.cache.get(key).await;
+        self.record(value.is_some());
+        value.map(|arc| (*arc).clone())
+    }
+
+    fn record(&self, hit: bool) {
+        let counter = if hit {
+            &self.stats.hits
+        } else {
+            &self.stats.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
     }
 
     pub async fn put(&self, key: K, value: V) {
@@ -124,6 +192,14 @@ where
         self.cache.contains_key(key)
     }
 
+    // Copy of every live entry, without touching hit stats or loading anything.
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.cache
+            .iter()
+            .map(|(key, value)| ((*key).clone(), (*value).clone()))
+            .collect()
+    }
+
     pub async fn get_multiple<I>(&self, keys: I) -> Vec<(K, V)>
     where
         I: IntoIterator<Item = K>,
@@ -196,6 +272,36 @@ mod tests {
         assert_eq!(load_count.load(Ordering::SeqCst), 1);
     }
 
+    #[tokio::test]
+    async fn test_hit_rate() {
+        let registry: &'static MetricsRegistry = Box::leak(Box::default());
+        let cache = LoadingCache::new(10, |key: &u32| {
+            let key = *key;
+            async move { (key != 0).then(|| format!("value_{}", key)) }
+        })
+        .named_in("TestHitRateCache", registry);
+
+        cache.get(&1).await;
+        cache.get(&1).await;
+        cache.get(&0).await;
+        cache.get_if_present(&1).await;
+
+        registry.collect();
+        let counters = registry.counters();
+        let requests = |result: &str| {
+            counters
+                .iter()
+                .find(|(key, _)| {
+                    key.name == "cache_requests_total"
+                        && key.labels
+                            == format!(r#"{{cache="TestHitRateCache",result="{}"}}"#, result)
+                })
+                .map(|(_, value)| *value)
+        };
+        assert_eq!(requests("hit"), Some(2));
+        assert_eq!(requests("miss"), Some(2));
+    }
+
     #[tokio::test]
     async fn test_capacity_eviction() {
         let cache = LoadingCache::new(3, |key: &u32| {
@@ -109,6 +109,11 @@ where
         }
     }
 
+    pub fn named(mut self, name: &str) -> Self {
+        self.loading_cache = self.loading_cache.named(name);
+        self
+    }
+
     pub fn new<F, Fut>(
         cache_type: CacheType,
         max_capacity: u64,
@@ -1,3 +1,4 @@
+use crate::util::metrics_registry::METRICS_REGISTRY;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
@@ -155,6 +156,44 @@ impl<K: MetricKey> PeriodicLoggerBuilder<K> {
     }
 }
 
+// Prints one line per processor in `METRICS_REGISTRY`, skipping the ones that never
+// received a message. Unlike the counters above, registry totals are never reset.
+pub fn spawn_registry_logger(every: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(every);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            ticker.tick().await;
+            for snapshot in METRICS_REGISTRY.processors() {
+                if snapshot.enqueued == 0 {
+                    continue;
+                }
+                let drops = snapshot
+                    .drops
+                    .iter()
+                    .map(|(reason, count)| format!("{}: {}", reason, count))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                info!(
+                    "[{}] enqueued: {}, dequeued: {}, occupancy: {}/{}, p50: {:.2}ms, p99: {:.2}ms, errors: {}, blocked: {} ({}ms), drops: [{}]",
+                    snapshot.name,
+                    snapshot.enqueued,
+                    snapshot.dequeued,
+                    snapshot.occupancy,
+                    snapshot.capacity,
+                    snapshot.p50_ms,
+                    snapshot.p99_ms,
+                    snapshot.errors,
+                    snapshot.blocked,
+                    snapshot.blocked_ms,
+                    drops
+                );
+            }
+        }
+    })
+}
+
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub enum TransactionMetric {
     Received,
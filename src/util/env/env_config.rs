@@ -1,6 +1,9 @@
+use crate::convention::chain::stamp::Commitment;
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 
+const DEFAULT_RPC_RATE_LIMIT: u32 = 50;
+
 pub static ENV_CONFIG: Lazy<EnvironmentStruct> = Lazy::new(|| {
     EnvironmentStruct::load_from_env().expect("Failed to load environment configuration")
 });
@@ -10,22 +13,175 @@ pub struct EnvironmentStruct {
     pub database_url: String,
     pub grpc_url: String,
     pub grpc_token: String,
+    // Additional Geyser endpoints streamed in parallel with `grpc_url`; a missing
+    // token falls back to `grpc_token`.
+    pub grpc_extra_urls: Vec<String>,
+    pub grpc_extra_tokens: Vec<String>,
     pub solana_rpc_url: String,
+    // WebSocket endpoint for the PubSub fallback, derived from `solana_rpc_url` when unset.
+    pub solana_ws_url: String,
+    // Additional RPC endpoints. Roles are "+"-joined (read, simulate, send) and default
+    // to all of them; rate limits are per endpoint in requests per second, primary first.
+    pub rpc_extra_urls: Vec<String>,
+    pub rpc_extra_roles: Vec<String>,
+    pub rpc_rate_limits: Vec<u32>,
+    // Commitments default to what the streams and the RPC client always used: confirmed
+    // accounts, processed transactions and RPC reads.
+    pub grpc_account_commitment: Commitment,
+    pub grpc_transaction_commitment: Commitment,
+    pub rpc_commitment: Commitment,
+    // Quoting refuses reserves whose last write is this many slots older than the
+    // pool state they are combined with.
+    pub max_quote_slot_distance: u64,
     pub wallet_file_path: String,
     pub enable_send_tx: bool,
+    // Durable nonce mode is enabled when at least one nonce account is configured.
+    pub nonce_accounts: Vec<String>,
+    // Where the Prometheus `/metrics` endpoint listens, local only unless set otherwise.
+    pub metrics_addr: String,
+    // Share of traces that never fired a transaction kept in `pipeline_trace`, 0 to 100.
+    pub trace_sample_percent: f64,
+    // Rows of `pipeline_trace` older than this are deleted.
+    pub trace_retention_days: i64,
+    // OTLP/HTTP collector base URL, e.g. `http://localhost:4318`. Span export is off without it.
+    pub otlp_endpoint: Option<String>,
+    // Stage deadlines in ms from the slot start, e.g. `send=800,send.Jito=600,simulation.PumpAmm=500`.
+    // Updates delivered at Confirmed or later count from their arrival instead.
+    pub deadline_budgets_ms: Vec<String>,
+    // Where cached chain state is snapshotted for a warm restart, empty turns it off.
+    pub state_snapshot_path: String,
+    pub state_snapshot_interval_secs: u64,
+    // Restored entries written more than this many slots before startup are dropped.
+    pub state_snapshot_max_slot_distance: u64,
 }
 
 impl EnvironmentStruct {
     fn load_from_env() -> anyhow::Result<Self> {
         dotenv::dotenv().ok();
 
+        let solana_rpc_url = std::env::var("SOLANA_RPC_URL")?;
         Ok(Self {
             database_url: std::env::var("DATABASE_URL")?,
             grpc_url: std::env::var("GRPC_URL")?,
             grpc_token: std::env::var("GRPC_TOKEN")?,
-            solana_rpc_url: std::env::var("SOLANA_RPC_URL")?,
+            grpc_extra_urls: list_var("GRPC_EXTRA_URLS"),
+            grpc_extra_tokens: list_var("GRPC_EXTRA_TOKENS"),
+            solana_ws_url: std::env::var("SOLANA_WS_URL")
+                .unwrap_or_else(|_| ws_url_from(&solana_rpc_url)),
+            solana_rpc_url,
+            rpc_extra_urls: list_var("RPC_EXTRA_URLS"),
+            rpc_extra_roles: list_var("RPC_EXTRA_ROLES"),
+            rpc_rate_limits: list_var("RPC_RATE_LIMITS")
+                .iter()
+                .map(|value| value.parse())
+                .collect::<Result<_, _>>()?,
+            grpc_account_commitment: commitment_var(
+                "GRPC_ACCOUNT_COMMITMENT",
+                Commitment::Confirmed,
+            )?,
+            grpc_transaction_commitment: commitment_var(
+                "GRPC_TRANSACTION_COMMITMENT",
+                Commitment::Processed,
+            )?,
+            rpc_commitment: commitment_var("RPC_COMMITMENT", Commitment::Processed)?,
+            max_quote_slot_distance: std::env::var("MAX_QUOTE_SLOT_DISTANCE")
+                .ok()
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or(4),
             wallet_file_path: std::env::var("WALLET_FILE_PATH")?,
             enable_send_tx: std::env::var("ENABLE_SEND_TX")?.to_lowercase() == "true",
+            nonce_accounts: list_var("NONCE_ACCOUNTS"),
+            metrics_addr: std::env::var("METRICS_ADDR")
+                .unwrap_or_else(|_| "127.0.0.1:9464".to_string()),
+            trace_sample_percent: std::env::var("TRACE_SAMPLE_PERCENT")
+                .ok()
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or(1.0),
+            trace_retention_days: std::env::var("TRACE_RETENTION_DAYS")
+                .ok()
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or(7),
+            otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok(),
+            deadline_budgets_ms: list_var("DEADLINE_BUDGETS_MS"),
+            state_snapshot_path: std::env::var("STATE_SNAPSHOT_PATH")
+                .unwrap_or_else(|_| "state_snapshot.bin".to_string()),
+            state_snapshot_interval_secs: std::env::var("STATE_SNAPSHOT_INTERVAL_SECS")
+                .ok()
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or(60),
+            state_snapshot_max_slot_distance: std::env::var("STATE_SNAPSHOT_MAX_SLOT_DISTANCE")
+                .ok()
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or(1_500),
+        })
+    }
+
+    pub fn grpc_endpoints(&self) -> Vec<(String, String)> {
+        let extras = self.grpc_extra_urls.iter().enumerate().map(|(i, url)| {
+            let token = self
+                .grpc_extra_tokens
+                .get(i)
+                .unwrap_or(&self.grpc_token)
+                .clone();
+            (url.clone(), token)
+        });
+        std::iter::once((self.grpc_url.clone(), self.grpc_token.clone()))
+            .chain(extras)
+            .collect()
+    }
+
+    // (url, roles, requests per second) with the primary endpoint serving every role.
+    pub fn rpc_endpoints(&self) -> Vec<(String, String, u32)> {
+        let extras = self.rpc_extra_urls.iter().enumerate().map(|(i, url)| {
+            let roles = self.rpc_extra_roles.get(i).cloned().unwrap_or_default();
+            (url.clone(), roles)
+        });
+        std::iter::once((self.solana_rpc_url.clone(), String::new()))
+            .chain(extras)
+            .enumerate()
+            .map(|(i, (url, roles))| {
+                let limit = self
+                    .rpc_rate_limits
+                    .get(i)
+                    .copied()
+                    .unwrap_or(DEFAULT_RPC_RATE_LIMIT);
+                (url, roles, limit)
+            })
+            .collect()
+    }
+}
+
+fn ws_url_from(rpc_url: &str) -> String {
+    if let Some(rest) = rpc_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        rpc_url.to_string()
+    }
+}
+
+fn list_var(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
         })
+        .unwrap_or_default()
+}
+
+fn commitment_var(name: &str, default: Commitment) -> anyhow::Result<Commitment> {
+    match std::env::var(name) {
+        Ok(value) => Commitment::parse(&value)
+            .ok_or_else(|| anyhow::anyhow!("{} has unknown commitment {}", name, value)),
+        Err(_) => Ok(default),
     }
 }
@@ -0,0 +1,376 @@
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Percentiles are taken over this many most recent handler runs.
+const LATENCY_WINDOW: usize = 2048;
+// Upper bounds in seconds, shared by every histogram.
+pub const HISTOGRAM_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+pub type Labels<'a> = &'a [(&'static str, &'a str)];
+
+// A metric name plus its rendered label set, e.g. `{channel="Jito",outcome="sent"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SeriesKey {
+    pub name: &'static str,
+    pub labels: String,
+}
+
+impl SeriesKey {
+    fn new(name: &'static str, labels: Labels) -> Self {
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            let pairs: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+                .collect();
+            format!("{{{}}}", pairs.join(","))
+        };
+        Self { name, labels }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[derive(Default)]
+pub struct Histogram {
+    // not cumulative, the last slot counts observations above every bucket
+    buckets: [AtomicU64; HISTOGRAM_BUCKETS.len() + 1],
+    sum_us: AtomicU64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HistogramSnapshot {
+    // cumulative counts per entry of `HISTOGRAM_BUCKETS`
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub sum_seconds: f64,
+}
+
+impl Histogram {
+    pub fn observe(&self, value: Duration) {
+        let seconds = value.as_secs_f64();
+        let idx = HISTOGRAM_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(HISTOGRAM_BUCKETS.len());
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.sum_us
+            .fetch_add(value.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let mut buckets = Vec::with_capacity(HISTOGRAM_BUCKETS.len());
+        for bucket in &self.buckets[..HISTOGRAM_BUCKETS.len()] {
+            cumulative += bucket.load(Ordering::Relaxed);
+            buckets.push(cumulative);
+        }
+        HistogramSnapshot {
+            buckets,
+            count: cumulative + self.buckets[HISTOGRAM_BUCKETS.len()].load(Ordering::Relaxed),
+            sum_seconds: self.sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        }
+    }
+}
+
+// Counters of one message processor. Totals only grow; `occupancy` is what has been
+// enqueued but not yet taken off the queue, `in_flight` what handlers are working on.
+pub struct ProcessorMetrics {
+    name: String,
+    capacity: usize,
+    enqueued: AtomicU64,
+    dequeued: AtomicU64,
+    in_flight: AtomicU64,
+    errors: AtomicU64,
+    // Publishes that found the buffer full and had to wait for room.
+    blocked: AtomicU64,
+    blocked_us: AtomicU64,
+    drops: Mutex<BTreeMap<&'static str, u64>>,
+    latencies_us: Mutex<VecDeque<u64>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessorSnapshot {
+    pub name: String,
+    pub capacity: usize,
+    pub enqueued: u64,
+    pub dequeued: u64,
+    pub occupancy: u64,
+    pub in_flight: u64,
+    pub errors: u64,
+    pub blocked: u64,
+    pub blocked_ms: u64,
+    pub drops: BTreeMap<String, u64>,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl ProcessorMetrics {
+    fn new(name: String, capacity: usize) -> Self {
+        Self {
+            name,
+            capacity,
+            enqueued: AtomicU64::new(0),
+            dequeued: AtomicU64::new(0),
+            in_flight: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            blocked: AtomicU64::new(0),
+            blocked_us: AtomicU64::new(0),
+            drops: Mutex::new(BTreeMap::new()),
+            latencies_us: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+        }
+    }
+
+    // Counted before the message is handed to the queue, so `pending` never misses it.
+    pub fn on_enqueue(&self) {
+        self.enqueued.fetch_add(1, Ordering::SeqCst);
+    }
+
+    // For messages that leave the queue without being processed.
+    pub fn on_dequeue(&self) {
+        self.dequeued.fetch_add(1, Ordering::SeqCst);
+    }
+
+    // Takes the message off the queue and marks it in flight in one step. In flight goes
+    // up first, so `pending` never reads the message as gone in between.
+    pub fn on_started(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.dequeued.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn on_blocked(&self, waited: Duration) {
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        self.blocked_us
+            .fetch_add(waited.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn on_drop(&self, reason: &'static str) {
+        *self.drops.lock().entry(reason).or_insert(0) += 1;
+    }
+
+    pub fn on_handled(&self, elapsed: Duration, failed: bool) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut latencies = self.latencies_us.lock();
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(elapsed.as_micros() as u64);
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Nothing queued and nothing being handled.
+    pub fn is_idle(&self) -> bool {
+        self.pending() == 0
+    }
+
+    pub fn pending(&self) -> u64 {
+        let enqueued = self.enqueued.load(Ordering::SeqCst);
+        let dequeued = self.dequeued.load(Ordering::SeqCst);
+        enqueued.saturating_sub(dequeued) + self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn snapshot(&self) -> ProcessorSnapshot {
+        let mut latencies: Vec<u64> = self.latencies_us.lock().iter().copied().collect();
+        latencies.sort_unstable();
+        let enqueued = self.enqueued.load(Ordering::Relaxed);
+        let dequeued = self.dequeued.load(Ordering::Relaxed);
+
+        ProcessorSnapshot {
+            name: self.name.clone(),
+            capacity: self.capacity,
+            enqueued,
+            dequeued,
+            occupancy: enqueued.saturating_sub(dequeued),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+            blocked_ms: self.blocked_us.load(Ordering::Relaxed) / 1000,
+            drops: self
+                .drops
+                .lock()
+                .iter()
+                .map(|(reason, count)| (reason.to_string(), *count))
+                .collect(),
+            p50_ms: percentile_ms(&latencies, 50),
+            p99_ms: percentile_ms(&latencies, 99),
+        }
+    }
+}
+
+fn percentile_ms(sorted_us: &[u64], p: usize) -> f64 {
+    if sorted_us.is_empty() {
+        return 0.0;
+    }
+    let idx = (sorted_us.len() * p).div_ceil(100).saturating_sub(1);
+    sorted_us[idx.min(sorted_us.len() - 1)] as f64 / 1000.0
+}
+
+type Collector = Box<dyn Fn() -> bool + Send + Sync>;
+
+// Every processor registers here when it is built, so loggers and exporters find all
+// of them without knowing where they live. Other components record labeled series
+// directly, or register a collector that refreshes their gauges right before export.
+#[derive(Default)]
+pub struct MetricsRegistry {
+    processors: RwLock<Vec<Arc<ProcessorMetrics>>>,
+    counters: DashMap<SeriesKey, AtomicU64>,
+    gauges: DashMap<SeriesKey, f64>,
+    histograms: DashMap<SeriesKey, Histogram>,
+    collectors: Mutex<Vec<Collector>>,
+}
+
+impl MetricsRegistry {
+    pub fn register_processor(&self, name: &str, capacity: usize) -> Arc<ProcessorMetrics> {
+        let metrics = Arc::new(ProcessorMetrics::new(name.to_string(), capacity));
+        self.processors.write().push(metrics.clone());
+        metrics
+    }
+
+    pub fn processors(&self) -> Vec<ProcessorSnapshot> {
+        self.processors
+            .read()
+            .iter()
+            .map(|metrics| metrics.snapshot())
+            .collect()
+    }
+
+    pub fn inc_counter(&self, name: &'static str, labels: Labels) {
+        self.add_counter(name, labels, 1);
+    }
+
+    pub fn add_counter(&self, name: &'static str, labels: Labels, value: u64) {
+        let key = SeriesKey::new(name, labels);
+        if let Some(counter) = self.counters.get(&key) {
+            counter.fetch_add(value, Ordering::Relaxed);
+            return;
+        }
+        self.counters
+            .entry(key)
+            .or_default()
+            .fetch_add(value, Ordering::Relaxed);
+    }
+
+    // For totals kept elsewhere and copied over by a collector.
+    pub fn set_counter(&self, name: &'static str, labels: Labels, value: u64) {
+        self.counters
+            .entry(SeriesKey::new(name, labels))
+            .or_default()
+            .store(value, Ordering::Relaxed);
+    }
+
+    pub fn set_gauge(&self, name: &'static str, labels: Labels, value: f64) {
+        self.gauges.insert(SeriesKey::new(name, labels), value);
+    }
+
+    pub fn observe(&self, name: &'static str, labels: Labels, value: Duration) {
+        let key = SeriesKey::new(name, labels);
+        if let Some(histogram) = self.histograms.get(&key) {
+            histogram.observe(value);
+            return;
+        }
+        self.histograms.entry(key).or_default().observe(value);
+    }
+
+    // The collector is dropped once it returns false, e.g. when what it reads is gone.
+    pub fn register_collector(&self, collector: impl Fn() -> bool + Send + Sync + 'static) {
+        self.collectors.lock().push(Box::new(collector));
+    }
+
+    pub fn collect(&self) {
+        self.collectors.lock().retain(|collector| collector());
+    }
+
+    pub fn counters(&self) -> BTreeMap<SeriesKey, u64> {
+        self.counters
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    pub fn gauges(&self) -> BTreeMap<SeriesKey, f64> {
+        self.gauges
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect()
+    }
+
+    pub fn histograms(&self) -> BTreeMap<SeriesKey, HistogramSnapshot> {
+        self.histograms
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().snapshot()))
+            .collect()
+    }
+}
+
+pub static METRICS_REGISTRY: Lazy<MetricsRegistry> = Lazy::new(MetricsRegistry::default);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_processor_snapshot() {
+        let registry = MetricsRegistry::default();
+        let metrics = registry.register_processor("test", 10);
+
+        for ms in 1..=100 {
+            metrics.on_enqueue();
+            metrics.on_started();
+            metrics.on_handled(Duration::from_millis(ms), ms % 10 == 0);
+        }
+        metrics.on_enqueue();
+        metrics.on_drop("full");
+        metrics.on_drop("full");
+
+        let snapshot = &registry.processors()[0];
+        assert_eq!(snapshot.occupancy, 1);
+        assert_eq!(snapshot.errors, 10);
+        assert_eq!(snapshot.drops["full"], 2);
+        assert_eq!(snapshot.p50_ms, 50.0);
+        assert_eq!(snapshot.p99_ms, 99.0);
+    }
+
+    #[test]
+    fn test_labeled_series() {
+        let registry = MetricsRegistry::default();
+        registry.inc_counter("sends_total", &[("channel", "Jito"), ("outcome", "sent")]);
+        registry.add_counter(
+            "sends_total",
+            &[("channel", "Jito"), ("outcome", "sent")],
+            2,
+        );
+        registry.observe("stage_seconds", &[], Duration::from_millis(3));
+        registry.observe("stage_seconds", &[], Duration::from_secs(10));
+
+        let counters = registry.counters();
+        let (key, value) = counters.iter().next().unwrap();
+        assert_eq!(key.labels, r#"{channel="Jito",outcome="sent"}"#);
+        assert_eq!(*value, 3);
+
+        let histogram = &registry.histograms()[&SeriesKey::new("stage_seconds", &[])];
+        assert_eq!(histogram.count, 2);
+        assert_eq!(histogram.buckets[1], 0);
+        assert_eq!(histogram.buckets[2], 1);
+        assert_eq!(*histogram.buckets.last().unwrap(), 1);
+    }
+}
@@ -6,6 +6,8 @@ pub mod env;
 pub mod error_handle;
 pub mod logging;
 pub mod macros;
+pub mod metrics_registry;
+pub mod prometheus;
 pub mod random;
 pub mod serde_helpers;
 pub mod serde_pubkey;
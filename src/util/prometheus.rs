@@ -0,0 +1,136 @@
+use crate::util::metrics_registry::{
+    MetricsRegistry, ProcessorSnapshot, SeriesKey, HISTOGRAM_BUCKETS,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+type ProcessorField = (&'static str, &'static str, fn(&ProcessorSnapshot) -> f64);
+
+const PROCESSOR_FIELDS: [ProcessorField; 9] = [
+    ("processor_enqueued_total", "counter", |p| p.enqueued as f64),
+    ("processor_dequeued_total", "counter", |p| p.dequeued as f64),
+    ("processor_errors_total", "counter", |p| p.errors as f64),
+    ("processor_blocked_total", "counter", |p| p.blocked as f64),
+    ("processor_occupancy", "gauge", |p| p.occupancy as f64),
+    ("processor_in_flight", "gauge", |p| p.in_flight as f64),
+    ("processor_capacity", "gauge", |p| p.capacity as f64),
+    ("processor_latency_p50_seconds", "gauge", |p| {
+        p.p50_ms / 1000.0
+    }),
+    ("processor_latency_p99_seconds", "gauge", |p| {
+        p.p99_ms / 1000.0
+    }),
+];
+
+// Renders the registry in the Prometheus text exposition format. Collectors are run
+// first so pulled gauges are current.
+pub fn render(registry: &MetricsRegistry) -> String {
+    registry.collect();
+    let mut out = String::new();
+
+    let processors = registry.processors();
+    for (name, kind, value) in PROCESSOR_FIELDS {
+        if processors.is_empty() {
+            break;
+        }
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for snapshot in &processors {
+            let _ = writeln!(
+                out,
+                "{}{{processor=\"{}\"}} {}",
+                name,
+                snapshot.name,
+                value(snapshot)
+            );
+        }
+    }
+    if processors.iter().any(|snapshot| !snapshot.drops.is_empty()) {
+        let _ = writeln!(out, "# TYPE processor_dropped_total counter");
+        for snapshot in &processors {
+            for (reason, count) in &snapshot.drops {
+                let _ = writeln!(
+                    out,
+                    "processor_dropped_total{{processor=\"{}\",reason=\"{}\"}} {}",
+                    snapshot.name, reason, count
+                );
+            }
+        }
+    }
+
+    write_family(&mut out, "counter", registry.counters());
+    write_family(&mut out, "gauge", registry.gauges());
+
+    let mut last_name = "";
+    for (key, histogram) in registry.histograms() {
+        if key.name != last_name {
+            let _ = writeln!(out, "# TYPE {} histogram", key.name);
+            last_name = key.name;
+        }
+        for (bound, count) in HISTOGRAM_BUCKETS.iter().zip(&histogram.buckets) {
+            let labels = with_label(&key.labels, "le", &bound.to_string());
+            let _ = writeln!(out, "{}_bucket{} {}", key.name, labels, count);
+        }
+        let labels = with_label(&key.labels, "le", "+Inf");
+        let _ = writeln!(out, "{}_bucket{} {}", key.name, labels, histogram.count);
+        let _ = writeln!(
+            out,
+            "{}_sum{} {}",
+            key.name, key.labels, histogram.sum_seconds
+        );
+        let _ = writeln!(out, "{}_count{} {}", key.name, key.labels, histogram.count);
+    }
+
+    out
+}
+
+fn write_family<V: std::fmt::Display>(
+    out: &mut String,
+    kind: &str,
+    series: BTreeMap<SeriesKey, V>,
+) {
+    let mut last_name = "";
+    for (key, value) in series {
+        if key.name != last_name {
+            let _ = writeln!(out, "# TYPE {} {}", key.name, kind);
+            last_name = key.name;
+        }
+        let _ = writeln!(out, "{}{} {}", key.name, key.labels, value);
+    }
+}
+
+// Appends one label to an already rendered label set.
+fn with_label(labels: &str, key: &str, value: &str) -> String {
+    match labels.strip_suffix('}') {
+        Some(open) => format!("{},{}=\"{}\"}}", open, key, value),
+        None => format!("{{{}=\"{}\"}}", key, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_renders_text_format() {
+        let registry = MetricsRegistry::default();
+        registry.inc_counter("simulations_total", &[("outcome", "success")]);
+        registry.set_gauge("grpc_stream_live", &[], 1.0);
+        registry.observe(
+            "pipeline_stage_seconds",
+            &[("from", "A"), ("to", "B")],
+            Duration::from_millis(20),
+        );
+
+        let text = render(&registry);
+        assert!(text.contains(
+            "# TYPE simulations_total counter\nsimulations_total{outcome=\"success\"} 1\n"
+        ));
+        assert!(text.contains("grpc_stream_live 1\n"));
+        assert!(text.contains("pipeline_stage_seconds_bucket{from=\"A\",to=\"B\",le=\"0.01\"} 0\n"));
+        assert!(
+            text.contains("pipeline_stage_seconds_bucket{from=\"A\",to=\"B\",le=\"0.025\"} 1\n")
+        );
+        assert!(text.contains("pipeline_stage_seconds_count{from=\"A\",to=\"B\"} 1\n"));
+    }
+}
@@ -14,3 +14,21 @@ pub fn random_choose<T>(items: &[T]) -> &T {
         .get(rand::rng().random_range(0..items.len()))
         .expect("random_select_unwrap called with empty slice")
 }
+
+// Picks an item with probability proportional to its weight; non-positive weights are never picked.
+pub fn weighted_select<T>(items: &[T], weight: impl Fn(&T) -> f64) -> Option<&T> {
+    let weights: Vec<f64> = items.iter().map(|item| weight(item).max(0.0)).collect();
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut target = rand::rng().random_range(0.0..total);
+    for (item, weight) in items.iter().zip(weights) {
+        if target < weight {
+            return Some(item);
+        }
+        target -= weight;
+    }
+    items.last()
+}
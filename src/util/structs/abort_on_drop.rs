@@ -0,0 +1,43 @@
+use tokio::task::JoinHandle;
+
+// Aborts the task once dropped. Tasks that spawn tasks of their own hold them this way,
+// so aborting the outer task stops the whole tree instead of detaching the children.
+pub struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> AbortOnDrop<T> {
+    pub fn new(handle: JoinHandle<T>) -> Self {
+        Self(handle)
+    }
+}
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_aborting_the_parent_stops_the_child() {
+        let child_ran = Arc::new(AtomicBool::new(false));
+        let ran = child_ran.clone();
+        let parent = tokio::spawn(async move {
+            let _child = AbortOnDrop::new(tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                ran.store(true, Ordering::Relaxed);
+            }));
+            std::future::pending::<()>().await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        parent.abort();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!child_ran.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+pub struct CooldownPolicy {
+    pub base_failure_cooldown: Duration,
+    pub max_failure_cooldown: Duration,
+    pub success_dedup_window: Duration,
+    pub success_memory: Duration,
+    pub default_dedup_window: Duration,
+}
+
+impl Default for CooldownPolicy {
+    fn default() -> Self {
+        Self {
+            base_failure_cooldown: Duration::seconds(10),
+            max_failure_cooldown: Duration::hours(1),
+            success_dedup_window: Duration::seconds(2),
+            success_memory: Duration::minutes(10),
+            default_dedup_window: Duration::seconds(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CooldownEntry {
+    pub error_class: Option<String>,
+    pub consecutive_failures: u32,
+    pub suppressed_until: DateTime<Utc>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Suppression {
+    Allowed {
+        dedup_window: Duration,
+    },
+    Suppressed {
+        reason: SuppressionReason,
+        until: DateTime<Utc>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuppressionReason {
+    pub error_class: String,
+    pub consecutive_failures: u32,
+}
+
+impl std::fmt::Display for SuppressionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "failed {} times in a row with {}",
+            self.consecutive_failures, self.error_class
+        )
+    }
+}
+
+impl CooldownPolicy {
+    pub fn on_failure(
+        &self,
+        previous: Option<CooldownEntry>,
+        error_class: String,
+        now: DateTime<Utc>,
+    ) -> CooldownEntry {
+        let (consecutive_failures, last_success_at) = match previous {
+            Some(prev) if prev.error_class.as_deref() == Some(error_class.as_str()) => {
+                (prev.consecutive_failures + 1, prev.last_success_at)
+            }
+            Some(prev) => (1, prev.last_success_at),
+            None => (1, None),
+        };
+
+        CooldownEntry {
+            error_class: Some(error_class),
+            consecutive_failures,
+            suppressed_until: now + self.failure_cooldown(consecutive_failures),
+            last_failure_at: Some(now),
+            last_success_at,
+        }
+    }
+
+    pub fn on_success(&self, now: DateTime<Utc>) -> CooldownEntry {
+        CooldownEntry {
+            error_class: None,
+            consecutive_failures: 0,
+            suppressed_until: now,
+            last_failure_at: None,
+            last_success_at: Some(now),
+        }
+    }
+
+    pub fn failure_cooldown(&self, consecutive_failures: u32) -> Duration {
+        let exponent = consecutive_failures.saturating_sub(1).min(20);
+        let cooldown = self.base_failure_cooldown * 2i32.pow(exponent);
+        cooldown.min(self.max_failure_cooldown)
+    }
+
+    pub fn suppression(&self, entry: Option<&CooldownEntry>, now: DateTime<Utc>) -> Suppression {
+        let Some(entry) = entry else {
+            return Suppression::Allowed {
+                dedup_window: self.default_dedup_window,
+            };
+        };
+
+        if let (Some(error_class), true) = (&entry.error_class, entry.suppressed_until > now) {
+            return Suppression::Suppressed {
+                reason: SuppressionReason {
+                    error_class: error_class.clone(),
+                    consecutive_failures: entry.consecutive_failures,
+                },
+                until: entry.suppressed_until,
+            };
+        }
+
+        let dedup_window = match entry.last_success_at {
+            Some(succeeded_at)
+                if entry.consecutive_failures == 0 && now - succeeded_at < self.success_memory =>
+            {
+                self.success_dedup_window
+            }
+            _ => self.default_dedup_window,
+        };
+        Suppression::Allowed { dedup_window }
+    }
+}
+
+// Simulation errors look like `InstructionError(3, Custom(6001))`; the instruction
+// index shifts with the number of compute budget / tip instructions, so drop it.
+pub fn classify_error(err: &str) -> String {
+    match err.strip_prefix("InstructionError(") {
+        Some(rest) => match rest.split_once(", ") {
+            Some((_, detail)) => format!("InstructionError({}", detail),
+            None => err.to_string(),
+        },
+        None => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_error_ignores_instruction_index() {
+        assert_eq!(
+            classify_error("InstructionError(2, Custom(6001))"),
+            classify_error("InstructionError(4, Custom(6001))"),
+        );
+        assert_ne!(
+            classify_error("InstructionError(2, Custom(6001))"),
+            classify_error("InstructionError(2, Custom(6002))"),
+        );
+        assert_eq!(classify_error("AccountNotFound"), "AccountNotFound");
+    }
+
+    #[test]
+    fn test_exponential_backoff_on_same_error() {
+        let policy = CooldownPolicy::default();
+        let now = Utc::now();
+
+        let first = policy.on_failure(None, "E".to_string(), now);
+        assert_eq!(first.consecutive_failures, 1);
+        assert_eq!(first.suppressed_until, now + Duration::seconds(10));
+
+        let second = policy.on_failure(Some(first), "E".to_string(), now);
+        assert_eq!(second.consecutive_failures, 2);
+        assert_eq!(second.suppressed_until, now + Duration::seconds(20));
+
+        let third = policy.on_failure(Some(second), "E".to_string(), now);
+        assert_eq!(third.suppressed_until, now + Duration::seconds(40));
+    }
+
+    #[test]
+    fn test_different_error_resets_backoff() {
+        let policy = CooldownPolicy::default();
+        let now = Utc::now();
+
+        let first = policy.on_failure(None, "E1".to_string(), now);
+        let second = policy.on_failure(Some(first), "E1".to_string(), now);
+        let other = policy.on_failure(Some(second), "E2".to_string(), now);
+        assert_eq!(other.consecutive_failures, 1);
+        assert_eq!(other.suppressed_until, now + Duration::seconds(10));
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = CooldownPolicy::default();
+        assert_eq!(policy.failure_cooldown(100), Duration::hours(1));
+    }
+
+    #[test]
+    fn test_suppression_state() {
+        let policy = CooldownPolicy::default();
+        let now = Utc::now();
+
+        assert_eq!(
+            policy.suppression(None, now),
+            Suppression::Allowed {
+                dedup_window: Duration::seconds(10)
+            }
+        );
+
+        let failed = policy.on_failure(None, "E".to_string(), now);
+        match policy.suppression(Some(&failed), now) {
+            Suppression::Suppressed { reason, .. } => assert_eq!(
+                reason,
+                SuppressionReason {
+                    error_class: "E".to_string(),
+                    consecutive_failures: 1
+                }
+            ),
+            other => panic!("Expected suppression, got {:?}", other),
+        }
+
+        let succeeded = policy.on_success(now);
+        assert_eq!(
+            policy.suppression(Some(&succeeded), now),
+            Suppression::Allowed {
+                dedup_window: Duration::seconds(2)
+            }
+        );
+    }
+}
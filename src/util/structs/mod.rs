@@ -1,6 +1,9 @@
+pub mod abort_on_drop;
 pub mod buffered_debouncer;
 pub mod cache_type;
+pub mod cooldown;
 pub mod lazy_arc;
 pub mod mint_pair;
 pub mod rate_limiter;
+pub mod stream_dedup;
 pub mod tx_dedup;
@@ -1,7 +1,8 @@
+use crate::util::metrics_registry::METRICS_REGISTRY;
 use parking_lot::RwLock;
 use std::error::Error;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
 
 pub struct RateLimiterConfig {
@@ -9,6 +10,16 @@ pub struct RateLimiterConfig {
     pub window_duration: Duration,
     pub burst_capacity: u32,
     pub name: String,
+    pub adaptive: Option<AdaptiveConfig>,
+}
+
+// AIMD: each throttle cuts the rate by `decrease_factor`, and every window without one
+// adds `increase_per_window` back, never leaving `min_requests..=max_requests`.
+#[derive(Debug, Clone)]
+pub struct AdaptiveConfig {
+    pub min_requests: u32,
+    pub decrease_factor: f64,
+    pub increase_per_window: f64,
 }
 
 pub struct RateLimiter {
@@ -21,6 +32,10 @@ struct RateLimiterInner {
     last_refill: Instant,
     accepted_count: u64,
     rejected_count: u64,
+    // Requests per window currently allowed, below `max_requests` after throttling.
+    current_rate: f64,
+    last_throttled: Option<Instant>,
+    throttled_count: u64,
 }
 
 impl RateLimiter {
@@ -35,6 +50,7 @@ impl RateLimiter {
             window_duration,
             burst_capacity,
             name,
+            adaptive: None,
         };
         Self::from_config(config)
     }
@@ -45,11 +61,47 @@ impl RateLimiter {
             last_refill: Instant::now(),
             accepted_count: 0,
             rejected_count: 0,
+            current_rate: config.max_requests as f64,
+            last_throttled: None,
+            throttled_count: 0,
         }));
+        register_metrics(Arc::downgrade(&inner), config.name.clone());
 
         Self { inner, config }
     }
 
+    pub fn with_adaptive(mut self, adaptive: AdaptiveConfig) -> Self {
+        self.config.adaptive = Some(adaptive);
+        self
+    }
+
+    // Called when the upstream answered with a rate-limit error. Throttles landing within
+    // one window of the previous cut count as the same event.
+    pub fn on_throttled(&self) {
+        let Some(adaptive) = &self.config.adaptive else {
+            return;
+        };
+        let mut inner = self.inner.write();
+        self.refill_tokens(&mut inner);
+        inner.throttled_count += 1;
+
+        let now = Instant::now();
+        if inner
+            .last_throttled
+            .is_some_and(|at| now.duration_since(at) < self.config.window_duration)
+        {
+            return;
+        }
+        inner.last_throttled = Some(now);
+        inner.current_rate =
+            (inner.current_rate * adaptive.decrease_factor).max(adaptive.min_requests as f64);
+        inner.available_tokens = inner.available_tokens.min(0.0);
+    }
+
+    pub fn current_rate(&self) -> f64 {
+        self.inner.read().current_rate
+    }
+
     pub fn try_acquire(&self) -> bool {
         self.try_acquire_n(1)
     }
@@ -114,24 +166,33 @@ impl RateLimiter {
         inner.last_refill = Instant::now();
         inner.accepted_count = 0;
         inner.rejected_count = 0;
+        inner.current_rate = self.config.max_requests as f64;
+        inner.last_throttled = None;
+        inner.throttled_count = 0;
     }
 
     pub fn metrics(&self) -> RateLimiterMetrics {
-        let inner = self.inner.read();
-        RateLimiterMetrics {
-            available_tokens: inner.available_tokens as u32,
-            accepted_count: inner.accepted_count,
-            rejected_count: inner.rejected_count,
-            name: self.config.name.clone(),
-        }
+        RateLimiterMetrics::of(&self.inner.read(), self.config.name.clone())
     }
 
     fn refill_tokens(&self, inner: &mut RateLimiterInner) {
         let now = Instant::now();
         let elapsed = now.duration_since(inner.last_refill);
+        let window = self.config.window_duration.as_secs_f64();
+
+        if let Some(adaptive) = &self.config.adaptive {
+            // the window right after a throttle does not count towards recovery
+            let recovering_since = inner
+                .last_throttled
+                .map(|at| at + self.config.window_duration)
+                .map_or(inner.last_refill, |at| at.max(inner.last_refill));
+            let recovered = now.saturating_duration_since(recovering_since);
+            let increase = recovered.as_secs_f64() / window * adaptive.increase_per_window;
+            inner.current_rate =
+                (inner.current_rate + increase).min(self.config.max_requests as f64);
+        }
 
-        let refill_rate =
-            self.config.max_requests as f64 / self.config.window_duration.as_secs_f64();
+        let refill_rate = inner.current_rate / window;
         let tokens_to_add = elapsed.as_secs_f64() * refill_rate;
 
         inner.available_tokens =
@@ -147,8 +208,7 @@ impl RateLimiter {
             return Duration::ZERO;
         }
 
-        let refill_rate =
-            self.config.max_requests as f64 / self.config.window_duration.as_secs_f64();
+        let refill_rate = inner.current_rate / self.config.window_duration.as_secs_f64();
         let seconds_to_wait = tokens_needed / refill_rate;
 
         Duration::from_secs_f64(seconds_to_wait + 0.001)
@@ -175,9 +235,57 @@ pub struct RateLimiterMetrics {
     pub available_tokens: u32,
     pub accepted_count: u64,
     pub rejected_count: u64,
+    pub throttled_count: u64,
+    pub current_rate: f64,
     pub name: String,
 }
 
+impl RateLimiterMetrics {
+    fn of(inner: &RateLimiterInner, name: String) -> Self {
+        Self {
+            available_tokens: inner.available_tokens as u32,
+            accepted_count: inner.accepted_count,
+            rejected_count: inner.rejected_count,
+            throttled_count: inner.throttled_count,
+            current_rate: inner.current_rate,
+            name,
+        }
+    }
+}
+
+// Exported until the limiter is dropped.
+fn register_metrics(inner: Weak<RwLock<RateLimiterInner>>, name: String) {
+    METRICS_REGISTRY.register_collector(move || {
+        let Some(inner) = inner.upgrade() else {
+            return false;
+        };
+        let metrics = RateLimiterMetrics::of(&inner.read(), name.clone());
+        let labels = [("limiter", name.as_str())];
+        METRICS_REGISTRY.set_gauge(
+            "rate_limiter_available_tokens",
+            &labels,
+            metrics.available_tokens as f64,
+        );
+        METRICS_REGISTRY.set_gauge("rate_limiter_rate", &labels, metrics.current_rate);
+        METRICS_REGISTRY.set_counter(
+            "rate_limiter_requests_total",
+            &[("limiter", name.as_str()), ("result", "accepted")],
+            metrics.accepted_count,
+        );
+        METRICS_REGISTRY.set_counter(
+            "rate_limiter_requests_total",
+            &[("limiter", name.as_str()), ("result", "rejected")],
+            metrics.rejected_count,
+        );
+        METRICS_REGISTRY.set_counter(
+            "rate_limiter_throttled_total",
+            &labels,
+            metrics.throttled_count,
+        );
+        true
+    });
+}
+
 unsafe impl Send for RateLimiter {}
 unsafe impl Sync for RateLimiter {}
 
@@ -318,6 +426,7 @@ mod tests {
             window_duration: Duration::from_secs(2),
             burst_capacity: 25,
             name: "config_test".to_string(),
+            adaptive: None,
         });
 
         assert!(limiter.try_acquire());
@@ -378,4 +487,35 @@ mod tests {
             panic!("Expected RateLimitError::ExceededLimit");
         }
     }
+
+    #[tokio::test]
+    async fn test_adaptive_backoff_and_recovery() {
+        let limiter = RateLimiter::new(
+            100,
+            Duration::from_millis(100),
+            100,
+            "adaptive_test".to_string(),
+        )
+        .with_adaptive(AdaptiveConfig {
+            min_requests: 10,
+            decrease_factor: 0.5,
+            increase_per_window: 20.0,
+        });
+
+        limiter.on_throttled();
+        // a second 429 from the same burst does not cut the rate again
+        limiter.on_throttled();
+        assert_eq!(limiter.current_rate(), 50.0);
+        assert!(!limiter.try_acquire());
+        assert_eq!(limiter.metrics().throttled_count, 2);
+
+        sleep(Duration::from_millis(150)).await;
+        limiter.on_throttled();
+        assert!(limiter.current_rate() < 50.0);
+
+        sleep(Duration::from_millis(300)).await;
+        assert!(limiter.try_acquire());
+        assert!(limiter.current_rate() > 25.0);
+        assert!(limiter.current_rate() <= 100.0);
+    }
 }
@@ -0,0 +1,77 @@
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const CLEANUP_EVERY: u64 = 4096;
+
+// Lets the first copy of a message through when several streams deliver the same
+// data. Keys are forgotten after `ttl`, which must outlast the delay between the
+// fastest and the slowest stream.
+pub struct StreamDeduplicator<K: Hash + Eq> {
+    seen: DashMap<K, Instant>,
+    ttl: Duration,
+    inserted: AtomicU64,
+}
+
+impl<K: Hash + Eq + Clone> StreamDeduplicator<K> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            seen: DashMap::new(),
+            ttl,
+            inserted: AtomicU64::new(0),
+        }
+    }
+
+    pub fn first_seen(&self, key: K) -> bool {
+        let now = Instant::now();
+        let first = match self.seen.entry(key) {
+            Entry::Occupied(mut entry) => {
+                if now.duration_since(*entry.get()) < self.ttl {
+                    false
+                } else {
+                    entry.insert(now);
+                    true
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        };
+
+        if first
+            && self
+                .inserted
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(CLEANUP_EVERY)
+        {
+            self.seen
+                .retain(|_, seen_at| now.duration_since(*seen_at) < self.ttl);
+        }
+        first
+    }
+
+    pub fn size(&self) -> usize {
+        self.seen.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_copy_wins() {
+        let dedup = StreamDeduplicator::new(Duration::from_millis(100));
+
+        assert!(dedup.first_seen(("a", 1u64)));
+        assert!(!dedup.first_seen(("a", 1u64)));
+        assert!(dedup.first_seen(("a", 2u64)));
+        assert!(dedup.first_seen(("b", 1u64)));
+
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(dedup.first_seen(("a", 1u64)));
+    }
+}
@@ -46,6 +46,10 @@ impl TxDeduplicator {
     }
 
     pub fn can_send(&self, minor_mint: &Pubkey, pools: &[Pubkey]) -> bool {
+        self.can_send_within(minor_mint, pools, self.backoff_duration)
+    }
+
+    pub fn can_send_within(&self, minor_mint: &Pubkey, pools: &[Pubkey], window: Duration) -> bool {
         let key = TxKey::new(minor_mint, pools);
         let now = Instant::now();
 
@@ -55,7 +59,7 @@ impl TxDeduplicator {
             .retain(|_, &mut last_sent| now.duration_since(last_sent) < self.backoff_duration * 2);
 
         if let Some(&last_sent) = entries.get(&key) {
-            if now.duration_since(last_sent) < self.backoff_duration {
+            if now.duration_since(last_sent) < window {
                 return false;
             }
         }
@@ -65,13 +69,17 @@ impl TxDeduplicator {
     }
 
     pub fn check_without_marking(&self, minor_mint: &Pubkey, pools: &[Pubkey]) -> bool {
+        self.check_within(minor_mint, pools, self.backoff_duration)
+    }
+
+    pub fn check_within(&self, minor_mint: &Pubkey, pools: &[Pubkey], window: Duration) -> bool {
         let key = TxKey::new(minor_mint, pools);
         let now = Instant::now();
 
         let entries = self.entries.read();
 
         if let Some(&last_sent) = entries.get(&key) {
-            now.duration_since(last_sent) >= self.backoff_duration
+            now.duration_since(last_sent) >= window
         } else {
             true
         }
@@ -162,6 +170,21 @@ mod tests {
         assert!(dedup.can_send(&mint, &pools2));
     }
 
+    #[test]
+    fn test_custom_window() {
+        let dedup = TxDeduplicator::new(Duration::from_millis(500));
+
+        let mint = new_unique_pubkey();
+        let pools = vec![new_unique_pubkey()];
+
+        assert!(dedup.can_send_within(&mint, &pools, Duration::from_millis(50)));
+        assert!(!dedup.can_send_within(&mint, &pools, Duration::from_millis(50)));
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert!(dedup.can_send_within(&mint, &pools, Duration::from_millis(50)));
+        assert!(!dedup.can_send(&mint, &pools));
+    }
+
     #[test]
     fn test_check_without_marking() {
         let dedup = TxDeduplicator::new(Duration::from_millis(100));
@@ -175,4 +198,20 @@ mod tests {
         dedup.mark_sent(&mint, &pools);
         assert!(!dedup.check_without_marking(&mint, &pools));
     }
+
+    #[test]
+    fn test_check_within() {
+        let dedup = TxDeduplicator::new(Duration::from_millis(500));
+
+        let mint = new_unique_pubkey();
+        let pools = vec![new_unique_pubkey()];
+
+        assert!(dedup.check_within(&mint, &pools, Duration::from_millis(50)));
+        assert!(dedup.can_send_within(&mint, &pools, Duration::from_millis(50)));
+        assert!(!dedup.check_within(&mint, &pools, Duration::from_millis(50)));
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert!(dedup.check_within(&mint, &pools, Duration::from_millis(50)));
+        assert!(!dedup.check_without_marking(&mint, &pools));
+    }
 }
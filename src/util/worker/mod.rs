@@ -1 +1,3 @@
+pub mod priority_pubsub;
 pub mod pubsub;
+pub mod sharded_pubsub;
@@ -0,0 +1,322 @@
+use crate::util::metrics_registry::{ProcessorMetrics, METRICS_REGISTRY};
+use crate::util::structs::rate_limiter::RateLimiter;
+use crate::util::worker::pubsub::{drain_with, DrainOutcome, PubSubConfig};
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+const TOKEN_WAIT: Duration = Duration::from_secs(1);
+
+pub trait Prioritized {
+    // Higher goes first; equal priorities go oldest first.
+    fn priority(&self) -> u64;
+    // Checked when the message is taken off the queue, expired ones are never processed.
+    fn is_expired(&self) -> bool;
+    // Same, for messages whose work is already covered, by a newer message or a
+    // recent duplicate.
+    fn is_superseded(&self) -> bool {
+        false
+    }
+}
+
+struct PriorityQueue<T> {
+    entries: BTreeMap<(u64, Reverse<u64>), T>,
+    sequence: u64,
+    capacity: usize,
+}
+
+impl<T: Prioritized> PriorityQueue<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            sequence: 0,
+            capacity: capacity.max(1),
+        }
+    }
+
+    // Returns false when the queue was full and the new message or a worse one was dropped.
+    fn push(&mut self, message: T) -> bool {
+        self.sequence += 1;
+        self.entries
+            .insert((message.priority(), Reverse(self.sequence)), message);
+        if self.entries.len() <= self.capacity {
+            return true;
+        }
+        self.entries.pop_first();
+        false
+    }
+
+    // The returned message is counted as dequeued once its worker calls `on_started`.
+    fn pop_best(&mut self, metrics: &ProcessorMetrics) -> Option<T> {
+        while let Some((_, message)) = self.entries.pop_last() {
+            let reason = if message.is_expired() {
+                "expired"
+            } else if message.is_superseded() {
+                "superseded"
+            } else {
+                return Some(message);
+            };
+            metrics.on_dequeue();
+            metrics.on_drop(reason);
+        }
+        None
+    }
+}
+
+// Like `PubSubProcessor`, but workers always take the highest priority message. With a
+// rate limiter, a worker holds a token before it picks, so the budget goes to the best
+// messages queued at that moment. Only messages rejected by `is_expired` or
+// `is_superseded` are dropped without using up the token, so any check that would make
+// the processor skip a message belongs there. `channel_buffer_size` bounds the queue.
+pub struct PriorityPubSubProcessor<T: Prioritized + Send + 'static> {
+    queue: Arc<Mutex<PriorityQueue<T>>>,
+    notify: Arc<Notify>,
+    metrics: Arc<ProcessorMetrics>,
+    worker_handles: Vec<JoinHandle<()>>,
+    closed: AtomicBool,
+    name: String,
+}
+
+impl<T: Prioritized + Send + 'static> PriorityPubSubProcessor<T> {
+    pub fn new<F, Fut>(
+        config: PubSubConfig,
+        rate_limiter: Option<Arc<RateLimiter>>,
+        processor: F,
+    ) -> Self
+    where
+        F: Fn(T) -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+    {
+        let queue = Arc::new(Mutex::new(PriorityQueue::new(config.channel_buffer_size)));
+        let notify = Arc::new(Notify::new());
+        let metrics = METRICS_REGISTRY.register_processor(&config.name, config.channel_buffer_size);
+
+        let mut worker_handles = Vec::with_capacity(config.worker_pool_size);
+        for worker_id in 0..config.worker_pool_size {
+            let queue = queue.clone();
+            let notify = notify.clone();
+            let metrics = metrics.clone();
+            let rate_limiter = rate_limiter.clone();
+            let processor_clone = processor.clone();
+            let name_clone = config.name.clone();
+
+            let handle = tokio::spawn(async move {
+                info!("[{}] Worker {} started", name_clone, worker_id);
+                let mut holding_token = false;
+
+                loop {
+                    if queue.lock().entries.is_empty() {
+                        notify.notified().await;
+                        continue;
+                    }
+                    if let Some(limiter) = &rate_limiter {
+                        if !holding_token {
+                            holding_token = limiter.acquire(TOKEN_WAIT).await;
+                            if !holding_token {
+                                continue;
+                            }
+                        }
+                    }
+                    // another worker may have emptied the queue meanwhile, keep the token
+                    let Some(msg) = queue.lock().pop_best(&metrics) else {
+                        continue;
+                    };
+                    holding_token = false;
+
+                    metrics.on_started();
+                    let started = Instant::now();
+                    let result = processor_clone(msg).await;
+                    metrics.on_handled(started.elapsed(), result.is_err());
+                    if let Err(e) = result {
+                        error!(
+                            "[{}] Worker {} failed to process message: {}",
+                            name_clone, worker_id, e
+                        );
+                    }
+                }
+            });
+
+            worker_handles.push(handle);
+        }
+
+        info!(
+            "[{}] Priority processor initialized with {} workers",
+            config.name, config.worker_pool_size
+        );
+
+        Self {
+            queue,
+            notify,
+            metrics,
+            worker_handles,
+            closed: AtomicBool::new(false),
+            name: config.name,
+        }
+    }
+
+    pub fn publish(&self, message: T) {
+        if self.closed.load(Ordering::Relaxed) {
+            self.metrics.on_drop("draining");
+            return;
+        }
+        self.metrics.on_enqueue();
+        if !self.queue.lock().push(message) {
+            // the evicted message leaves the queue without being processed
+            self.metrics.on_dequeue();
+            self.metrics.on_drop("overflow");
+        }
+        self.notify.notify_one();
+    }
+
+    // Queued messages still wait for rate limiter tokens, expired and superseded ones are dropped.
+    pub async fn drain(&self, timeout: Duration) -> DrainOutcome {
+        drain_with(&self.closed, &self.metrics, timeout).await
+    }
+
+    pub fn depth(&self) -> usize {
+        self.queue.lock().entries.len()
+    }
+
+    pub async fn shutdown(self) {
+        info!("[{}] Shutting down processor...", self.name);
+        for handle in self.worker_handles {
+            handle.abort();
+        }
+        info!(
+            "[{}] Processor shutdown complete, {} messages left unprocessed",
+            self.name,
+            self.queue.lock().entries.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::metrics_registry::MetricsRegistry;
+
+    struct Job {
+        id: u32,
+        priority: u64,
+        expired: bool,
+        superseded: bool,
+    }
+
+    impl Prioritized for Job {
+        fn priority(&self) -> u64 {
+            self.priority
+        }
+
+        fn is_expired(&self) -> bool {
+            self.expired
+        }
+
+        fn is_superseded(&self) -> bool {
+            self.superseded
+        }
+    }
+
+    fn job(id: u32, priority: u64, expired: bool) -> Job {
+        Job {
+            id,
+            priority,
+            expired,
+            superseded: false,
+        }
+    }
+
+    #[test]
+    fn test_pops_by_priority_then_age_and_drops_expired() {
+        let metrics = MetricsRegistry::default().register_processor("TestPriorityQueue", 3);
+        let mut queue = PriorityQueue::new(3);
+
+        assert!(queue.push(job(1, 10, false)));
+        assert!(queue.push(job(2, 50, true)));
+        assert!(queue.push(job(3, 10, false)));
+        // full: the newest of the lowest priority is evicted
+        assert!(!queue.push(job(4, 20, false)));
+
+        let order: Vec<u32> = std::iter::from_fn(|| queue.pop_best(&metrics))
+            .map(|job| job.id)
+            .collect();
+        assert_eq!(order, vec![4, 1]);
+        assert_eq!(metrics.snapshot().drops["expired"], 1);
+    }
+
+    #[test]
+    fn test_drops_superseded_before_picking() {
+        let metrics = MetricsRegistry::default().register_processor("TestPriorityQueue", 3);
+        let mut queue = PriorityQueue::new(3);
+
+        assert!(queue.push(Job {
+            superseded: true,
+            ..job(1, 50, false)
+        }));
+        assert!(queue.push(job(2, 10, false)));
+
+        assert_eq!(queue.pop_best(&metrics).map(|job| job.id), Some(2));
+        assert_eq!(metrics.snapshot().drops["superseded"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_drain_skips_expired_and_refuses_new_messages() {
+        let config = PubSubConfig {
+            worker_pool_size: 2,
+            channel_buffer_size: 16,
+            name: "TestPriorityDrainProcessor".to_string(),
+        };
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let handled_clone = handled.clone();
+        let processor = PriorityPubSubProcessor::new(config, None, move |job: Job| {
+            let handled = handled_clone.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                handled.lock().push(job.id);
+                Ok(())
+            }
+        });
+
+        for id in 0..6 {
+            processor.publish(job(id, id as u64, id == 3));
+        }
+        let outcome = processor.drain(Duration::from_secs(5)).await;
+        assert!(outcome.drained());
+        assert_eq!(processor.depth(), 0);
+
+        processor.publish(job(7, 100, false));
+        assert_eq!(processor.depth(), 0);
+        assert_eq!(processor.metrics.snapshot().drops["draining"], 1);
+
+        let mut handled = handled.lock().clone();
+        handled.sort();
+        assert_eq!(handled, vec![0, 1, 2, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_drain_gives_up_after_timeout() {
+        let config = PubSubConfig {
+            worker_pool_size: 1,
+            channel_buffer_size: 16,
+            name: "TestPriorityDrainTimeoutProcessor".to_string(),
+        };
+        let processor = PriorityPubSubProcessor::new(config, None, |_: Job| async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok(())
+        });
+
+        for id in 0..3 {
+            processor.publish(job(id, 1, false));
+        }
+        let outcome = processor.drain(Duration::from_millis(50)).await;
+        assert!(!outcome.drained());
+        assert_eq!(outcome.pending, 3);
+        processor.shutdown().await;
+    }
+}
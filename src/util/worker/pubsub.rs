@@ -1,9 +1,14 @@
+use crate::util::metrics_registry::{ProcessorMetrics, METRICS_REGISTRY};
 use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, Mutex};
 use tokio::task::JoinHandle;
 use tracing::{error, info};
 
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 // Configuration for the pub/sub system
 pub struct PubSubConfig {
     pub worker_pool_size: usize,
@@ -21,10 +26,26 @@ impl Default for PubSubConfig {
     }
 }
 
+// What was left of a processor when draining stopped.
+#[derive(Debug, Clone)]
+pub struct DrainOutcome {
+    pub name: String,
+    pub pending: u64,
+    pub waited: Duration,
+}
+
+impl DrainOutcome {
+    pub fn drained(&self) -> bool {
+        self.pending == 0
+    }
+}
+
 // Generic pub/sub processor that can handle any message type
 pub struct PubSubProcessor<T: Send + 'static> {
     tx_sender: mpsc::Sender<T>,
     worker_handles: Vec<JoinHandle<()>>,
+    metrics: Arc<ProcessorMetrics>,
+    closed: AtomicBool,
     name: String,
 }
 
@@ -43,12 +64,14 @@ impl<T: Send + 'static> PubSubProcessor<T> {
     {
         let (tx_sender, tx_receiver) = mpsc::channel::<T>(config.channel_buffer_size);
         let rx = Arc::new(Mutex::new(tx_receiver));
+        let metrics = METRICS_REGISTRY.register_processor(&config.name, config.channel_buffer_size);
         let mut worker_handles = Vec::with_capacity(config.worker_pool_size);
 
         for worker_id in 0..config.worker_pool_size {
             let rx_clone = Arc::clone(&rx);
             let processor_clone = processor.clone();
             let name_clone = config.name.clone();
+            let metrics = metrics.clone();
 
             let handle = tokio::spawn(async move {
                 info!("[{}] Worker {} started", name_clone, worker_id);
@@ -61,7 +84,11 @@ impl<T: Send + 'static> PubSubProcessor<T> {
 
                     match message {
                         Some(msg) => {
-                            if let Err(e) = processor_clone(msg).await {
+                            metrics.on_started();
+                            let started = Instant::now();
+                            let result = processor_clone(msg).await;
+                            metrics.on_handled(started.elapsed(), result.is_err());
+                            if let Err(e) = result {
                                 error!(
                                     "[{}] Worker {} failed to process message: {}",
                                     name_clone, worker_id, e
@@ -90,27 +117,26 @@ impl<T: Send + 'static> PubSubProcessor<T> {
         Self {
             tx_sender,
             worker_handles,
+            metrics,
+            closed: AtomicBool::new(false),
             name: config.name,
         }
     }
 
+    // Waits for room when the buffer is full; how often and how long is recorded.
     pub async fn publish(&self, message: T) -> Result<()> {
-        self.tx_sender
-            .send(message)
-            .await
-            .map_err(|e| anyhow::anyhow!("[{}] Failed to send message: {}", self.name, e))?;
-        Ok(())
+        reject_if_closed(&self.closed, &self.metrics)?;
+        publish_to(&self.tx_sender, &self.metrics, &self.name, message).await
     }
 
     pub fn try_publish(&self, message: T) -> Result<()> {
-        self.tx_sender.try_send(message).map_err(|e| match e {
-            mpsc::error::TrySendError::Full(_) => {
-                anyhow::anyhow!("[{}] Channel buffer is full, message dropped", self.name)
-            }
-            mpsc::error::TrySendError::Closed(_) => {
-                anyhow::anyhow!("[{}] Channel is closed", self.name)
-            }
-        })
+        reject_if_closed(&self.closed, &self.metrics)?;
+        try_publish_to(&self.tx_sender, &self.metrics, &self.name, message)
+    }
+
+    // Unlike `shutdown`, works through the shared handle the statics hand out.
+    pub async fn drain(&self, timeout: Duration) -> DrainOutcome {
+        drain_with(&self.closed, &self.metrics, timeout).await
     }
 
     pub async fn shutdown(self) {
@@ -127,6 +153,90 @@ impl<T: Send + 'static> PubSubProcessor<T> {
     }
 }
 
+pub(super) fn reject_if_closed(closed: &AtomicBool, metrics: &ProcessorMetrics) -> Result<()> {
+    if closed.load(Ordering::Relaxed) {
+        metrics.on_drop("draining");
+        return Err(anyhow::anyhow!(
+            "[{}] Processor is draining",
+            metrics.name()
+        ));
+    }
+    Ok(())
+}
+
+// Refuses new messages, then waits until everything accepted so far has been handled.
+pub(super) async fn drain_with(
+    closed: &AtomicBool,
+    metrics: &ProcessorMetrics,
+    timeout: Duration,
+) -> DrainOutcome {
+    closed.store(true, Ordering::Relaxed);
+    let started = Instant::now();
+    while !metrics.is_idle() && started.elapsed() < timeout {
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+    DrainOutcome {
+        name: metrics.name().to_string(),
+        pending: metrics.pending(),
+        waited: started.elapsed(),
+    }
+}
+
+pub(super) async fn publish_to<T>(
+    sender: &mpsc::Sender<T>,
+    metrics: &ProcessorMetrics,
+    name: &str,
+    message: T,
+) -> Result<()> {
+    metrics.on_enqueue();
+    let message = match sender.try_send(message) {
+        Ok(()) => return Ok(()),
+        Err(mpsc::error::TrySendError::Full(message)) => message,
+        Err(mpsc::error::TrySendError::Closed(_)) => {
+            metrics.on_dequeue();
+            metrics.on_drop("closed");
+            return Err(anyhow::anyhow!("[{}] Channel is closed", name));
+        }
+    };
+
+    let started = Instant::now();
+    let result = sender.send(message).await;
+    metrics.on_blocked(started.elapsed());
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            metrics.on_dequeue();
+            metrics.on_drop("closed");
+            Err(anyhow::anyhow!("[{}] Failed to send message: {}", name, e))
+        }
+    }
+}
+
+pub(super) fn try_publish_to<T>(
+    sender: &mpsc::Sender<T>,
+    metrics: &ProcessorMetrics,
+    name: &str,
+    message: T,
+) -> Result<()> {
+    metrics.on_enqueue();
+    match sender.try_send(message) {
+        Ok(()) => Ok(()),
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            metrics.on_dequeue();
+            metrics.on_drop("full");
+            Err(anyhow::anyhow!(
+                "[{}] Channel buffer is full, message dropped",
+                name
+            ))
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => {
+            metrics.on_dequeue();
+            metrics.on_drop("closed");
+            Err(anyhow::anyhow!("[{}] Channel is closed", name))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -163,4 +273,25 @@ mod tests {
 
         processor.shutdown().await;
     }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_handlers_and_refuses_new_messages() {
+        let config = PubSubConfig {
+            worker_pool_size: 2,
+            channel_buffer_size: 50,
+            name: "TestDrainProcessor".to_string(),
+        };
+        let processor = PubSubProcessor::new(config, |_: u32| async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(())
+        });
+
+        for i in 0..6 {
+            processor.publish(i).await.unwrap();
+        }
+        let outcome = processor.drain(Duration::from_secs(5)).await;
+        assert!(outcome.drained());
+        assert!(outcome.waited >= Duration::from_millis(50));
+        assert!(processor.try_publish(7).is_err());
+    }
 }
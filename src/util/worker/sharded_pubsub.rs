@@ -0,0 +1,220 @@
+use crate::util::metrics_registry::{ProcessorMetrics, METRICS_REGISTRY};
+use crate::util::worker::pubsub::{
+    drain_with, publish_to, reject_if_closed, try_publish_to, DrainOutcome, PubSubConfig,
+};
+use anyhow::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+type KeyFn<T, K> = Arc<dyn Fn(&T) -> K + Send + Sync>;
+
+// Like `PubSubProcessor`, but every message is routed by its key to a shard owned by a
+// single worker, so messages with the same key are processed one at a time and in the
+// order they were published. `worker_pool_size` is the number of shards and
+// `channel_buffer_size` is split between them.
+pub struct ShardedPubSubProcessor<K: Hash, T: Send + 'static> {
+    shard_senders: Vec<mpsc::Sender<T>>,
+    worker_handles: Vec<JoinHandle<()>>,
+    key_of: KeyFn<T, K>,
+    metrics: Arc<ProcessorMetrics>,
+    closed: AtomicBool,
+    name: String,
+}
+
+impl<K: Hash, T: Send + 'static> ShardedPubSubProcessor<K, T> {
+    pub fn new<KF, F, Fut>(config: PubSubConfig, key_of: KF, processor: F) -> Self
+    where
+        KF: Fn(&T) -> K + Send + Sync + 'static,
+        F: Fn(T) -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+    {
+        let shard_count = config.worker_pool_size.max(1);
+        let buffer_size = (config.channel_buffer_size / shard_count).max(1);
+        let metrics = METRICS_REGISTRY.register_processor(&config.name, buffer_size * shard_count);
+        let mut shard_senders = Vec::with_capacity(shard_count);
+        let mut worker_handles = Vec::with_capacity(shard_count);
+
+        for shard_id in 0..shard_count {
+            let (sender, mut receiver) = mpsc::channel::<T>(buffer_size);
+            let processor_clone = processor.clone();
+            let name_clone = config.name.clone();
+            let metrics = metrics.clone();
+
+            let handle = tokio::spawn(async move {
+                info!("[{}] Shard {} started", name_clone, shard_id);
+
+                while let Some(msg) = receiver.recv().await {
+                    metrics.on_started();
+                    let started = Instant::now();
+                    let result = processor_clone(msg).await;
+                    metrics.on_handled(started.elapsed(), result.is_err());
+                    if let Err(e) = result {
+                        error!(
+                            "[{}] Shard {} failed to process message: {}",
+                            name_clone, shard_id, e
+                        );
+                    }
+                }
+                info!(
+                    "[{}] Shard {} shutting down - channel closed",
+                    name_clone, shard_id
+                );
+            });
+
+            shard_senders.push(sender);
+            worker_handles.push(handle);
+        }
+
+        info!(
+            "[{}] Processor initialized with {} shards",
+            config.name, shard_count
+        );
+
+        Self {
+            shard_senders,
+            worker_handles,
+            key_of: Arc::new(key_of),
+            metrics,
+            closed: AtomicBool::new(false),
+            name: config.name,
+        }
+    }
+
+    fn shard_of(&self, message: &T) -> usize {
+        let mut hasher = DefaultHasher::new();
+        (self.key_of)(message).hash(&mut hasher);
+        (hasher.finish() % self.shard_senders.len() as u64) as usize
+    }
+
+    pub async fn publish(&self, message: T) -> Result<()> {
+        reject_if_closed(&self.closed, &self.metrics)?;
+        let shard = self.shard_of(&message);
+        publish_to(
+            &self.shard_senders[shard],
+            &self.metrics,
+            &self.name,
+            message,
+        )
+        .await
+    }
+
+    pub fn try_publish(&self, message: T) -> Result<()> {
+        reject_if_closed(&self.closed, &self.metrics)?;
+        let shard = self.shard_of(&message);
+        try_publish_to(
+            &self.shard_senders[shard],
+            &self.metrics,
+            &self.name,
+            message,
+        )
+    }
+
+    pub async fn drain(&self, timeout: Duration) -> DrainOutcome {
+        drain_with(&self.closed, &self.metrics, timeout).await
+    }
+
+    pub async fn shutdown(self) {
+        info!("[{}] Shutting down processor...", self.name);
+        drop(self.shard_senders);
+
+        for (idx, handle) in self.worker_handles.into_iter().enumerate() {
+            if let Err(e) = handle.await {
+                error!("[{}] Shard {} failed to join: {}", self.name, idx, e);
+            }
+        }
+
+        info!("[{}] Processor shutdown complete", self.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_keeps_per_key_order() {
+        let config = PubSubConfig {
+            worker_pool_size: 4,
+            channel_buffer_size: 400,
+            name: "TestShardedProcessor".to_string(),
+        };
+
+        let seen: Arc<Mutex<HashMap<u32, Vec<u32>>>> = Arc::default();
+        let seen_clone = seen.clone();
+
+        let processor = ShardedPubSubProcessor::new(
+            config,
+            |(key, _): &(u32, u32)| *key,
+            move |(key, seq): (u32, u32)| {
+                let seen = seen_clone.clone();
+                async move {
+                    // later messages finish faster, which would reorder a shared pool
+                    tokio::time::sleep(tokio::time::Duration::from_millis(
+                        (10 - seq as u64 % 10) / 2,
+                    ))
+                    .await;
+                    seen.lock().await.entry(key).or_default().push(seq);
+                    Ok(())
+                }
+            },
+        );
+
+        for seq in 0..20 {
+            for key in 0..8 {
+                processor.publish((key, seq)).await.unwrap();
+            }
+        }
+        processor.shutdown().await;
+
+        let seen = seen.lock().await;
+        for key in 0..8 {
+            assert_eq!(seen[&key], (0..20).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drain_empties_every_shard_and_refuses_new_messages() {
+        let config = PubSubConfig {
+            worker_pool_size: 4,
+            channel_buffer_size: 400,
+            name: "TestShardedDrainProcessor".to_string(),
+        };
+
+        let handled: Arc<Mutex<Vec<(u32, u32)>>> = Arc::default();
+        let handled_clone = handled.clone();
+
+        let processor = ShardedPubSubProcessor::new(
+            config,
+            |(key, _): &(u32, u32)| *key,
+            move |message: (u32, u32)| {
+                let handled = handled_clone.clone();
+                async move {
+                    tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+                    handled.lock().await.push(message);
+                    Ok(())
+                }
+            },
+        );
+
+        for seq in 0..5 {
+            for key in 0..8 {
+                processor.publish((key, seq)).await.unwrap();
+            }
+        }
+        let outcome = processor.drain(Duration::from_secs(5)).await;
+        assert!(outcome.drained());
+        assert_eq!(handled.lock().await.len(), 40);
+
+        assert!(processor.publish((0, 5)).await.is_err());
+        assert!(processor.try_publish((1, 5)).is_err());
+        assert_eq!(processor.metrics.snapshot().drops["draining"], 2);
+    }
+}